@@ -0,0 +1,75 @@
+use std::path::PathBuf;
+
+use barnacle_lib::{
+    Repository,
+    repository::{DeployKind, Game, snapshot},
+};
+use clap::Subcommand;
+
+use crate::{Result, find_game};
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// List the games along with how they're deployed and their targets
+    List,
+    /// Add a game of the given kind, without targets
+    Add {
+        name: String,
+        /// How the game is deployed, like `gamebryo` or `creation_engine`
+        #[arg(long, default_value = "overlay")]
+        kind: DeployKind,
+    },
+    /// Rename a game
+    Rename { game: String, new_name: String },
+    /// Remove a game along with its profiles and mods
+    Remove { game: String },
+    /// Set the directories a game's mods are deployed to
+    SetTarget {
+        game: String,
+        #[arg(required = true)]
+        targets: Vec<PathBuf>,
+    },
+}
+
+pub fn run(repo: &Repository, command: Command, json: bool) -> Result<()> {
+    match command {
+        Command::List if json => {
+            let games = repo
+                .games()?
+                .iter()
+                .map(Game::snapshot)
+                .collect::<std::result::Result<Vec<_>, _>>()?;
+            println!("{}", snapshot::to_json(&games));
+        }
+        Command::List => {
+            for game in repo.games()? {
+                let targets: Vec<String> = game
+                    .targets()?
+                    .iter()
+                    .map(|target| target.display().to_string())
+                    .collect();
+                println!(
+                    "{}\t{}\t{}\t{}",
+                    game.name()?,
+                    game.deploy_kind()?,
+                    game.deploy_method()?,
+                    targets.join(":")
+                );
+            }
+        }
+        Command::Add { name, kind } => {
+            repo.add_game(&name, kind)?;
+        }
+        Command::Rename { game, new_name } => {
+            find_game(repo, &game)?.set_name(&new_name)?;
+        }
+        Command::Remove { game } => {
+            repo.remove_game(find_game(repo, &game)?)?;
+        }
+        Command::SetTarget { game, targets } => {
+            find_game(repo, &game)?.set_targets(targets)?;
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,124 @@
+//! Command-line frontend for Barnacle, for scripting and headless machines.
+//!
+//! Mod commands act on the current profile, which `barnacle profiles switch` sets.
+
+use std::{io, process::ExitCode};
+
+use barnacle_lib::{
+    Repository,
+    repository::{Game, Profile, entities},
+};
+use clap::{Parser, Subcommand};
+use thiserror::Error;
+use tracing_subscriber::EnvFilter;
+
+mod games;
+mod mods;
+mod profiles;
+mod tools;
+
+type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug, Error)]
+enum Error {
+    #[error(transparent)]
+    Barnacle(#[from] barnacle_lib::Error),
+    #[error(transparent)]
+    Entity(#[from] entities::Error),
+    #[error("No game named `{0}`")]
+    NoGame(String),
+    #[error("No profile named `{0}`")]
+    NoProfile(String),
+    #[error("No mod named `{0}` in the current profile")]
+    NoMod(String),
+    #[error("No tool named `{0}`")]
+    NoTool(String),
+}
+
+#[derive(Debug, Parser)]
+#[command(name = "barnacle", version, about = "A mod manager for Linux")]
+struct Cli {
+    /// Print listings as JSON
+    #[arg(long, global = true)]
+    json: bool,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Manage games
+    Games {
+        #[command(subcommand)]
+        command: games::Command,
+    },
+    /// Manage the profiles of a game
+    Profiles {
+        #[command(subcommand)]
+        command: profiles::Command,
+    },
+    /// Manage the mods of the current profile
+    Mods {
+        #[command(subcommand)]
+        command: mods::Command,
+    },
+    /// Manage and run the tools of a game
+    Tools {
+        #[command(subcommand)]
+        command: tools::Command,
+    },
+}
+
+fn main() -> ExitCode {
+    // Human friendly panicking in release mode
+    human_panic::setup_panic!();
+
+    // Logging goes to stderr, leaving stdout to the output of the commands
+    tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .with_writer(io::stderr)
+        .init();
+
+    let cli = Cli::parse();
+    let repo = Repository::new();
+
+    let result = match cli.command {
+        Command::Games { command } => {
+            games::run(&repo, command, cli.json).map(|()| ExitCode::SUCCESS)
+        }
+        Command::Profiles { command } => {
+            profiles::run(&repo, command, cli.json).map(|()| ExitCode::SUCCESS)
+        }
+        Command::Mods { command } => {
+            mods::run(&repo, command, cli.json).map(|()| ExitCode::SUCCESS)
+        }
+        Command::Tools { command } => tools::run(&repo, command, cli.json),
+    };
+
+    result.unwrap_or_else(|e| {
+        eprintln!("Error: {e}");
+        ExitCode::FAILURE
+    })
+}
+
+/// Find a game by its name.
+fn find_game(repo: &Repository, name: &str) -> Result<Game> {
+    for game in repo.games()? {
+        if game.name()? == name {
+            return Ok(game);
+        }
+    }
+
+    Err(Error::NoGame(name.to_string()))
+}
+
+/// Find a profile of a game by its name.
+fn find_profile(game: &Game, name: &str) -> Result<Profile> {
+    for profile in game.profiles()? {
+        if profile.name()? == name {
+            return Ok(profile);
+        }
+    }
+
+    Err(Error::NoProfile(name.to_string()))
+}
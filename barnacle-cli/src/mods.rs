@@ -0,0 +1,91 @@
+use std::path::PathBuf;
+
+use barnacle_lib::{
+    Repository,
+    repository::{ModEntry, Profile, snapshot},
+};
+use clap::Subcommand;
+
+use crate::{Error, Result};
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// List the mods in load order, marking the enabled ones with `x`
+    List,
+    /// Install a mod from an archive and add it to the end of the list
+    Install {
+        archive: PathBuf,
+        /// Defaults to the archive's name
+        #[arg(long)]
+        name: Option<String>,
+        /// Enable the mod right away
+        #[arg(long)]
+        enable: bool,
+    },
+    /// Remove a mod from the game, along with its files and its entries in every profile
+    Remove { name: String },
+    /// Enable a mod
+    Enable { name: String },
+    /// Disable a mod
+    Disable { name: String },
+    /// Move a mod to the given position in the list, starting at 1. Later mods win file
+    /// conflicts.
+    Move { name: String, position: usize },
+}
+
+pub fn run(repo: &Repository, command: Command, json: bool) -> Result<()> {
+    let mut profile = repo.current_profile()?;
+
+    match command {
+        Command::List if json => {
+            println!("{}", snapshot::to_json(&profile.snapshot()?.mod_entries));
+        }
+        Command::List => {
+            for (i, entry) in profile.mod_entries()?.iter().enumerate() {
+                let marker = if entry.enabled()? { "x" } else { " " };
+                println!("{:>3} [{marker}] {}", i.saturating_add(1), entry.name()?);
+            }
+        }
+        Command::Install {
+            archive,
+            name,
+            enable,
+        } => {
+            let name = name.unwrap_or_else(|| {
+                archive
+                    .file_stem()
+                    .map(|stem| stem.to_string_lossy().into_owned())
+                    .unwrap_or_default()
+            });
+
+            let mod_ = profile.parent()?.add_mod(&name, Some(archive.as_path()))?;
+            let mut entry = profile.add_mod_entry(mod_)?;
+            if enable {
+                entry.set_enabled(true)?;
+            }
+        }
+        Command::Remove { name } => {
+            let entry = find_entry(&profile, &name)?;
+            profile.parent()?.remove_mod(entry.mod_())?;
+        }
+        Command::Enable { name } => find_entry(&profile, &name)?.set_enabled(true)?,
+        Command::Disable { name } => find_entry(&profile, &name)?.set_enabled(false)?,
+        Command::Move { name, position } => {
+            let entry = find_entry(&profile, &name)?;
+            profile.move_mod_entry(&entry, position.saturating_sub(1))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Find the entry of a mod in a profile by the mod's name.
+fn find_entry(profile: &Profile, name: &str) -> Result<ModEntry> {
+    for entry in profile.mod_entries()? {
+        if entry.name()? == name {
+            return Ok(entry);
+        }
+    }
+
+    Err(Error::NoMod(name.to_string()))
+}
@@ -0,0 +1,65 @@
+use barnacle_lib::{
+    Repository,
+    repository::{Profile, snapshot},
+};
+use clap::Subcommand;
+
+use crate::{Result, find_game, find_profile};
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// List the profiles of a game, marking the current one with `*`
+    List { game: String },
+    /// Add an empty profile to a game
+    Add { game: String, name: String },
+    /// Make a profile the current one
+    Switch { game: String, profile: String },
+    /// Add a profile with the same mods and plugin order as an existing one
+    Duplicate {
+        game: String,
+        profile: String,
+        new_name: String,
+    },
+}
+
+pub fn run(repo: &Repository, command: Command, json: bool) -> Result<()> {
+    match command {
+        Command::List { game } if json => {
+            let profiles = find_game(repo, &game)?
+                .profiles()?
+                .iter()
+                .map(Profile::snapshot)
+                .collect::<std::result::Result<Vec<_>, _>>()?;
+            println!("{}", snapshot::to_json(&profiles));
+        }
+        Command::List { game } => {
+            let current = repo.current_profile().ok();
+            for profile in find_game(repo, &game)?.profiles()? {
+                let marker = if current.as_ref() == Some(&profile) {
+                    "*"
+                } else {
+                    " "
+                };
+                println!("{marker} {}", profile.name()?);
+            }
+        }
+        Command::Add { game, name } => {
+            find_game(repo, &game)?.add_profile(&name)?;
+        }
+        Command::Switch { game, profile } => {
+            let profile = find_profile(&find_game(repo, &game)?, &profile)?;
+            repo.set_current_profile(&profile)?;
+        }
+        Command::Duplicate {
+            game,
+            profile,
+            new_name,
+        } => {
+            let mut game = find_game(repo, &game)?;
+            let profile = find_profile(&game, &profile)?;
+            game.duplicate_profile(&profile, &new_name)?;
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,92 @@
+use std::{path::PathBuf, process::ExitCode};
+
+use barnacle_lib::{
+    Repository,
+    repository::{Tool, snapshot},
+};
+use clap::Subcommand;
+
+use crate::{Error, Result, find_game};
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// List the tools of a game
+    List { game: String },
+    /// Add a tool that runs an executable with the given arguments
+    Add {
+        game: String,
+        name: String,
+        path: PathBuf,
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+        args: Vec<String>,
+    },
+    /// Remove a tool
+    Remove { game: String, name: String },
+    /// Run a tool of the current profile's game with the profile deployed, and wait for it to
+    /// exit. Exits with the tool's exit code.
+    Run { name: String },
+}
+
+pub fn run(repo: &Repository, command: Command, json: bool) -> Result<ExitCode> {
+    match command {
+        Command::List { game } if json => {
+            let tools = find_game(repo, &game)?
+                .tools()?
+                .iter()
+                .map(Tool::snapshot)
+                .collect::<std::result::Result<Vec<_>, _>>()?;
+            println!("{}", snapshot::to_json(&tools));
+        }
+        Command::List { game } => {
+            for tool in find_game(repo, &game)?.tools()? {
+                println!(
+                    "{}\t{}\t{}",
+                    tool.name()?,
+                    tool.path()?.display(),
+                    tool.args()?.join(" ")
+                );
+            }
+        }
+        Command::Add {
+            game,
+            name,
+            path,
+            args,
+        } => {
+            let args: Vec<&str> = args.iter().map(String::as_str).collect();
+            find_game(repo, &game)?.add_tool(&name, &path, &args)?;
+        }
+        Command::Remove { game, name } => {
+            let mut game = find_game(repo, &game)?;
+            let tool = find_tool(&game.tools()?, &name)?;
+            game.remove_tool(tool)?;
+        }
+        Command::Run { name } => {
+            let profile = repo.current_profile()?;
+            let tool = find_tool(&profile.parent()?.tools()?, &name)?;
+
+            let launch = tool.launch(&profile)?;
+            let run = launch.run().clone();
+            let status = launch.wait()?;
+            eprintln!("Output logged to {}", run.log_path()?.display());
+
+            return Ok(status
+                .code()
+                .and_then(|code| u8::try_from(code).ok())
+                .map_or(ExitCode::FAILURE, ExitCode::from));
+        }
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Find a tool by its name.
+fn find_tool(tools: &[Tool], name: &str) -> Result<Tool> {
+    for tool in tools {
+        if tool.name()? == name {
+            return Ok(tool.clone());
+        }
+    }
+
+    Err(Error::NoTool(name.to_string()))
+}
@@ -6,7 +6,7 @@ use iced::{
     Task, Theme, application,
     widget::{button, column, row, space, text},
 };
-use tracing::Level;
+use tracing::{Level, error};
 use tracing_subscriber::{EnvFilter, FmtSubscriber};
 
 use crate::components::{
@@ -29,11 +29,16 @@ enum Message {
     LibraryManager(library_manager::Message),
     ShowLibraryManager,
     HideLibraryManager,
+    Launch,
+    LaunchFinished(Result<(), String>),
 }
 
 struct App {
     title: String,
     theme: Theme,
+    repo: Repository,
+    /// Whether a game or tool is running, which has to exit before another can be launched
+    launched: bool,
     // Components
     mod_list: ModList,
     library_manager: LibraryManager,
@@ -64,6 +69,8 @@ impl App {
             Self {
                 title: "Barnacle".into(),
                 theme,
+                repo,
+                launched: false,
                 mod_list,
                 library_manager,
                 show_library_manager: false,
@@ -98,6 +105,27 @@ impl App {
                 self.show_library_manager = false;
                 Task::none()
             }
+            Message::Launch => {
+                self.launched = true;
+                let repo = self.repo.clone();
+
+                Task::perform(
+                    async move {
+                        // Waiting for the game to exit blocks
+                        tokio::task::spawn_blocking(move || launch(&repo))
+                            .await
+                            .unwrap_or_else(|e| Err(e.to_string()))
+                    },
+                    Message::LaunchFinished,
+                )
+            }
+            Message::LaunchFinished(result) => {
+                self.launched = false;
+                if let Err(e) = result {
+                    error!("Failed to launch: {e}");
+                }
+                Task::none()
+            }
         }
     }
 
@@ -107,7 +135,7 @@ impl App {
             // Top bar
             row![
                 text("Game:"),
-                button(icon("play")),
+                button(icon("play")).on_press_maybe((!self.launched).then_some(Message::Launch)),
                 text("Profile:"),
                 space::horizontal(),
                 button(icon("library")).on_press(Message::ShowLibraryManager),
@@ -140,3 +168,21 @@ impl App {
         self.theme.clone()
     }
 }
+
+/// Launch the first tool of the current profile's game, and wait for it to exit.
+fn launch(repo: &Repository) -> Result<(), String> {
+    let profile = repo.current_profile().map_err(|e| e.to_string())?;
+    let tool = profile
+        .parent()
+        .and_then(|game| game.tools())
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .next()
+        .ok_or("The game has no tools to launch")?;
+
+    tool.launch(&profile)
+        .and_then(|launch| launch.wait())
+        .map_err(|e| e.to_string())?;
+
+    Ok(())
+}
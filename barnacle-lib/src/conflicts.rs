@@ -0,0 +1,348 @@
+//! File-level conflict analysis between the mods of a profile.
+//!
+//! Mods are layered in mod entry order, so when several mods provide the same file, the one
+//! that comes last in the list wins and hides the others. Paths are compared case-insensitively,
+//! because the games being modded run on Windows filesystem semantics.
+//!
+//! Bethesda's games also load files from `.bsa` and `.ba2` archives. Archives are loaded along
+//! with the plugin they're named after, so they override each other in plugin load order, and
+//! loose files override every archive.
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use tracing::warn;
+use walkdir::WalkDir;
+
+use crate::{
+    formats::{ba2, bsa},
+    repository::ModEntry,
+};
+
+/// A file that is provided by more than one mod.
+#[derive(Debug, Clone)]
+pub struct FileConflict {
+    /// The path of the file relative to the mod directory, as spelled by the winning mod
+    pub path: PathBuf,
+    /// The mod whose copy of the file is used
+    pub winner: ModEntry,
+    /// The mods whose copies are hidden, highest priority first
+    pub losers: Vec<ModEntry>,
+}
+
+/// Where a mod provides a file from: its directory, or one of its archives.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileSource {
+    pub entry: ModEntry,
+    /// The archive the file is packed in, relative to the mod directory. `None` for loose files.
+    pub archive: Option<PathBuf>,
+}
+
+/// A file that is provided more than once, where at least one of the copies is packed in an
+/// archive.
+#[derive(Debug, Clone)]
+pub struct ArchiveConflict {
+    /// The path of the file inside the archives and mod directories, as spelled by the winner
+    pub path: PathBuf,
+    /// The copy of the file that is used
+    pub winner: FileSource,
+    /// The copies that are hidden, highest priority first
+    pub losers: Vec<FileSource>,
+}
+
+/// The result of analyzing a profile's mods for conflicting files.
+#[derive(Debug, Clone, Default)]
+pub struct Conflicts {
+    files: Vec<FileConflict>,
+    archive_files: Vec<ArchiveConflict>,
+    fully_overridden: Vec<ModEntry>,
+}
+
+impl Conflicts {
+    /// Analyze the given mods, lowest priority first, paired with their directories. The names
+    /// of the enabled plugins, in load order, decide the order of the mods' archives.
+    pub(crate) fn analyze(mods: Vec<(ModEntry, PathBuf)>, plugins: &[String]) -> Self {
+        let file_lists: Vec<Vec<PathBuf>> = mods.iter().map(|(_, dir)| list_files(dir)).collect();
+        let (raw_conflicts, fully_overridden) = index(&file_lists);
+        let archive_files = archive_conflicts(&mods, &file_lists, plugins);
+
+        let entry = |i: usize| {
+            mods.get(i)
+                .map(|(entry, _)| entry.clone())
+                .expect("Indices come from the list of mods")
+        };
+
+        Self {
+            files: raw_conflicts
+                .into_iter()
+                .map(|c| FileConflict {
+                    path: c.path,
+                    winner: entry(c.winner),
+                    losers: c.losers.into_iter().map(entry).collect(),
+                })
+                .collect(),
+            archive_files,
+            fully_overridden: fully_overridden.into_iter().map(entry).collect(),
+        }
+    }
+
+    /// Every file provided by more than one mod or archive, where an archive is involved,
+    /// sorted by path.
+    pub fn archive_files(&self) -> &[ArchiveConflict] {
+        &self.archive_files
+    }
+
+    /// Every file provided by more than one mod, sorted by path.
+    pub fn files(&self) -> &[FileConflict] {
+        &self.files
+    }
+
+    /// The conflicts in which the given mod's files win.
+    pub fn overwrites(&self, entry: &ModEntry) -> Vec<&FileConflict> {
+        self.files.iter().filter(|c| &c.winner == entry).collect()
+    }
+
+    /// The conflicts in which the given mod's files are hidden by another mod.
+    pub fn overwritten_by(&self, entry: &ModEntry) -> Vec<&FileConflict> {
+        self.files
+            .iter()
+            .filter(|c| c.losers.contains(entry))
+            .collect()
+    }
+
+    /// Mods that have every single one of their files hidden by other mods.
+    pub fn fully_overridden(&self) -> &[ModEntry] {
+        &self.fully_overridden
+    }
+}
+
+/// Find the conflicts involving the archives at the top of the mods' directories. Archives are
+/// layered below all loose files, in the load order of their plugins. Archives without an
+/// enabled plugin come first, like the ones the game's ini loads.
+fn archive_conflicts(
+    mods: &[(ModEntry, PathBuf)],
+    loose_files: &[Vec<PathBuf>],
+    plugins: &[String],
+) -> Vec<ArchiveConflict> {
+    let mut archives = Vec::new();
+    for (entry, dir) in mods {
+        for (archive, files) in archives_in(dir) {
+            let rank = archive_rank(&archive, plugins);
+            archives.push((rank, entry, archive, files));
+        }
+    }
+    if archives.is_empty() {
+        return Vec::new();
+    }
+    // Stable, so archives of the same plugin stay in mod order
+    archives.sort_by_key(|(rank, ..)| *rank);
+
+    let mut sources = Vec::new();
+    let mut file_lists = Vec::new();
+    for (_, entry, archive, files) in archives {
+        sources.push(FileSource {
+            entry: entry.clone(),
+            archive: Some(archive),
+        });
+        file_lists.push(files);
+    }
+    let archive_count = sources.len();
+    for ((entry, _), files) in mods.iter().zip(loose_files) {
+        sources.push(FileSource {
+            entry: entry.clone(),
+            archive: None,
+        });
+        file_lists.push(files.clone());
+    }
+
+    let source = |i: usize| {
+        sources
+            .get(i)
+            .cloned()
+            .expect("Indices come from the list of sources")
+    };
+
+    index(&file_lists)
+        .0
+        .into_iter()
+        .filter(|c| c.winner < archive_count || c.losers.iter().any(|i| *i < archive_count))
+        .map(|c| ArchiveConflict {
+            path: c.path,
+            winner: source(c.winner),
+            losers: c.losers.into_iter().map(source).collect(),
+        })
+        .collect()
+}
+
+/// Returns the archives at the top of a directory, relative to it and sorted by name, along
+/// with the files in them. Archives that can't be read are left out.
+fn archives_in(dir: &Path) -> Vec<(PathBuf, Vec<PathBuf>)> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    let mut archives: Vec<(PathBuf, Vec<PathBuf>)> = entries
+        .filter_map(|e| e.ok())
+        .filter_map(|e| {
+            let path = e.path();
+            let extension = path.extension()?.to_string_lossy().to_lowercase();
+            let files = match extension.as_str() {
+                "bsa" => bsa::list(&path),
+                "ba2" => ba2::list(&path),
+                _ => return None,
+            };
+
+            match files {
+                Ok(files) => Some((PathBuf::from(e.file_name()), files)),
+                Err(err) => {
+                    warn!("Failed to read archive {}: {err}", path.display());
+                    None
+                }
+            }
+        })
+        .collect();
+    archives.sort();
+
+    archives
+}
+
+/// Position in the load order of the plugin that loads an archive. That's the plugin with the
+/// same name, which may be followed by a suffix like ` - Textures`.
+fn archive_rank(archive: &Path, plugins: &[String]) -> Option<usize> {
+    let archive = archive.file_stem()?.to_string_lossy().to_lowercase();
+
+    plugins.iter().position(|plugin| {
+        let plugin = Path::new(plugin)
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+
+        archive == plugin
+            || archive
+                .strip_prefix(&plugin)
+                .is_some_and(|suffix| suffix.starts_with(" - "))
+    })
+}
+
+#[derive(Debug, PartialEq)]
+struct RawConflict {
+    path: PathBuf,
+    winner: usize,
+    losers: Vec<usize>,
+}
+
+/// Find the conflicts between lists of relative file paths, lowest priority first. Returns the
+/// conflicts along with the indices of the lists that are hidden completely.
+fn index(file_lists: &[Vec<PathBuf>]) -> (Vec<RawConflict>, Vec<usize>) {
+    // Lowercased path -> indices of the providing lists, in priority order
+    let mut providers: BTreeMap<String, Vec<(usize, &Path)>> = BTreeMap::new();
+
+    for (i, files) in file_lists.iter().enumerate() {
+        for file in files {
+            providers
+                .entry(file.to_string_lossy().to_lowercase())
+                .or_default()
+                .push((i, file));
+        }
+    }
+
+    let mut hidden_counts = vec![0usize; file_lists.len()];
+    let mut conflicts = Vec::new();
+
+    for providers in providers.into_values() {
+        let Some((&(winner, path), losers)) = providers.split_last() else {
+            continue;
+        };
+        if losers.is_empty() {
+            continue;
+        }
+
+        for (loser, _) in losers {
+            if let Some(count) = hidden_counts.get_mut(*loser) {
+                *count = count.saturating_add(1);
+            }
+        }
+
+        conflicts.push(RawConflict {
+            path: path.to_path_buf(),
+            winner,
+            losers: losers.iter().rev().map(|(i, _)| *i).collect(),
+        });
+    }
+
+    let fully_overridden = file_lists
+        .iter()
+        .zip(hidden_counts)
+        .enumerate()
+        .filter(|(_, (files, hidden))| !files.is_empty() && *hidden == files.len())
+        .map(|(i, _)| i)
+        .collect();
+
+    (conflicts, fully_overridden)
+}
+
+/// Returns the paths of every file inside `dir`, relative to `dir`.
+pub(crate) fn list_files(dir: &Path) -> Vec<PathBuf> {
+    WalkDir::new(dir)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| !e.file_type().is_dir())
+        .filter_map(|e| e.path().strip_prefix(dir).ok().map(Path::to_path_buf))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn paths(paths: &[&str]) -> Vec<PathBuf> {
+        paths.iter().map(PathBuf::from).collect()
+    }
+
+    #[test]
+    fn test_index() {
+        let file_lists = [
+            paths(&["textures/rock.dds", "meshes/rock.nif"]),
+            paths(&["Textures/Rock.dds"]),
+            paths(&["textures/rock.dds", "readme.txt"]),
+        ];
+
+        let (conflicts, fully_overridden) = index(&file_lists);
+
+        assert_eq!(
+            conflicts,
+            vec![RawConflict {
+                path: PathBuf::from("textures/rock.dds"),
+                winner: 2,
+                losers: vec![1, 0],
+            }]
+        );
+        // The second mod only provides a single file, which the third one overrides
+        assert_eq!(fully_overridden, vec![1]);
+    }
+
+    #[test]
+    fn test_archive_rank() {
+        let plugins = ["Base.esm".to_string(), "Patch.esp".to_string()];
+
+        assert_eq!(archive_rank(Path::new("Patch.bsa"), &plugins), Some(1));
+        assert_eq!(
+            archive_rank(Path::new("base - textures.ba2"), &plugins),
+            Some(0)
+        );
+        assert_eq!(archive_rank(Path::new("Patches.bsa"), &plugins), None);
+    }
+
+    #[test]
+    fn test_index_no_conflicts() {
+        let file_lists = [paths(&["a.esp"]), Vec::new(), paths(&["b.esp"])];
+
+        let (conflicts, fully_overridden) = index(&file_lists);
+
+        assert!(conflicts.is_empty());
+        assert!(fully_overridden.is_empty());
+    }
+}
@@ -0,0 +1,249 @@
+use std::path::PathBuf;
+
+use crate::{
+    definitions::{Definition, ToolDefinition},
+    deployers::CreationGame,
+    repository::DeployKind,
+};
+
+/// A definition as it's written in the table below
+struct Builtin {
+    id: &'static str,
+    name: &'static str,
+    deploy_kind: DeployKind,
+    data_dir: &'static str,
+    appdata_dirs: &'static [&'static str],
+    /// Names and paths of the executables
+    tools: &'static [(&'static str, &'static str)],
+    /// Patterns of the files that belong in the install directory
+    root_files: &'static [&'static str],
+    implicit_masters: &'static [&'static str],
+    executables: &'static [&'static str],
+    steam_app_ids: &'static [u32],
+    gog_ids: &'static [u64],
+    epic_app_names: &'static [&'static str],
+    lutris_slugs: &'static [&'static str],
+}
+
+const BUILTIN: &[Builtin] = &[
+    Builtin {
+        id: "morrowind",
+        name: "Morrowind",
+        deploy_kind: DeployKind::OpenMW,
+        data_dir: "Data Files",
+        // OpenMW keeps its configuration in `~/.config/openmw` instead
+        appdata_dirs: &[],
+        tools: &[
+            ("OpenMW", "/usr/bin/openmw"),
+            ("OpenMW Launcher", "/usr/bin/openmw-launcher"),
+        ],
+        root_files: &[],
+        implicit_masters: &[],
+        executables: &["Morrowind.exe"],
+        steam_app_ids: &[22320],
+        gog_ids: &[1440163901],
+        epic_app_names: &[],
+        lutris_slugs: &["the-elder-scrolls-iii-morrowind"],
+    },
+    Builtin {
+        id: "oblivion",
+        name: "Oblivion",
+        deploy_kind: DeployKind::Gamebryo,
+        data_dir: "Data",
+        appdata_dirs: &["Oblivion"],
+        tools: &[("Oblivion", "Oblivion.exe"), ("OBSE", "obse_loader.exe")],
+        root_files: &["obse_*"],
+        implicit_masters: &["Oblivion.esm"],
+        executables: &["Oblivion.exe"],
+        steam_app_ids: &[22330],
+        gog_ids: &[1458058109],
+        epic_app_names: &[],
+        lutris_slugs: &["the-elder-scrolls-iv-oblivion"],
+    },
+    Builtin {
+        id: "fallout3",
+        name: "Fallout 3",
+        deploy_kind: DeployKind::Gamebryo,
+        data_dir: "Data",
+        appdata_dirs: &["Fallout3"],
+        tools: &[("Fallout 3", "Fallout3.exe"), ("FOSE", "fose_loader.exe")],
+        root_files: &["fose_*"],
+        implicit_masters: &["Fallout3.esm"],
+        executables: &["Fallout3.exe"],
+        steam_app_ids: &[22300, 22370],
+        gog_ids: &[1454315831],
+        epic_app_names: &["adeae8bbfc94427db57c7dfecce3f1d4"],
+        lutris_slugs: &["fallout-3"],
+    },
+    Builtin {
+        id: "falloutnv",
+        name: "Fallout: New Vegas",
+        deploy_kind: DeployKind::Gamebryo,
+        data_dir: "Data",
+        appdata_dirs: &["FalloutNV"],
+        tools: &[
+            ("Fallout: New Vegas", "FalloutNV.exe"),
+            ("NVSE", "nvse_loader.exe"),
+        ],
+        root_files: &["nvse_*"],
+        implicit_masters: &["FalloutNV.esm"],
+        executables: &["FalloutNV.exe"],
+        steam_app_ids: &[22380],
+        gog_ids: &[1454587428],
+        epic_app_names: &["5daeb974a22a435988892319b3a4f476"],
+        lutris_slugs: &["fallout-new-vegas"],
+    },
+    Builtin {
+        id: "skyrim",
+        name: "Skyrim",
+        deploy_kind: DeployKind::Gamebryo,
+        data_dir: "Data",
+        appdata_dirs: &["Skyrim"],
+        tools: &[("Skyrim", "TESV.exe"), ("SKSE", "skse_loader.exe")],
+        root_files: &["skse_*"],
+        implicit_masters: &["Skyrim.esm", "Update.esm"],
+        executables: &["TESV.exe"],
+        steam_app_ids: &[72850],
+        gog_ids: &[],
+        epic_app_names: &[],
+        lutris_slugs: &["the-elder-scrolls-v-skyrim"],
+    },
+    Builtin {
+        id: "skyrimse",
+        name: "Skyrim Special Edition",
+        deploy_kind: DeployKind::CreationEngine,
+        data_dir: "Data",
+        appdata_dirs: &[
+            "Skyrim Special Edition",
+            "Skyrim Special Edition GOG",
+            "Skyrim Special Edition EPIC",
+        ],
+        tools: &[
+            ("Skyrim Special Edition", "SkyrimSE.exe"),
+            ("SKSE", "skse64_loader.exe"),
+        ],
+        root_files: &["skse64_*"],
+        implicit_masters: CreationGame::SkyrimSE.base_masters(),
+        executables: &["SkyrimSE.exe"],
+        steam_app_ids: &[489830],
+        gog_ids: &[1711230643],
+        epic_app_names: &[],
+        lutris_slugs: &["the-elder-scrolls-v-skyrim-special-edition"],
+    },
+    Builtin {
+        id: "skyrimvr",
+        name: "Skyrim VR",
+        deploy_kind: DeployKind::CreationEngine,
+        data_dir: "Data",
+        appdata_dirs: &["Skyrim VR"],
+        tools: &[("Skyrim VR", "SkyrimVR.exe"), ("SKSE", "sksevr_loader.exe")],
+        root_files: &["sksevr_*"],
+        implicit_masters: CreationGame::SkyrimVR.base_masters(),
+        executables: &["SkyrimVR.exe"],
+        steam_app_ids: &[611670],
+        gog_ids: &[],
+        epic_app_names: &[],
+        lutris_slugs: &["the-elder-scrolls-v-skyrim-vr"],
+    },
+    Builtin {
+        id: "fallout4",
+        name: "Fallout 4",
+        deploy_kind: DeployKind::CreationEngine,
+        data_dir: "Data",
+        appdata_dirs: &["Fallout4"],
+        tools: &[("Fallout 4", "Fallout4.exe"), ("F4SE", "f4se_loader.exe")],
+        root_files: &["f4se_*"],
+        implicit_masters: CreationGame::Fallout4.base_masters(),
+        executables: &["Fallout4.exe"],
+        steam_app_ids: &[377160],
+        gog_ids: &[1998527297],
+        epic_app_names: &[],
+        lutris_slugs: &["fallout-4"],
+    },
+    Builtin {
+        id: "fallout4vr",
+        name: "Fallout 4 VR",
+        deploy_kind: DeployKind::CreationEngine,
+        data_dir: "Data",
+        appdata_dirs: &["Fallout4VR"],
+        tools: &[
+            ("Fallout 4 VR", "Fallout4VR.exe"),
+            ("F4SE", "f4sevr_loader.exe"),
+        ],
+        root_files: &["f4sevr_*"],
+        implicit_masters: &["Fallout4.esm", "Fallout4_VR.esm"],
+        executables: &["Fallout4VR.exe"],
+        steam_app_ids: &[611660],
+        gog_ids: &[],
+        epic_app_names: &[],
+        lutris_slugs: &["fallout-4-vr"],
+    },
+    Builtin {
+        id: "starfield",
+        name: "Starfield",
+        deploy_kind: DeployKind::CreationEngine,
+        data_dir: "Data",
+        appdata_dirs: &["Starfield"],
+        tools: &[("Starfield", "Starfield.exe"), ("SFSE", "sfse_loader.exe")],
+        root_files: &["sfse_*"],
+        implicit_masters: CreationGame::Starfield.base_masters(),
+        executables: &["Starfield.exe"],
+        steam_app_ids: &[1716740],
+        gog_ids: &[],
+        epic_app_names: &[],
+        lutris_slugs: &["starfield"],
+    },
+    Builtin {
+        id: "bg3",
+        name: "Baldur's Gate 3",
+        deploy_kind: DeployKind::BaldursGate3,
+        data_dir: "Data",
+        appdata_dirs: &["Larian Studios/Baldur's Gate 3"],
+        tools: &[
+            ("Baldur's Gate 3", "bin/bg3.exe"),
+            ("Baldur's Gate 3 (DirectX 11)", "bin/bg3_dx11.exe"),
+        ],
+        root_files: &[],
+        implicit_masters: &[],
+        executables: &["bin/bg3.exe"],
+        steam_app_ids: &[1086940],
+        gog_ids: &[1456460669],
+        epic_app_names: &[],
+        lutris_slugs: &["baldurs-gate-3"],
+    },
+];
+
+/// The definitions that ship with Barnacle.
+pub fn builtin() -> Vec<Definition> {
+    BUILTIN.iter().map(Definition::from).collect()
+}
+
+impl From<&Builtin> for Definition {
+    fn from(builtin: &Builtin) -> Self {
+        let strings = |items: &[&str]| items.iter().map(|item| item.to_string()).collect();
+
+        Self {
+            id: builtin.id.to_string(),
+            name: builtin.name.to_string(),
+            deploy_kind: builtin.deploy_kind,
+            data_dir: PathBuf::from(builtin.data_dir),
+            appdata_dirs: builtin.appdata_dirs.iter().map(PathBuf::from).collect(),
+            tools: builtin
+                .tools
+                .iter()
+                .map(|(name, path)| ToolDefinition {
+                    name: name.to_string(),
+                    path: PathBuf::from(path),
+                    args: Vec::new(),
+                })
+                .collect(),
+            root_files: strings(builtin.root_files),
+            implicit_masters: strings(builtin.implicit_masters),
+            executables: builtin.executables.iter().map(PathBuf::from).collect(),
+            steam_app_ids: builtin.steam_app_ids.to_vec(),
+            gog_ids: builtin.gog_ids.to_vec(),
+            epic_app_names: strings(builtin.epic_app_names),
+            lutris_slugs: strings(builtin.lutris_slugs),
+        }
+    }
+}
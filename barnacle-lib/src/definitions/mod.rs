@@ -0,0 +1,264 @@
+//! Definitions of the games Barnacle knows how to mod, used to set up new games and to
+//! recognize installed ones.
+//!
+//! Besides the built-in definitions, users can define games of their own in TOML files in
+//! [`user_dir`].
+
+use std::{
+    io,
+    path::{Component, Path, PathBuf},
+};
+
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::{detect::DetectedGame, repository::DeployKind, runtime::Runtime};
+
+mod builtin;
+mod user;
+
+pub use builtin::builtin;
+pub use user::{load_dir, user_dir};
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("{path}: invalid `{field}`: {message}")]
+    Invalid {
+        path: PathBuf,
+        field: String,
+        message: String,
+    },
+    #[error("{0}: {1}")]
+    Toml(PathBuf, toml::de::Error),
+    #[error("I/O error: {0}")]
+    Io(#[from] io::Error),
+}
+
+/// Everything needed to set up a game, and to recognize its installs.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Definition {
+    /// Short name telling definitions apart, like `skyrimse`
+    pub id: String,
+    pub name: String,
+    pub deploy_kind: DeployKind,
+    /// The directory mods are deployed to, relative to the install directory
+    pub data_dir: PathBuf,
+    /// Candidates for the game's folder in `AppData/Local`, where its plugin lists are kept.
+    /// Different stores' versions of a game can use different folders.
+    pub appdata_dirs: Vec<PathBuf>,
+    /// The game's executables and script extender loaders
+    pub tools: Vec<ToolDefinition>,
+    /// Names of the files at the top of a mod that belong in the install directory instead of
+    /// the data directory, like script extender libraries. `*` matches any number of
+    /// characters, and a trailing `/` matches a directory and everything in it.
+    pub root_files: Vec<String>,
+    /// The masters the game loads on its own, in order
+    pub implicit_masters: Vec<String>,
+    /// Executables whose presence identifies an install of the game, relative to the install
+    /// directory
+    pub executables: Vec<PathBuf>,
+    pub steam_app_ids: Vec<u32>,
+    pub gog_ids: Vec<u64>,
+    /// Epic's app names, as used by Legendary
+    pub epic_app_names: Vec<String>,
+    pub lutris_slugs: Vec<String>,
+}
+
+/// An executable to register as a [`Tool`](crate::repository::Tool).
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ToolDefinition {
+    pub name: String,
+    /// Path of the executable, relative to the install directory
+    pub path: PathBuf,
+    #[serde(default)]
+    pub args: Vec<String>,
+}
+
+impl Definition {
+    /// Returns the AppData folder of the game run through the given runtime: the first
+    /// candidate that exists in the runtime's `AppData/Local`, or the first one if none do.
+    pub fn appdata_dir(&self, runtime: &Runtime) -> Option<&Path> {
+        let local_appdata_dir = runtime.local_appdata_dir();
+
+        self.appdata_dirs
+            .iter()
+            .find(|dir| {
+                local_appdata_dir
+                    .as_ref()
+                    .is_some_and(|local| local.join(dir).is_dir())
+            })
+            .or_else(|| self.appdata_dirs.first())
+            .map(PathBuf::as_path)
+    }
+
+    /// Whether a file in a mod, given relative to the mod's directory, belongs in the install
+    /// directory according to [`Definition::root_files`].
+    pub fn is_root_file(&self, rel_path: &Path) -> bool {
+        is_root_file(&self.root_files, rel_path)
+    }
+}
+
+/// Returns the built-in definitions followed by the valid ones in [`user_dir`], along with
+/// the errors of the invalid ones.
+pub fn all() -> (Vec<Definition>, Vec<Error>) {
+    let mut definitions = builtin();
+    let (user, errors) = load_dir(&user_dir(), &definitions);
+    definitions.extend(user);
+
+    (definitions, errors)
+}
+
+/// Whether a file in a mod, given relative to the mod's directory, matches one of the
+/// [`Definition::root_files`] patterns.
+pub(crate) fn is_root_file(root_files: &[String], rel_path: &Path) -> bool {
+    let mut components = rel_path.components();
+    let (Some(Component::Normal(first)), rest) = (components.next(), components.next()) else {
+        return false;
+    };
+    let first = first.to_string_lossy();
+
+    root_files
+        .iter()
+        .any(|pattern| match pattern.strip_suffix('/') {
+            Some(dir) => rest.is_some() && wildcard_match(dir, &first),
+            None => rest.is_none() && wildcard_match(pattern, &first),
+        })
+}
+
+/// Match a name against a pattern in which `*` matches any number of characters, ignoring
+/// case.
+fn wildcard_match(pattern: &str, name: &str) -> bool {
+    let pattern = pattern.to_lowercase();
+    let name = name.to_lowercase();
+
+    let mut parts = pattern.split('*');
+    let Some(mut rest) = name.strip_prefix(parts.next().unwrap_or_default()) else {
+        return false;
+    };
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        return rest.is_empty();
+    };
+
+    for part in middle {
+        match rest.split_once(part) {
+            Some((_, after)) => rest = after,
+            None => return false,
+        }
+    }
+
+    rest.ends_with(last)
+}
+
+/// What a new game is set up from.
+#[derive(Debug, Clone, PartialEq)]
+pub enum GamePreset {
+    /// A bare game of the given kind, to be configured by hand
+    Bare(DeployKind),
+    /// A game installed in the given directory, configured from its definition
+    Definition {
+        definition: Box<Definition>,
+        install_dir: PathBuf,
+        runtime: Runtime,
+    },
+}
+
+impl From<DeployKind> for GamePreset {
+    fn from(deploy_kind: DeployKind) -> Self {
+        Self::Bare(deploy_kind)
+    }
+}
+
+impl From<DetectedGame> for GamePreset {
+    fn from(detected: DetectedGame) -> Self {
+        Self::Definition {
+            definition: Box::new(detected.definition),
+            install_dir: detected.install_dir,
+            runtime: detected.runtime,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use tempfile::tempdir;
+
+    use crate::{Repository, deployers::CreationGame};
+
+    use super::*;
+
+    #[test]
+    fn test_add_game() {
+        let repo = Repository::mock();
+        let install_dir = tempdir().unwrap();
+        let compat_data = tempdir().unwrap();
+        fs::write(install_dir.path().join("SkyrimSE.exe"), "").unwrap();
+
+        let local_appdata_dir = compat_data
+            .path()
+            .join("pfx/drive_c/users/steamuser/AppData/Local");
+        fs::create_dir_all(local_appdata_dir.join("Skyrim Special Edition GOG")).unwrap();
+
+        let definition = builtin().into_iter().find(|d| d.id == "skyrimse").unwrap();
+        let runtime = Runtime::Proton {
+            proton_dir: PathBuf::from("/proton"),
+            compat_data: compat_data.path().to_path_buf(),
+            app_id: 489830,
+            steam_dir: PathBuf::new(),
+        };
+        let game = repo
+            .add_game(
+                "Skyrim",
+                GamePreset::Definition {
+                    definition: Box::new(definition),
+                    install_dir: install_dir.path().to_path_buf(),
+                    runtime: runtime.clone(),
+                },
+            )
+            .unwrap();
+
+        assert_eq!(game.deploy_kind().unwrap(), DeployKind::CreationEngine);
+        assert_eq!(game.targets().unwrap(), [install_dir.path().join("Data")]);
+        assert_eq!(game.runtime().unwrap(), runtime);
+        assert_eq!(
+            game.implicit_masters().unwrap(),
+            CreationGame::SkyrimSE.base_masters()
+        );
+        assert_eq!(game.root_files().unwrap(), ["skse64_*"]);
+        assert_eq!(
+            game.appdata_dir().unwrap(),
+            local_appdata_dir.join("Skyrim Special Edition GOG")
+        );
+
+        // The script extender isn't installed
+        let tools = game.tools().unwrap();
+        assert_eq!(tools.len(), 1);
+        assert_eq!(
+            tools.first().unwrap().path().unwrap(),
+            install_dir.path().join("SkyrimSE.exe")
+        );
+    }
+
+    #[test]
+    fn test_is_root_file() {
+        let mut definition = builtin().into_iter().find(|d| d.id == "skyrimse").unwrap();
+        definition.root_files.push("enb*/".to_string());
+
+        let cases = [
+            ("skse64_loader.exe", true),
+            ("SKSE64_1_6_1170.dll", true),
+            ("enbseries/effect.txt", true),
+            ("enbseries", false),
+            ("Data/skse64_loader.exe", false),
+            ("skse.dll", false),
+        ];
+        for (path, expected) in cases {
+            assert_eq!(definition.is_root_file(Path::new(path)), expected, "{path}");
+        }
+    }
+}
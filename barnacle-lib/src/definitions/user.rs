@@ -0,0 +1,300 @@
+use std::{
+    fs,
+    path::{Component, Path, PathBuf},
+};
+
+use serde::Deserialize;
+
+use crate::{
+    definitions::{Definition, Error, Result, ToolDefinition},
+    fs::config_dir,
+    repository::DeployKind,
+};
+
+/// Name of the directory in the config directory that user definitions are kept in
+const DIR_NAME: &str = "games";
+
+/// A definition as it's written in a TOML file
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct DefinitionFile {
+    /// Defaults to the file's name
+    id: Option<String>,
+    name: String,
+    #[serde(default)]
+    deploy_kind: DeployKind,
+    /// Set to `false` to deploy a Gamebryo or Creation Engine game without touching its
+    /// plugin lists
+    plugins: Option<bool>,
+    #[serde(default)]
+    data_dir: PathBuf,
+    #[serde(default)]
+    appdata_dirs: Vec<PathBuf>,
+    #[serde(default)]
+    tools: Vec<ToolDefinition>,
+    #[serde(default)]
+    root_files: Vec<String>,
+    #[serde(default)]
+    implicit_masters: Vec<String>,
+    #[serde(default)]
+    detect: DetectHints,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct DetectHints {
+    steam_app_ids: Vec<u32>,
+    gog_ids: Vec<u64>,
+    epic_app_names: Vec<String>,
+    lutris_slugs: Vec<String>,
+    executables: Vec<PathBuf>,
+}
+
+/// The directory user definitions are loaded from, `games` in the config directory.
+pub fn user_dir() -> PathBuf {
+    config_dir().join(DIR_NAME)
+}
+
+/// Load every `.toml` definition in a directory, in the order of their file names. Returns the
+/// valid definitions along with an error for each invalid one. A definition can't take the ID
+/// of one in `existing` or of another one in the directory.
+pub fn load_dir(dir: &Path, existing: &[Definition]) -> (Vec<Definition>, Vec<Error>) {
+    let mut definitions: Vec<Definition> = Vec::new();
+    let mut errors = Vec::new();
+
+    let mut paths: Vec<PathBuf> = match fs::read_dir(dir) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "toml"))
+            .collect(),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+        Err(e) => return (Vec::new(), vec![e.into()]),
+    };
+    paths.sort();
+
+    for path in paths {
+        match load(&path) {
+            Ok(definition) => {
+                let taken = existing
+                    .iter()
+                    .chain(&definitions)
+                    .any(|d| d.id == definition.id);
+                if taken {
+                    errors.push(invalid(&path, "id", "another definition has the same ID"));
+                } else {
+                    definitions.push(definition);
+                }
+            }
+            Err(e) => errors.push(e),
+        }
+    }
+
+    (definitions, errors)
+}
+
+/// Load and validate a single definition file.
+fn load(path: &Path) -> Result<Definition> {
+    let text = fs::read_to_string(path)?;
+    let file: DefinitionFile =
+        toml::from_str(&text).map_err(|e| Error::Toml(path.to_path_buf(), e))?;
+
+    let id = match file.id {
+        Some(id) => id,
+        None => path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default(),
+    };
+    if id.trim().is_empty() {
+        return Err(invalid(path, "id", "can't be empty"));
+    }
+    if file.name.trim().is_empty() {
+        return Err(invalid(path, "name", "can't be empty"));
+    }
+
+    let mut deploy_kind = file.deploy_kind;
+    match (file.plugins, deploy_kind) {
+        (Some(false), DeployKind::Gamebryo | DeployKind::CreationEngine) => {
+            deploy_kind = DeployKind::Overlay;
+        }
+        (Some(true), DeployKind::Overlay | DeployKind::BaldursGate3) => {
+            return Err(invalid(
+                path,
+                "plugins",
+                "the deploy kind doesn't handle plugins",
+            ));
+        }
+        _ => {}
+    }
+
+    check_relative(path, "data_dir", &file.data_dir)?;
+    for (i, dir) in file.appdata_dirs.iter().enumerate() {
+        check_relative(path, &format!("appdata_dirs[{i}]"), dir)?;
+    }
+    for (i, tool) in file.tools.iter().enumerate() {
+        if tool.name.trim().is_empty() {
+            return Err(invalid(path, &format!("tools[{i}].name"), "can't be empty"));
+        }
+        if tool.path.as_os_str().is_empty() {
+            return Err(invalid(path, &format!("tools[{i}].path"), "can't be empty"));
+        }
+    }
+    for (i, pattern) in file.root_files.iter().enumerate() {
+        let name = pattern.strip_suffix('/').unwrap_or(pattern);
+        if name.is_empty() || name.contains('/') {
+            return Err(invalid(
+                path,
+                &format!("root_files[{i}]"),
+                "has to be the name of a file or directory at the top of a mod",
+            ));
+        }
+    }
+    // Root files are deployed to the directory the data directory is in
+    if !file.root_files.is_empty() && file.data_dir.components().count() != 1 {
+        return Err(invalid(
+            path,
+            "root_files",
+            "need a `data_dir` directly inside the install directory",
+        ));
+    }
+    for (i, exe) in file.detect.executables.iter().enumerate() {
+        check_relative(path, &format!("detect.executables[{i}]"), exe)?;
+        if exe.as_os_str().is_empty() {
+            return Err(invalid(
+                path,
+                &format!("detect.executables[{i}]"),
+                "can't be empty",
+            ));
+        }
+    }
+
+    Ok(Definition {
+        id,
+        name: file.name,
+        deploy_kind,
+        data_dir: file.data_dir,
+        appdata_dirs: file.appdata_dirs,
+        tools: file.tools,
+        root_files: file.root_files,
+        implicit_masters: file.implicit_masters,
+        executables: file.detect.executables,
+        steam_app_ids: file.detect.steam_app_ids,
+        gog_ids: file.detect.gog_ids,
+        epic_app_names: file.detect.epic_app_names,
+        lutris_slugs: file.detect.lutris_slugs,
+    })
+}
+
+/// Make sure a path stays inside the directory it's relative to.
+fn check_relative(path: &Path, field: &str, value: &Path) -> Result<()> {
+    if value
+        .components()
+        .all(|c| matches!(c, Component::Normal(_)))
+    {
+        Ok(())
+    } else {
+        Err(invalid(
+            path,
+            field,
+            "has to be a relative path inside the game",
+        ))
+    }
+}
+
+fn invalid(path: &Path, field: &str, message: &str) -> Error {
+    Error::Invalid {
+        path: path.to_path_buf(),
+        field: field.to_string(),
+        message: message.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use tempfile::tempdir;
+
+    use crate::definitions::builtin;
+
+    use super::*;
+
+    #[test]
+    fn test_load_dir() {
+        let dir = tempdir().unwrap();
+        let files = [
+            (
+                "enderal.toml",
+                r#"
+name = "Enderal"
+deploy_kind = "gamebryo"
+plugins = false
+data_dir = "Data"
+root_files = ["skse_*"]
+implicit_masters = ["Skyrim.esm", "Update.esm", "Enderal - Forgotten Stories.esm"]
+
+[[tools]]
+name = "Enderal"
+path = "Enderal Launcher.exe"
+
+[detect]
+steam_app_ids = [933480]
+executables = ["TESV.exe"]
+"#,
+            ),
+            ("kind.toml", "name = \"Kind\"\ndeploy_kind = \"unreal\"\n"),
+            ("data.toml", "name = \"Data\"\ndata_dir = \"../Data\"\n"),
+            (
+                "tool.toml",
+                "name = \"Tool\"\n[[tools]]\nname = \"Game\"\npath = \"\"\n",
+            ),
+            ("plugins.toml", "name = \"Plugins\"\nplugins = true\n"),
+            ("root.toml", "name = \"Root\"\nroot_files = [\"skse_*\"]\n"),
+            ("skyrim.toml", "id = \"skyrimse\"\nname = \"Skyrim\"\n"),
+        ];
+        for (name, contents) in files {
+            fs::write(dir.path().join(name), contents).unwrap();
+        }
+        fs::write(dir.path().join("notes.txt"), "").unwrap();
+
+        let (definitions, errors) = load_dir(dir.path(), &builtin());
+
+        assert_eq!(definitions.len(), 1);
+        let enderal = definitions.first().unwrap();
+        assert_eq!(enderal.id, "enderal");
+        assert_eq!(enderal.deploy_kind, DeployKind::Overlay);
+        assert_eq!(enderal.steam_app_ids, [933480]);
+        assert_eq!(enderal.executables, [PathBuf::from("TESV.exe")]);
+        assert!(enderal.is_root_file(Path::new("skse_loader.exe")));
+        assert_eq!(
+            enderal.implicit_masters,
+            [
+                "Skyrim.esm",
+                "Update.esm",
+                "Enderal - Forgotten Stories.esm"
+            ]
+        );
+
+        let errors: Vec<(String, Option<String>)> = errors
+            .iter()
+            .map(|e| match e {
+                Error::Invalid { path, field, .. } => (file_name(path), Some(field.clone())),
+                Error::Toml(path, _) => (file_name(path), None),
+                Error::Io(e) => panic!("{e}"),
+            })
+            .collect();
+        assert_eq!(
+            errors,
+            [
+                ("data.toml".to_string(), Some("data_dir".to_string())),
+                ("kind.toml".to_string(), None),
+                ("plugins.toml".to_string(), Some("plugins".to_string())),
+                ("root.toml".to_string(), Some("root_files".to_string())),
+                ("skyrim.toml".to_string(), Some("id".to_string())),
+                ("tool.toml".to_string(), Some("tools[0].path".to_string())),
+            ]
+        );
+    }
+
+    fn file_name(path: &Path) -> String {
+        path.file_name().unwrap().to_string_lossy().into_owned()
+    }
+}
@@ -0,0 +1,256 @@
+use std::{
+    fs::{self, File},
+    io,
+    os::unix::fs::symlink,
+    path::{Path, PathBuf},
+};
+
+use md5::{Digest, Md5};
+use tracing::warn;
+
+use crate::{
+    deployers::{
+        Deployer, Error, PLUGIN_LISTS_BACKUP_DIR_NAME, Result, Setup, file_deployer,
+        manifest::{Manifest, ManifestFile},
+        plugin_lists::PluginLists,
+    },
+    formats::{
+        lspk::{self, Package},
+        lsx::{self, ModuleInfo},
+    },
+    repository::Profile,
+};
+
+/// Location of the list of mods to load, relative to the Larian user data directory
+const MOD_SETTINGS_PATH: &str = "PlayerProfiles/Public/modsettings.lsx";
+/// Name of the file in a game's directory that keeps track of the packages placed in `Mods`
+const PAK_MANIFEST_FILE_NAME: &str = "paks.toml";
+/// Name of the directory in a game's directory that packages of the same name are moved to
+const PAK_BACKUP_DIR_NAME: &str = "vanilla_paks";
+
+/// Deploys Baldur's Gate 3.
+///
+/// The `.pak` packages of the enabled mods are symlinked into the `Mods` folder of the Larian
+/// user data directory, which is the game's AppData directory, and registered in
+/// `modsettings.lsx` in mod order, right after the base game's module. Loose-file overrides,
+/// which are mods without packages and the subdirectories of mods with them, are placed in the
+/// game's `Data` directory by a file based [`Deployer`]. It places whole mod directories, so the
+/// packages of mods with both end up in `Data` as well, where the game doesn't load them.
+pub struct Bg3Deployer {
+    loose: Option<Box<dyn Deployer>>,
+    paks: Vec<PathBuf>,
+    mods_dir: PathBuf,
+    manifest_path: PathBuf,
+    backup_dir: PathBuf,
+    lists: PluginLists,
+}
+
+impl Bg3Deployer {
+    pub fn new(profile: &Profile) -> Result<Self> {
+        Self::with_setup(profile, &Setup::new(profile)?)
+    }
+
+    pub(crate) fn with_setup(profile: &Profile, setup: &Setup) -> Result<Self> {
+        let user_dir = &setup.appdata_dir;
+        if user_dir.as_os_str().is_empty() {
+            return Err(Error::NoAppDataDir);
+        }
+
+        let mut paks = Vec::new();
+        let mut loose_dirs = Vec::new();
+        for dir in &setup.mod_dirs {
+            let dir_paks = lspk::packages_in(dir)?;
+            if dir_paks.is_empty() || has_subdirs(dir)? {
+                loose_dirs.push(dir.clone());
+            }
+            paks.extend(dir_paks);
+        }
+
+        // Loose files need the game's Data directory as the target, packages don't
+        let loose = match setup.primary_target() {
+            Ok(_) => Some(file_deployer(profile, setup, loose_dirs)?),
+            Err(Error::NoTarget) if loose_dirs.is_empty() => None,
+            Err(e) => return Err(e),
+        };
+
+        let game_dir = profile.parent()?.dir()?;
+
+        Ok(Self {
+            loose,
+            paks,
+            mods_dir: user_dir.join("Mods"),
+            manifest_path: game_dir.join(PAK_MANIFEST_FILE_NAME),
+            backup_dir: game_dir.join(PAK_BACKUP_DIR_NAME),
+            lists: PluginLists::new(user_dir, &game_dir.join(PLUGIN_LISTS_BACKUP_DIR_NAME)),
+        })
+    }
+
+    /// Link every package into `Mods`, returning the modules to register along with the MD5
+    /// hashes of their packages.
+    fn place_paks(&self, manifest: &mut Manifest) -> Result<Vec<(ModuleInfo, String)>> {
+        let mut modules = Vec::new();
+
+        for pak in &self.paks {
+            let Some(name) = pak.file_name() else {
+                continue;
+            };
+
+            let dest = manifest.prepare(Path::new(name), &self.backup_dir)?;
+            symlink(pak, &dest)?;
+            manifest.files.push(ManifestFile {
+                path: PathBuf::from(name),
+                source: pak.clone(),
+                hash: None,
+            });
+
+            // Parts are linked along with the package they belong to, but only it has metadata
+            if lspk::is_part(pak) {
+                continue;
+            }
+
+            match Package::open(pak).and_then(|p| ModuleInfo::from_package(&p)) {
+                Ok(Some(module)) => modules.push((module, md5_file(pak)?)),
+                Ok(None) => {}
+                Err(e) => warn!("Failed to read the metadata of {}: {e}", pak.display()),
+            }
+        }
+
+        Ok(modules)
+    }
+
+    fn remove_paks(&self) -> Result<()> {
+        let Some(manifest) = Manifest::load(&self.manifest_path)? else {
+            return Ok(());
+        };
+
+        manifest.restore(&self.backup_dir, |dest, file| {
+            fs::read_link(dest).is_ok_and(|t| t == file.source)
+        })?;
+        fs::remove_file(&self.manifest_path)?;
+
+        Ok(())
+    }
+}
+
+impl Deployer for Bg3Deployer {
+    fn deploy(&mut self) -> Result<()> {
+        self.remove_paks()?;
+
+        fs::create_dir_all(&self.mods_dir)?;
+        let mut manifest = Manifest::new(&self.mods_dir);
+
+        let modules = match self.place_paks(&mut manifest) {
+            Ok(modules) => modules,
+            Err(e) => {
+                // Don't leave a half finished deployment behind
+                manifest.restore(&self.backup_dir, |dest, file| {
+                    fs::read_link(dest).is_ok_and(|t| t == file.source)
+                })?;
+                return Err(e);
+            }
+        };
+        manifest.save(&self.manifest_path)?;
+
+        let existing = self.lists.original(MOD_SETTINGS_PATH)?;
+        self.lists.write_files(&[(
+            MOD_SETTINGS_PATH,
+            lsx::mod_settings(existing.as_deref(), &modules)?,
+        )])?;
+
+        if let Some(loose) = &mut self.loose {
+            loose.deploy()?;
+        }
+
+        Ok(())
+    }
+
+    fn undeploy(&mut self) -> Result<()> {
+        if let Some(loose) = &mut self.loose {
+            loose.undeploy()?;
+        }
+
+        self.remove_paks()?;
+        self.lists.restore(&[MOD_SETTINGS_PATH])?;
+
+        Ok(())
+    }
+
+    fn mount_points(&self) -> Vec<PathBuf> {
+        self.loose
+            .as_ref()
+            .map(|loose| loose.mount_points())
+            .unwrap_or_default()
+    }
+}
+
+fn has_subdirs(dir: &Path) -> io::Result<bool> {
+    for entry in fs::read_dir(dir)? {
+        if entry?.file_type()?.is_dir() {
+            return Ok(true);
+        }
+    }
+
+    Ok(false)
+}
+
+fn md5_file(path: &Path) -> io::Result<String> {
+    let mut hasher = Md5::new();
+    io::copy(&mut File::open(path)?, &mut hasher)?;
+
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+#[cfg(test)]
+mod test {
+    use tempfile::tempdir;
+
+    use super::*;
+
+    const META: &str = r#"<save><region id="Config"><node id="root"><children>
+        <node id="ModuleInfo">
+            <attribute id="Folder" type="LSString" value="BetterCamp"/>
+            <attribute id="Name" type="LSString" value="Better Camp"/>
+            <attribute id="UUID" type="FixedString" value="f3c2a1b0-0000-4000-8000-000000000001"/>
+        </node>
+    </children></node></region></save>"#;
+
+    #[test]
+    fn test_deploy_undeploy() {
+        let dir = tempdir().unwrap();
+        let user_dir = dir.path().join("Baldur's Gate 3");
+        let mod_dir = dir.path().join("better_camp");
+        fs::create_dir_all(&mod_dir).unwrap();
+        lspk::build(
+            &mod_dir.join("BetterCamp.pak"),
+            &[("Mods/BetterCamp/meta.lsx", META.as_bytes(), 2)],
+        );
+        // Neither the second part of the package nor a broken one hold up the deployment
+        fs::write(mod_dir.join("BetterCamp_1.pak"), "part").unwrap();
+        fs::write(mod_dir.join("Broken.pak"), "broken").unwrap();
+
+        let mut deployer = Bg3Deployer {
+            loose: None,
+            paks: lspk::packages_in(&mod_dir).unwrap(),
+            mods_dir: user_dir.join("Mods"),
+            manifest_path: dir.path().join(PAK_MANIFEST_FILE_NAME),
+            backup_dir: dir.path().join(PAK_BACKUP_DIR_NAME),
+            lists: PluginLists::new(&user_dir, &dir.path().join(PLUGIN_LISTS_BACKUP_DIR_NAME)),
+        };
+
+        deployer.deploy().unwrap();
+
+        assert_eq!(
+            fs::read_link(user_dir.join("Mods/BetterCamp.pak")).unwrap(),
+            mod_dir.join("BetterCamp.pak")
+        );
+        let mod_settings = fs::read_to_string(user_dir.join(MOD_SETTINGS_PATH)).unwrap();
+        assert!(mod_settings.contains("value=\"BetterCamp\""));
+        assert!(mod_settings.contains(&md5_file(&mod_dir.join("BetterCamp.pak")).unwrap()));
+        assert!(user_dir.join("Mods/BetterCamp_1.pak").exists());
+
+        deployer.undeploy().unwrap();
+
+        assert!(!user_dir.join("Mods/BetterCamp.pak").exists());
+        assert!(!user_dir.join(MOD_SETTINGS_PATH).exists());
+    }
+}
@@ -0,0 +1,355 @@
+use std::{
+    collections::HashSet,
+    fs::{self, File},
+    io, mem,
+    path::{Path, PathBuf},
+};
+
+use tracing::{debug, warn};
+
+use crate::{
+    deployers::{
+        Deployer, Part, Result, Setup,
+        manifest::{Manifest, ManifestFile},
+    },
+    fs::move_file,
+    repository::Profile,
+};
+
+/// Name of the file in a game's directory that copied files are recorded in
+const MANIFEST_FILE_NAME: &str = "copies.toml";
+/// Name of the directory in a game's directory that original game files are moved to while
+/// copies replace them
+const BACKUP_DIR_NAME: &str = "vanilla_copies";
+
+/// Deploys by copying every winning file of the enabled mods into the target directory.
+///
+/// This is the most compatible method, for games and launchers that refuse to work with links
+/// or overlay mounts. Original game files are moved into the game's vanilla backup before being
+/// overwritten, and the manifest records a hash of every copied file. Redeploying only copies
+/// files that changed, and leaves files that were edited in the target since they were deployed
+/// alone. Files created in the target while deployed are moved into the profile's overwrite
+/// directory when undeploying.
+#[derive(Debug)]
+pub struct CopyDeployer {
+    target: PathBuf,
+    mod_dirs: Vec<PathBuf>,
+    part: Part,
+    manifest_path: PathBuf,
+    backup_dir: PathBuf,
+    overwrite_dir: PathBuf,
+}
+
+impl CopyDeployer {
+    pub fn new(profile: &Profile) -> Result<Self> {
+        let setup = Setup::new(profile)?;
+
+        Self::with_mod_dirs(
+            profile,
+            setup.primary_target()?,
+            setup.mod_dirs,
+            Part::default(),
+        )
+    }
+
+    /// Copy the given [`Part`] of the files of the given mod directories, lowest priority first,
+    /// into the given target instead of the game's.
+    pub(crate) fn with_mod_dirs(
+        profile: &Profile,
+        target: PathBuf,
+        mod_dirs: Vec<PathBuf>,
+        part: Part,
+    ) -> Result<Self> {
+        let game_dir = profile.parent()?.dir()?;
+
+        Ok(Self {
+            target,
+            mod_dirs,
+            manifest_path: game_dir.join(part.file_name(MANIFEST_FILE_NAME)),
+            backup_dir: game_dir.join(part.file_name(BACKUP_DIR_NAME)),
+            part,
+            overwrite_dir: profile.overwrite_dir()?,
+        })
+    }
+
+    /// Bring the deployment recorded in the manifest up to date with the current set of mod
+    /// files. Files are recorded before they're copied, so that a deployment that's cut short
+    /// can be undone like any other.
+    fn update(&self, manifest: &mut Manifest) -> Result<()> {
+        let planned = self.part.resolve_files(&self.mod_dirs);
+        let planned_keys: HashSet<String> = planned.iter().map(|(p, _)| key(p)).collect();
+
+        // Remove files that no longer belong to the deployment, bringing back the originals.
+        // Until the manifest is saved again, the one on disk still covers them.
+        let (stale, kept): (Vec<_>, Vec<_>) = mem::take(&mut manifest.files)
+            .into_iter()
+            .partition(|f| !planned_keys.contains(&key(&f.path)));
+        manifest.files = kept;
+
+        for file in stale {
+            let dest = manifest.target.join(&file.path);
+            if is_modified(&dest, &file) {
+                warn!(
+                    "Leaving {} in place, since it was changed after being deployed",
+                    dest.display()
+                );
+                // Keeping track of it means undeploying leaves it alone as well
+                manifest.files.push(file);
+                continue;
+            }
+
+            if dest.exists() {
+                fs::remove_file(&dest)?;
+            }
+
+            if let Some(i) = manifest.shadowed.iter().position(|p| p == &file.path) {
+                move_file(&self.backup_dir.join(&file.path), &dest)?;
+                manifest.shadowed.remove(i);
+            }
+        }
+
+        // Files to copy, along with whether they replace an outdated copy
+        let mut pending = Vec::new();
+
+        for (rel_path, source) in planned {
+            let previous = manifest
+                .files
+                .iter_mut()
+                .find(|f| key(&f.path) == key(&rel_path));
+
+            let Some(previous) = previous else {
+                let hash = hash_file(&source)?;
+                manifest.add(ManifestFile {
+                    path: rel_path.clone(),
+                    source: source.clone(),
+                    hash: Some(hash),
+                });
+                pending.push((rel_path, source, false));
+                continue;
+            };
+
+            let dest = manifest.target.join(&previous.path);
+            if is_modified(&dest, previous) {
+                warn!(
+                    "Not replacing {}, since it was changed after being deployed",
+                    dest.display()
+                );
+                continue;
+            }
+
+            // Already deployed, so it only needs to be copied again if it changed
+            let hash = hash_file(&source)?;
+            if !dest.exists() || previous.hash.as_ref() != Some(&hash) {
+                previous.hash = Some(hash);
+                pending.push((previous.path.clone(), source.clone(), dest.exists()));
+            }
+            previous.source = source;
+        }
+
+        manifest.save(&self.manifest_path)?;
+
+        for (rel_path, source, replace) in pending {
+            if replace {
+                fs::remove_file(manifest.target.join(&rel_path))?;
+            }
+
+            let dest = manifest.prepare(&rel_path, &self.backup_dir)?;
+
+            debug!("Copying {} to {}", source.display(), dest.display());
+            fs::copy(&source, &dest)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Deployer for CopyDeployer {
+    fn deploy(&mut self) -> Result<()> {
+        let mut manifest = match Manifest::load(&self.manifest_path)? {
+            Some(old) if old.target == self.target => old,
+            Some(_) => {
+                // The game's target changed since the last deployment
+                self.undeploy()?;
+                Manifest::new(&self.target)
+            }
+            None => Manifest::new(&self.target),
+        };
+
+        if let Err(e) = self.update(&mut manifest) {
+            // Don't leave a half finished deployment behind
+            self.undeploy()?;
+            return Err(e);
+        }
+
+        manifest.save(&self.manifest_path)
+    }
+
+    fn undeploy(&mut self) -> Result<()> {
+        let Some(manifest) = Manifest::load(&self.manifest_path)? else {
+            return Ok(());
+        };
+
+        if self.part.collects_new_files() {
+            manifest.collect_new_files(&self.overwrite_dir)?;
+        }
+        manifest.restore(&self.backup_dir, |dest, file| !is_modified(dest, file))?;
+        fs::remove_file(&self.manifest_path)?;
+
+        Ok(())
+    }
+}
+
+/// Whether a deployed file still exists but no longer matches the hash it was deployed with
+fn is_modified(dest: &Path, file: &ManifestFile) -> bool {
+    dest.exists() && hash_file(dest).ok() != file.hash
+}
+
+fn hash_file(path: &Path) -> io::Result<String> {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update_reader(File::open(path)?)?;
+
+    Ok(hasher.finalize().to_hex().to_string())
+}
+
+fn key(path: &Path) -> String {
+    path.to_string_lossy().to_lowercase()
+}
+
+#[cfg(test)]
+mod test {
+    use tempfile::tempdir;
+
+    use super::*;
+
+    #[test]
+    fn test_deploy_undeploy() {
+        let dir = tempdir().unwrap();
+        let target = dir.path().join("game");
+        let mod_dir = dir.path().join("mod");
+
+        fs::create_dir_all(&target).unwrap();
+        fs::create_dir_all(mod_dir.join("textures")).unwrap();
+        fs::write(target.join("game.esm"), "vanilla").unwrap();
+        fs::write(mod_dir.join("game.esm"), "modded").unwrap();
+        fs::write(mod_dir.join("textures/rock.dds"), "rock").unwrap();
+
+        let mut deployer = CopyDeployer {
+            target: target.clone(),
+            mod_dirs: vec![mod_dir.clone()],
+            part: Part::default(),
+            manifest_path: dir.path().join(MANIFEST_FILE_NAME),
+            backup_dir: dir.path().join(BACKUP_DIR_NAME),
+            overwrite_dir: dir.path().join("overwrite"),
+        };
+
+        deployer.deploy().unwrap();
+
+        assert_eq!(
+            fs::read_to_string(target.join("game.esm")).unwrap(),
+            "modded"
+        );
+        assert_eq!(
+            fs::read_to_string(target.join("textures/rock.dds")).unwrap(),
+            "rock"
+        );
+
+        // Redeploying picks up changes to the mod, including removed files
+        fs::write(mod_dir.join("textures/rock.dds"), "better rock").unwrap();
+        fs::remove_file(mod_dir.join("game.esm")).unwrap();
+        deployer.deploy().unwrap();
+
+        assert_eq!(
+            fs::read_to_string(target.join("textures/rock.dds")).unwrap(),
+            "better rock"
+        );
+        assert_eq!(
+            fs::read_to_string(target.join("game.esm")).unwrap(),
+            "vanilla"
+        );
+
+        deployer.undeploy().unwrap();
+
+        assert!(!target.join("textures").exists());
+        assert_eq!(
+            fs::read_to_string(target.join("game.esm")).unwrap(),
+            "vanilla"
+        );
+    }
+
+    #[test]
+    fn test_modified_files() {
+        let dir = tempdir().unwrap();
+        let target = dir.path().join("game");
+        let mod_dir = dir.path().join("mod");
+
+        fs::create_dir_all(&target).unwrap();
+        fs::create_dir_all(&mod_dir).unwrap();
+        fs::write(mod_dir.join("settings.ini"), "default").unwrap();
+
+        let mut deployer = CopyDeployer {
+            target: target.clone(),
+            mod_dirs: vec![mod_dir.clone()],
+            part: Part::default(),
+            manifest_path: dir.path().join(MANIFEST_FILE_NAME),
+            backup_dir: dir.path().join(BACKUP_DIR_NAME),
+            overwrite_dir: dir.path().join("overwrite"),
+        };
+
+        deployer.deploy().unwrap();
+        fs::write(target.join("settings.ini"), "tweaked").unwrap();
+
+        fs::write(mod_dir.join("readme.txt"), "hello").unwrap();
+
+        // The edited file is kept, without holding up the rest of the deployment
+        deployer.deploy().unwrap();
+        assert_eq!(
+            fs::read_to_string(target.join("settings.ini")).unwrap(),
+            "tweaked"
+        );
+        assert_eq!(
+            fs::read_to_string(target.join("readme.txt")).unwrap(),
+            "hello"
+        );
+
+        // Undeploying leaves the edited file alone as well
+        deployer.undeploy().unwrap();
+        assert_eq!(
+            fs::read_to_string(target.join("settings.ini")).unwrap(),
+            "tweaked"
+        );
+    }
+    #[test]
+    fn test_failed_deploy() {
+        let dir = tempdir().unwrap();
+        let target = dir.path().join("game");
+        let mod_dir = dir.path().join("mod");
+
+        fs::create_dir_all(&target).unwrap();
+        fs::create_dir_all(mod_dir.join("textures")).unwrap();
+        fs::write(target.join("game.esm"), "vanilla").unwrap();
+        // A file in the way of a directory the mod needs, which fails the deployment after
+        // game.esm has been replaced
+        fs::write(target.join("textures"), "").unwrap();
+        fs::write(mod_dir.join("game.esm"), "modded").unwrap();
+        fs::write(mod_dir.join("textures/rock.dds"), "rock").unwrap();
+
+        let mut deployer = CopyDeployer {
+            target: target.clone(),
+            mod_dirs: vec![mod_dir.clone()],
+            part: Part::default(),
+            manifest_path: dir.path().join(MANIFEST_FILE_NAME),
+            backup_dir: dir.path().join(BACKUP_DIR_NAME),
+            overwrite_dir: dir.path().join("overwrite"),
+        };
+
+        assert!(deployer.deploy().is_err());
+
+        assert_eq!(
+            fs::read_to_string(target.join("game.esm")).unwrap(),
+            "vanilla"
+        );
+        assert!(target.join("textures").is_file());
+        assert!(!deployer.manifest_path.exists());
+        assert!(!deployer.backup_dir.exists());
+    }
+}
@@ -0,0 +1,371 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    deployers::{
+        Deployer, Error, PLUGIN_LISTS_BACKUP_DIR_NAME, Result, Setup,
+        gamebryo::{
+            apply_plugin_order, is_master, is_plugin_enabled, masters_in, plugin_name, plugins_in,
+        },
+        plugin_lists::PluginLists,
+        plugin_order, resolve_files,
+    },
+    formats::plugin::GameId,
+    repository::Profile,
+};
+
+const LIST_NAMES: [&str; 1] = ["plugins.txt"];
+/// Plugin extensions of the Creation Engine games
+const EXTENSIONS: [&str; 3] = ["esm", "esl", "esp"];
+
+/// A game built on the Creation Engine, told apart by its base master.
+///
+/// Games set up from a definition have their own list of implicit masters, which takes the place
+/// of [`CreationGame::base_masters`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum CreationGame {
+    SkyrimSE,
+    SkyrimVR,
+    Fallout4,
+    Starfield,
+}
+
+impl CreationGame {
+    /// Detect the game from the base master in its data directory.
+    pub fn detect(data_dir: &Path) -> Option<Self> {
+        Self::from_masters(&masters_in(data_dir))
+    }
+
+    /// Tell the game from the names of masters it has, like the implicit masters of its
+    /// definition. Names are compared case-insensitively.
+    pub fn from_masters(masters: &[String]) -> Option<Self> {
+        // Skyrim VR ships Skyrim.esm as well, so it has to be checked first
+        [
+            Self::SkyrimVR,
+            Self::SkyrimSE,
+            Self::Fallout4,
+            Self::Starfield,
+        ]
+        .into_iter()
+        .find(|game| {
+            masters
+                .iter()
+                .any(|master| master.eq_ignore_ascii_case(game.identifying_master()))
+        })
+    }
+
+    /// The game its plugins are made for
+    pub fn plugin_game(self) -> GameId {
+        match self {
+            Self::SkyrimSE => GameId::SkyrimSE,
+            Self::SkyrimVR => GameId::SkyrimVR,
+            Self::Fallout4 => GameId::Fallout4,
+            Self::Starfield => GameId::Starfield,
+        }
+    }
+
+    /// The master only this game has
+    fn identifying_master(self) -> &'static str {
+        match self {
+            Self::SkyrimSE => "Skyrim.esm",
+            Self::SkyrimVR => "SkyrimVR.esm",
+            Self::Fallout4 => "Fallout4.esm",
+            Self::Starfield => "Starfield.esm",
+        }
+    }
+
+    /// The masters the game always loads first, in order
+    pub const fn base_masters(self) -> &'static [&'static str] {
+        match self {
+            Self::SkyrimSE => &[
+                "Skyrim.esm",
+                "Update.esm",
+                "Dawnguard.esm",
+                "HearthFires.esm",
+                "Dragonborn.esm",
+            ],
+            Self::SkyrimVR => &[
+                "Skyrim.esm",
+                "Update.esm",
+                "Dawnguard.esm",
+                "HearthFires.esm",
+                "Dragonborn.esm",
+                "SkyrimVR.esm",
+            ],
+            Self::Fallout4 => &[
+                "Fallout4.esm",
+                "DLCRobot.esm",
+                "DLCworkshop01.esm",
+                "DLCCoast.esm",
+                "DLCworkshop02.esm",
+                "DLCworkshop03.esm",
+                "DLCNukaWorld.esm",
+                "DLCUltraHighResolution.esm",
+            ],
+            Self::Starfield => &[
+                "Starfield.esm",
+                "Constellation.esm",
+                "OldMars.esm",
+                "ShatteredSpace.esm",
+                "SFBGS003.esm",
+                "SFBGS004.esm",
+                "SFBGS006.esm",
+                "SFBGS007.esm",
+                "SFBGS008.esm",
+                "BlueprintShips-Starfield.esm",
+            ],
+        }
+    }
+
+    /// The file listing the game's Creation Club plugins, if it has one
+    fn ccc_file_name(self) -> Option<&'static str> {
+        match self {
+            Self::SkyrimSE => Some("Skyrim.ccc"),
+            Self::SkyrimVR => None,
+            Self::Fallout4 => Some("Fallout4.ccc"),
+            Self::Starfield => Some("Starfield.ccc"),
+        }
+    }
+}
+
+/// Returns every plugin the game loads on its own, without it being in `plugins.txt`: the given
+/// masters, or the base masters of the game if there are none, followed by the Creation Club
+/// plugins, as far as `exists` finds them.
+fn implicit_plugins(
+    game: Option<CreationGame>,
+    masters: &[String],
+    data_dir: &Path,
+    exists: impl Fn(&str) -> bool,
+) -> Result<Vec<String>> {
+    let mut plugins = masters.to_vec();
+    if plugins.is_empty() {
+        let game = game.ok_or(Error::UnknownGame)?;
+        plugins.extend(game.base_masters().iter().map(|master| master.to_string()));
+    }
+
+    // The list is kept next to the executable, one level above the data directory
+    let ccc = game.and_then(CreationGame::ccc_file_name).and_then(|name| {
+        [Some(data_dir), data_dir.parent()]
+            .into_iter()
+            .flatten()
+            .find_map(|dir| fs::read_to_string(dir.join(name)).ok())
+    });
+    plugins.extend(
+        ccc.iter()
+            .flat_map(|contents| contents.lines())
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(str::to_string),
+    );
+
+    plugins.retain(|plugin| exists(plugin));
+    Ok(plugins)
+}
+
+/// Deploys Skyrim Special Edition, Skyrim VR, Fallout 4 and Starfield on top of a file based
+/// [`Deployer`].
+///
+/// Besides placing the files, the plugins are written to `plugins.txt` in the game's AppData
+/// directory, following the profile's plugin order, with active ones marked by an asterisk.
+/// The base game's masters and the installed Creation Club content are loaded by the game on
+/// its own, so they're left out of the list.
+pub struct CreationEngineDeployer {
+    inner: Box<dyn Deployer>,
+    target: PathBuf,
+    implicit_masters: Vec<String>,
+    mod_dirs: Vec<PathBuf>,
+    plugin_order: Vec<(String, bool)>,
+    lists: PluginLists,
+}
+
+impl CreationEngineDeployer {
+    pub fn new(profile: &Profile, inner: Box<dyn Deployer>) -> Result<Self> {
+        Self::with_setup(profile, &Setup::new(profile)?, inner)
+    }
+
+    pub(crate) fn with_setup(
+        profile: &Profile,
+        setup: &Setup,
+        inner: Box<dyn Deployer>,
+    ) -> Result<Self> {
+        if setup.appdata_dir.as_os_str().is_empty() {
+            return Err(Error::NoAppDataDir);
+        }
+
+        let game = profile.parent()?;
+
+        Ok(Self {
+            inner,
+            target: setup.primary_target()?,
+            implicit_masters: game.implicit_masters()?,
+            mod_dirs: setup.mod_dirs.clone(),
+            plugin_order: plugin_order(profile)?,
+            lists: PluginLists::new(
+                &setup.appdata_dir,
+                &game.dir()?.join(PLUGIN_LISTS_BACKUP_DIR_NAME),
+            ),
+        })
+    }
+}
+
+impl Deployer for CreationEngineDeployer {
+    fn deploy(&mut self) -> Result<()> {
+        let mut base_plugins: Vec<String> = plugins_in(&self.target, &EXTENSIONS)?
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+        base_plugins.sort_by_key(|name| name.to_lowercase());
+        let mod_plugins: Vec<String> = resolve_files(&self.mod_dirs)
+            .into_iter()
+            .filter_map(|(rel_path, _)| plugin_name(&rel_path, &EXTENSIONS))
+            .collect();
+        let mod_plugins = apply_plugin_order(mod_plugins, String::as_str, &self.plugin_order);
+
+        let implicit = implicit_plugins(
+            CreationGame::detect(&self.target),
+            &self.implicit_masters,
+            &self.target,
+            |plugin| {
+                base_plugins
+                    .iter()
+                    .chain(&mod_plugins)
+                    .any(|p| p.eq_ignore_ascii_case(plugin))
+            },
+        )?;
+        let order = load_order(&implicit, base_plugins, mod_plugins);
+
+        self.inner.deploy()?;
+
+        self.lists.write(&[(
+            "plugins.txt",
+            order
+                .iter()
+                .map(|plugin| plugin_line(plugin, is_plugin_enabled(plugin, &self.plugin_order)))
+                .collect(),
+        )])?;
+
+        Ok(())
+    }
+
+    fn undeploy(&mut self) -> Result<()> {
+        self.inner.undeploy()?;
+        self.lists.restore(&LIST_NAMES)?;
+
+        Ok(())
+    }
+
+    fn mount_points(&self) -> Vec<PathBuf> {
+        self.inner.mount_points()
+    }
+}
+
+/// Returns the line of `plugins.txt` for a plugin, which is marked with an asterisk when active.
+fn plugin_line(name: &str, enabled: bool) -> String {
+    if enabled {
+        format!("*{name}")
+    } else {
+        name.to_string()
+    }
+}
+
+/// Decide the order of `plugins.txt`: plugins already in the data directory first, followed by
+/// the mods' plugins in mod order, with masters and light plugins ahead of regular plugins.
+/// Implicitly loaded plugins are left out, and a mod plugin replacing another one takes its
+/// place.
+fn load_order(
+    implicit: &[String],
+    base_plugins: Vec<String>,
+    mod_plugins: Vec<String>,
+) -> Vec<String> {
+    let mut order: Vec<String> = Vec::new();
+
+    for name in base_plugins.into_iter().chain(mod_plugins) {
+        if implicit.iter().any(|p| p.eq_ignore_ascii_case(&name)) {
+            continue;
+        }
+
+        match order.iter_mut().find(|p| p.eq_ignore_ascii_case(&name)) {
+            Some(existing) => *existing = name,
+            None => order.push(name),
+        }
+    }
+
+    order.sort_by_key(|name| !is_master(name));
+
+    order
+}
+
+#[cfg(test)]
+mod test {
+    use tempfile::tempdir;
+
+    use super::*;
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|n| n.to_string()).collect()
+    }
+
+    #[test]
+    fn test_implicit_plugins() {
+        let dir = tempdir().unwrap();
+        let data_dir = dir.path().join("Data");
+        fs::create_dir_all(&data_dir).unwrap();
+        for plugin in [
+            "Skyrim.esm",
+            "Update.esm",
+            "Dawnguard.esm",
+            "ccBGSSSE001-Fish.esm",
+        ] {
+            fs::write(data_dir.join(plugin), "").unwrap();
+        }
+        fs::write(
+            dir.path().join("Skyrim.ccc"),
+            "ccBGSSSE001-Fish.esm\r\nccQDRSSE001-SurvivalMode.esl\r\n",
+        )
+        .unwrap();
+
+        let exists = |p: &str| data_dir.join(p).exists();
+        let game = CreationGame::detect(&data_dir);
+        assert_eq!(game, Some(CreationGame::SkyrimSE));
+        assert_eq!(
+            implicit_plugins(game, &[], &data_dir, exists).unwrap(),
+            names(&[
+                "Skyrim.esm",
+                "Update.esm",
+                "Dawnguard.esm",
+                "ccBGSSSE001-Fish.esm"
+            ])
+        );
+
+        // The game's own masters take the place of the base masters
+        let masters = names(&["Update.esm", "Skyrim.esm"]);
+        assert_eq!(
+            implicit_plugins(game, &masters, &data_dir, exists).unwrap(),
+            names(&["Update.esm", "Skyrim.esm", "ccBGSSSE001-Fish.esm"])
+        );
+        assert_eq!(
+            implicit_plugins(None, &masters, &data_dir, exists).unwrap(),
+            names(&["Update.esm", "Skyrim.esm"])
+        );
+        assert!(matches!(
+            implicit_plugins(None, &[], &data_dir, exists),
+            Err(Error::UnknownGame)
+        ));
+    }
+
+    #[test]
+    fn test_load_order() {
+        let implicit = names(&["Skyrim.esm", "Update.esm"]);
+        let base_plugins = names(&["Skyrim.esm", "Update.esm", "Cleaned.esp"]);
+        let mod_plugins = names(&["Patch.esp", "cleaned.esp", "Light.esl", "update.esm"]);
+
+        assert_eq!(
+            load_order(&implicit, base_plugins, mod_plugins),
+            names(&["Light.esl", "cleaned.esp", "Patch.esp"])
+        );
+        assert_eq!(plugin_line("Patch.esp", true), "*Patch.esp");
+        assert_eq!(plugin_line("Patch.esp", false), "Patch.esp");
+    }
+}
@@ -0,0 +1,326 @@
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use crate::{
+    deployers::{
+        Deployer, Error, PLUGIN_LISTS_BACKUP_DIR_NAME, Result, Setup, plugin_lists::PluginLists,
+        plugin_order, resolve_files,
+    },
+    formats::plugin::GameId,
+    repository::Profile,
+};
+
+const LIST_NAMES: [&str; 2] = ["plugins.txt", "loadorder.txt"];
+/// Plugin extensions of the Gamebryo games
+const EXTENSIONS: [&str; 2] = ["esm", "esp"];
+
+/// Deploys Oblivion, Fallout 3, New Vegas and Skyrim on top of a file based [`Deployer`].
+///
+/// Besides placing the files, every enabled plugin is listed in `plugins.txt` in the game's
+/// AppData directory. These engines order plugins by their modification time rather than by their
+/// position in the list, so the mods' plugins are given increasing timestamps after those of
+/// the base game's plugins, following the profile's plugin order. `loadorder.txt` isn't read by
+/// the games, but is written as well for the tools that expect it.
+pub struct GamebryoDeployer {
+    inner: Box<dyn Deployer>,
+    target: PathBuf,
+    mod_dirs: Vec<PathBuf>,
+    plugin_order: Vec<(String, bool)>,
+    lists: PluginLists,
+}
+
+impl GamebryoDeployer {
+    pub fn new(profile: &Profile, inner: Box<dyn Deployer>) -> Result<Self> {
+        Self::with_setup(profile, &Setup::new(profile)?, inner)
+    }
+
+    pub(crate) fn with_setup(
+        profile: &Profile,
+        setup: &Setup,
+        inner: Box<dyn Deployer>,
+    ) -> Result<Self> {
+        if setup.appdata_dir.as_os_str().is_empty() {
+            return Err(Error::NoAppDataDir);
+        }
+
+        Ok(Self {
+            inner,
+            target: setup.primary_target()?,
+            mod_dirs: setup.mod_dirs.clone(),
+            plugin_order: plugin_order(profile)?,
+            lists: PluginLists::new(
+                &setup.appdata_dir,
+                &profile.parent()?.dir()?.join(PLUGIN_LISTS_BACKUP_DIR_NAME),
+            ),
+        })
+    }
+}
+
+impl Deployer for GamebryoDeployer {
+    fn deploy(&mut self) -> Result<()> {
+        let base_plugins = plugins_in(&self.target, &EXTENSIONS)?;
+        let mod_plugins: Vec<(String, PathBuf)> = resolve_files(&self.mod_dirs)
+            .into_iter()
+            .filter_map(|(rel_path, source)| Some((plugin_name(&rel_path, &EXTENSIONS)?, source)))
+            .collect();
+        let mod_plugins =
+            apply_plugin_order(mod_plugins, |(name, _)| name.as_str(), &self.plugin_order);
+
+        let order = load_order(
+            base_plugins,
+            mod_plugins.iter().map(|(name, _)| name.clone()).collect(),
+        );
+        let timestamps: HashMap<&str, SystemTime> = order
+            .iter()
+            .map(|(name, time)| (name.as_str(), *time))
+            .collect();
+
+        // Overlays show the timestamps of the mod files, so they're set before deploying
+        for (name, source) in &mod_plugins {
+            if let Some(time) = timestamps.get(name.as_str()) {
+                set_modified(source, *time)?;
+            }
+        }
+
+        self.inner.deploy()?;
+
+        // Copies have timestamps of their own
+        for (name, _) in &mod_plugins {
+            if let Some(time) = timestamps.get(name.as_str()) {
+                set_modified(&self.target.join(name), *time)?;
+            }
+        }
+
+        // Disabled plugins are left out of the list of active plugins, but keep their place in
+        // the load order
+        let plugins: Vec<String> = order.into_iter().map(|(name, _)| name).collect();
+        let active = plugins
+            .iter()
+            .filter(|name| is_plugin_enabled(name, &self.plugin_order))
+            .cloned()
+            .collect();
+        self.lists
+            .write(&[("plugins.txt", active), ("loadorder.txt", plugins)])?;
+
+        Ok(())
+    }
+
+    fn undeploy(&mut self) -> Result<()> {
+        self.inner.undeploy()?;
+        self.lists.restore(&LIST_NAMES)?;
+
+        Ok(())
+    }
+
+    fn mount_points(&self) -> Vec<PathBuf> {
+        self.inner.mount_points()
+    }
+}
+
+/// Returns the file name of a plugin at the top of the data directory, or `None` if the path
+/// isn't one.
+pub(crate) fn plugin_name(rel_path: &Path, extensions: &[&str]) -> Option<String> {
+    let extension = rel_path.extension()?.to_str()?.to_lowercase();
+    if rel_path.components().count() != 1 || !extensions.contains(&extension.as_str()) {
+        return None;
+    }
+
+    rel_path.to_str().map(str::to_string)
+}
+
+/// Sort plugins by the profile's plugin order. Disabled plugins keep their place, so that the
+/// lists can still record them. Plugins that aren't part of the order yet keep their relative
+/// order after the others.
+pub(crate) fn apply_plugin_order<T>(
+    mut plugins: Vec<T>,
+    name: impl Fn(&T) -> &str,
+    order: &[(String, bool)],
+) -> Vec<T> {
+    plugins.sort_by_key(|plugin| {
+        order
+            .iter()
+            .position(|(p, _)| p.eq_ignore_ascii_case(name(plugin)))
+            .unwrap_or(usize::MAX)
+    });
+
+    plugins
+}
+
+/// Whether a plugin is enabled in the profile's plugin order. Plugins that aren't part of the
+/// order yet are.
+pub(crate) fn is_plugin_enabled(name: &str, order: &[(String, bool)]) -> bool {
+    order
+        .iter()
+        .find(|(p, _)| p.eq_ignore_ascii_case(name))
+        .is_none_or(|(_, enabled)| *enabled)
+}
+
+/// Whether a plugin loads before regular plugins, judging by its extension
+pub(crate) fn is_master(name: &str) -> bool {
+    let name = name.to_lowercase();
+    name.ends_with(".esm") || name.ends_with(".esl")
+}
+
+/// Returns the plugins directly inside `dir`, along with their modification times.
+pub(crate) fn plugins_in(dir: &Path, extensions: &[&str]) -> io::Result<Vec<(String, SystemTime)>> {
+    let mut plugins = Vec::new();
+
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let Some(name) = plugin_name(Path::new(&entry.file_name()), extensions) else {
+            continue;
+        };
+
+        plugins.push((name, entry.metadata()?.modified()?));
+    }
+
+    Ok(plugins)
+}
+
+/// Returns the names of the masters in a directory, or none if it can't be read.
+pub(crate) fn masters_in(dir: &Path) -> Vec<String> {
+    plugins_in(dir, &["esm"])
+        .map(|plugins| plugins.into_iter().map(|(name, _)| name).collect())
+        .unwrap_or_default()
+}
+
+/// Tell a Gamebryo game from the names of masters it has, like the implicit masters of its
+/// definition. Names are compared case-insensitively.
+pub(crate) fn gamebryo_game(masters: &[String]) -> Option<GameId> {
+    [
+        (GameId::Oblivion, "Oblivion.esm"),
+        (GameId::FalloutNV, "FalloutNV.esm"),
+        (GameId::Fallout3, "Fallout3.esm"),
+        (GameId::Skyrim, "Skyrim.esm"),
+    ]
+    .into_iter()
+    .find(|(_, base_master)| {
+        masters
+            .iter()
+            .any(|master| master.eq_ignore_ascii_case(base_master))
+    })
+    .map(|(game, _)| game)
+}
+
+/// Decide the load order and the timestamps that produce it. The base game's plugins keep their
+/// own timestamps and come first, followed by the mods' plugins in mod order, one minute apart.
+/// A mod plugin that replaces one of the base game's takes its place. Masters always load before
+/// other plugins, regardless of their timestamps.
+fn load_order(
+    mut base_plugins: Vec<(String, SystemTime)>,
+    mod_plugins: Vec<String>,
+) -> Vec<(String, SystemTime)> {
+    base_plugins.sort_by_key(|(_, time)| *time);
+    let latest = base_plugins
+        .last()
+        .map_or(SystemTime::UNIX_EPOCH, |(_, time)| *time);
+
+    let mut order: Vec<(String, SystemTime)> = Vec::new();
+    let mut minutes: u32 = 0;
+
+    for (name, time) in base_plugins {
+        // Use the spelling of a replacing mod plugin, since that's the file that's deployed
+        let name = mod_plugins
+            .iter()
+            .find(|p| p.eq_ignore_ascii_case(&name))
+            .cloned()
+            .unwrap_or(name);
+        order.push((name, time));
+    }
+
+    for name in mod_plugins {
+        if order.iter().any(|(p, _)| p.eq_ignore_ascii_case(&name)) {
+            continue;
+        }
+
+        minutes = minutes.saturating_add(1);
+        let time = latest
+            .checked_add(Duration::from_secs(60).saturating_mul(minutes))
+            .unwrap_or(latest);
+        order.push((name, time));
+    }
+
+    order.sort_by_key(|(name, _)| !is_master(name));
+
+    order
+}
+
+fn set_modified(path: &Path, time: SystemTime) -> io::Result<()> {
+    let file = File::open(path)?;
+    if file.metadata()?.modified()? != time {
+        file.set_modified(time)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn at(secs: u64) -> SystemTime {
+        SystemTime::UNIX_EPOCH
+            .checked_add(Duration::from_secs(secs))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_load_order() {
+        let base_plugins = vec![
+            ("DeadMoney.esm".to_string(), at(20)),
+            ("FalloutNV.esm".to_string(), at(10)),
+        ];
+        let mod_plugins = vec![
+            "Patch.esp".to_string(),
+            "deadmoney.esm".to_string(),
+            "Framework.esm".to_string(),
+        ];
+
+        let order = load_order(base_plugins, mod_plugins);
+
+        assert_eq!(
+            order,
+            vec![
+                ("FalloutNV.esm".to_string(), at(10)),
+                ("deadmoney.esm".to_string(), at(20)),
+                ("Framework.esm".to_string(), at(140)),
+                ("Patch.esp".to_string(), at(80)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_plugin_name() {
+        assert_eq!(
+            plugin_name(Path::new("Mod.ESP"), &EXTENSIONS),
+            Some("Mod.ESP".to_string())
+        );
+        assert_eq!(
+            plugin_name(Path::new("optional/Mod.esp"), &EXTENSIONS),
+            None
+        );
+        assert_eq!(plugin_name(Path::new("Mod.esl"), &EXTENSIONS), None);
+    }
+
+    #[test]
+    fn test_apply_plugin_order() {
+        let order = vec![
+            ("Patch.esp".to_string(), true),
+            ("Disabled.esp".to_string(), false),
+            ("Base.esm".to_string(), true),
+        ];
+        let plugins = ["New.esp", "base.esm", "Disabled.esp", "Patch.esp"];
+
+        assert_eq!(
+            apply_plugin_order(plugins.to_vec(), |p| *p, &order),
+            ["Patch.esp", "Disabled.esp", "base.esm", "New.esp"]
+        );
+        assert!(!is_plugin_enabled("disabled.esp", &order));
+        assert!(is_plugin_enabled("New.esp", &order));
+    }
+}
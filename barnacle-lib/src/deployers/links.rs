@@ -0,0 +1,335 @@
+use std::{
+    fs, io,
+    os::unix::fs::{MetadataExt, symlink},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    deployers::{
+        Deployer, Part, Result, Setup,
+        manifest::{Manifest, ManifestFile},
+    },
+    repository::Profile,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkKind {
+    Symlink,
+    Hardlink,
+}
+
+impl LinkKind {
+    /// Name of the file in a game's directory that links of this kind are recorded in
+    fn manifest_file_name(self) -> &'static str {
+        match self {
+            Self::Symlink => "symlinks.toml",
+            Self::Hardlink => "hardlinks.toml",
+        }
+    }
+
+    /// Name of the directory in a game's directory that original game files are moved to while
+    /// links of this kind replace them
+    fn backup_dir_name(self) -> &'static str {
+        match self {
+            Self::Symlink => "vanilla_symlinks",
+            Self::Hardlink => "vanilla_hardlinks",
+        }
+    }
+}
+
+/// Deploys by linking every file of the enabled mods into the target directory.
+///
+/// This works without OverlayFS support, e.g. inside containers or on NTFS/exFAT game
+/// libraries. Hard links additionally require the mods and the target to be on the same
+/// filesystem. A manifest of every link is kept in the game directory, so undeploying removes
+/// exactly those links and puts back any original files they replaced. Files created in the
+/// target while deployed are moved into the profile's overwrite directory.
+#[derive(Debug)]
+pub struct LinkDeployer {
+    kind: LinkKind,
+    target: PathBuf,
+    mod_dirs: Vec<PathBuf>,
+    part: Part,
+    manifest_path: PathBuf,
+    backup_dir: PathBuf,
+    overwrite_dir: PathBuf,
+}
+
+impl LinkDeployer {
+    pub fn new(profile: &Profile, kind: LinkKind) -> Result<Self> {
+        let setup = Setup::new(profile)?;
+
+        Self::with_mod_dirs(
+            profile,
+            kind,
+            setup.primary_target()?,
+            setup.mod_dirs,
+            Part::default(),
+        )
+    }
+
+    /// Link the given [`Part`] of the files of the given mod directories, lowest priority first,
+    /// into the given target instead of the game's.
+    pub(crate) fn with_mod_dirs(
+        profile: &Profile,
+        kind: LinkKind,
+        target: PathBuf,
+        mod_dirs: Vec<PathBuf>,
+        part: Part,
+    ) -> Result<Self> {
+        let game_dir = profile.parent()?.dir()?;
+
+        Ok(Self {
+            kind,
+            target,
+            mod_dirs,
+            manifest_path: game_dir.join(part.file_name(kind.manifest_file_name())),
+            backup_dir: game_dir.join(part.file_name(kind.backup_dir_name())),
+            part,
+            overwrite_dir: profile.overwrite_dir()?,
+        })
+    }
+
+    fn link(&self, source: &Path, dest: &Path) -> io::Result<()> {
+        match self.kind {
+            LinkKind::Symlink => symlink(source, dest),
+            LinkKind::Hardlink => fs::hard_link(source, dest),
+        }
+    }
+
+    /// Whether `dest` is still the link that was created to `source`
+    fn is_own_link(&self, dest: &Path, source: &Path) -> bool {
+        match self.kind {
+            LinkKind::Symlink => fs::read_link(dest).is_ok_and(|t| t == source),
+            LinkKind::Hardlink => {
+                match (fs::symlink_metadata(dest), fs::symlink_metadata(source)) {
+                    (Ok(dest), Ok(source)) => {
+                        dest.dev() == source.dev() && dest.ino() == source.ino()
+                    }
+                    _ => false,
+                }
+            }
+        }
+    }
+
+    fn place_files(&self, manifest: &mut Manifest) -> io::Result<()> {
+        for file in manifest.files.clone() {
+            let dest = manifest.prepare(&file.path, &self.backup_dir)?;
+
+            self.link(&file.source, &dest)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Deployer for LinkDeployer {
+    fn deploy(&mut self) -> Result<()> {
+        if Manifest::load(&self.manifest_path)?.is_some() {
+            self.undeploy()?;
+        }
+
+        // Every link is recorded before any of them are created, so that a deployment that's cut
+        // short can be undone like any other
+        let mut manifest = Manifest::new(&self.target);
+        for (path, source) in self.part.resolve_files(&self.mod_dirs) {
+            manifest.add(ManifestFile {
+                path,
+                source,
+                hash: None,
+            });
+        }
+        manifest.save(&self.manifest_path)?;
+
+        if let Err(e) = self.place_files(&mut manifest) {
+            // Don't leave a half finished deployment behind
+            self.undeploy()?;
+            return Err(e.into());
+        }
+
+        manifest.save(&self.manifest_path)
+    }
+
+    fn undeploy(&mut self) -> Result<()> {
+        let Some(manifest) = Manifest::load(&self.manifest_path)? else {
+            return Ok(());
+        };
+
+        if self.part.collects_new_files() {
+            manifest.collect_new_files(&self.overwrite_dir)?;
+        }
+        manifest.restore(&self.backup_dir, |dest, file| {
+            self.is_own_link(dest, &file.source)
+        })?;
+        fs::remove_file(&self.manifest_path)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use tempfile::tempdir;
+
+    use super::*;
+
+    #[test]
+    fn test_deploy_undeploy() {
+        let dir = tempdir().unwrap();
+        let target = dir.path().join("game");
+        let mods = [dir.path().join("mod_a"), dir.path().join("mod_b")];
+
+        fs::create_dir_all(&target).unwrap();
+        fs::write(target.join("game.esm"), "vanilla").unwrap();
+        for mod_dir in &mods {
+            fs::create_dir_all(mod_dir.join("textures")).unwrap();
+            fs::write(mod_dir.join("textures/rock.dds"), mod_dir.to_str().unwrap()).unwrap();
+        }
+        let top_mod = mods.last().unwrap();
+        fs::write(top_mod.join("game.esm"), "modded").unwrap();
+
+        for kind in [LinkKind::Symlink, LinkKind::Hardlink] {
+            let mut deployer = LinkDeployer {
+                kind,
+                target: target.clone(),
+                mod_dirs: mods.to_vec(),
+                part: Part::default(),
+                manifest_path: dir.path().join(kind.manifest_file_name()),
+                backup_dir: dir.path().join(kind.backup_dir_name()),
+                overwrite_dir: dir.path().join("overwrite"),
+            };
+
+            deployer.deploy().unwrap();
+
+            assert_eq!(
+                fs::read_to_string(target.join("textures/rock.dds")).unwrap(),
+                top_mod.to_str().unwrap()
+            );
+            assert_eq!(
+                fs::read_to_string(target.join("game.esm")).unwrap(),
+                "modded"
+            );
+
+            // Written by the game while deployed
+            fs::write(target.join("textures/lod.dds"), "lod").unwrap();
+            fs::write(target.join("skse.log"), "log").unwrap();
+
+            deployer.undeploy().unwrap();
+
+            assert!(!target.join("textures").exists());
+            assert!(!target.join("skse.log").exists());
+            assert_eq!(
+                fs::read_to_string(deployer.overwrite_dir.join("textures/lod.dds")).unwrap(),
+                "lod"
+            );
+            assert_eq!(
+                fs::read_to_string(deployer.overwrite_dir.join("skse.log")).unwrap(),
+                "log"
+            );
+            assert_eq!(
+                fs::read_to_string(target.join("game.esm")).unwrap(),
+                "vanilla"
+            );
+        }
+    }
+
+    #[test]
+    fn test_interrupted_deploy() {
+        let dir = tempdir().unwrap();
+        let target = dir.path().join("game");
+        let mod_dir = dir.path().join("mod");
+
+        fs::create_dir_all(&target).unwrap();
+        fs::create_dir_all(mod_dir.join("textures")).unwrap();
+        fs::write(target.join("game.esm"), "vanilla").unwrap();
+        fs::write(mod_dir.join("game.esm"), "modded").unwrap();
+        fs::write(mod_dir.join("textures/rock.dds"), "rock").unwrap();
+
+        let mut deployer = LinkDeployer {
+            kind: LinkKind::Symlink,
+            target: target.clone(),
+            mod_dirs: vec![mod_dir.clone()],
+            part: Part::default(),
+            manifest_path: dir.path().join(LinkKind::Symlink.manifest_file_name()),
+            backup_dir: dir.path().join(LinkKind::Symlink.backup_dir_name()),
+            overwrite_dir: dir.path().join("overwrite"),
+        };
+
+        // Everything is recorded, but the deployment stops after placing the first file
+        let mut manifest = Manifest::new(&target);
+        for (path, source) in deployer.part.resolve_files(&deployer.mod_dirs) {
+            manifest.add(ManifestFile {
+                path,
+                source,
+                hash: None,
+            });
+        }
+        manifest.save(&deployer.manifest_path).unwrap();
+        let first = manifest.files.first().unwrap().clone();
+        let dest = manifest.prepare(&first.path, &deployer.backup_dir).unwrap();
+        deployer.link(&first.source, &dest).unwrap();
+
+        deployer.undeploy().unwrap();
+
+        assert!(!target.join("textures").exists());
+        assert_eq!(
+            fs::read_to_string(target.join("game.esm")).unwrap(),
+            "vanilla"
+        );
+        assert!(!deployer.manifest_path.exists());
+    }
+
+    #[test]
+    fn test_root_files() {
+        let dir = tempdir().unwrap();
+        let install_dir = dir.path().join("game");
+        let data_dir = install_dir.join("Data");
+        let mod_dir = dir.path().join("mod");
+
+        fs::create_dir_all(&data_dir).unwrap();
+        fs::create_dir_all(mod_dir.join("textures")).unwrap();
+        fs::write(install_dir.join("skse64_loader.exe"), "old loader").unwrap();
+        fs::write(mod_dir.join("skse64_loader.exe"), "loader").unwrap();
+        fs::write(mod_dir.join("textures/rock.dds"), "rock").unwrap();
+
+        let root_files = vec!["skse64_*".to_string()];
+        let deployer = |target: &Path, part: Part| LinkDeployer {
+            kind: LinkKind::Symlink,
+            target: target.to_path_buf(),
+            mod_dirs: vec![mod_dir.clone()],
+            manifest_path: dir
+                .path()
+                .join(part.file_name(LinkKind::Symlink.manifest_file_name())),
+            backup_dir: dir
+                .path()
+                .join(part.file_name(LinkKind::Symlink.backup_dir_name())),
+            overwrite_dir: dir.path().join("overwrite"),
+            part,
+        };
+        let mut data = deployer(&data_dir, Part::Data(root_files.clone()));
+        let mut root = deployer(&install_dir, Part::Root(root_files));
+
+        data.deploy().unwrap();
+        root.deploy().unwrap();
+
+        assert_eq!(
+            fs::read_to_string(install_dir.join("skse64_loader.exe")).unwrap(),
+            "loader"
+        );
+        assert!(!data_dir.join("skse64_loader.exe").exists());
+        assert!(data_dir.join("textures/rock.dds").is_symlink());
+
+        // Written by the game next to its executable, which isn't the profile's to collect
+        fs::write(install_dir.join("skse64.log"), "log").unwrap();
+
+        root.undeploy().unwrap();
+        data.undeploy().unwrap();
+
+        assert_eq!(
+            fs::read_to_string(install_dir.join("skse64_loader.exe")).unwrap(),
+            "old loader"
+        );
+        assert!(install_dir.join("skse64.log").is_file());
+        assert!(!data_dir.join("textures").exists());
+    }
+}
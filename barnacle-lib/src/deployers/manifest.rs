@@ -0,0 +1,273 @@
+use std::{
+    collections::HashSet,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::{conflicts::list_files, deployers::Result, fs::move_file};
+
+/// Keeps track of every change a file based deployer made to a target directory, so that
+/// undeploying can revert exactly those changes and nothing else.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub(crate) struct Manifest {
+    /// The directory that was deployed to
+    pub target: PathBuf,
+    /// Files placed in the target
+    pub files: Vec<ManifestFile>,
+    /// Original files that were moved to the backup directory to make room, relative to the
+    /// target
+    pub shadowed: Vec<PathBuf>,
+    /// Directories that had to be created in the target, relative to it, parents first
+    pub created_dirs: Vec<PathBuf>,
+    /// Files that were in the target before it was deployed to, relative to it
+    #[serde(default)]
+    pub existing: Vec<PathBuf>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct ManifestFile {
+    /// Location of the file, relative to the target
+    pub path: PathBuf,
+    /// The mod file it was created from
+    pub source: PathBuf,
+    /// Hash of the file's contents, for deployers that place copies
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hash: Option<String>,
+}
+
+impl Manifest {
+    pub fn new(target: &Path) -> Self {
+        Self {
+            target: target.to_path_buf(),
+            existing: list_files(target),
+            ..Default::default()
+        }
+    }
+
+    /// Load the manifest at `path`, if there is one.
+    pub fn load(path: &Path) -> Result<Option<Self>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        Ok(Some(toml::from_str(&fs::read_to_string(path)?)?))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        fs::write(path, toml::to_string_pretty(self)?)?;
+
+        Ok(())
+    }
+
+    /// Record a file that's about to be placed in the target, along with the directories that
+    /// have to be created for it and the original file it will shadow. Saving the manifest
+    /// before placing any of the files means an interrupted deployment can still be undone.
+    pub fn add(&mut self, file: ManifestFile) {
+        if let Some(parent) = file.path.parent() {
+            let mut rel_dir = PathBuf::new();
+
+            for component in parent.components() {
+                rel_dir.push(component);
+
+                if !self.target.join(&rel_dir).exists() && !self.created_dirs.contains(&rel_dir) {
+                    self.created_dirs.push(rel_dir.clone());
+                }
+            }
+        }
+
+        if self.target.join(&file.path).symlink_metadata().is_ok() {
+            self.shadowed.push(file.path.clone());
+        }
+
+        self.files.push(file);
+    }
+
+    /// Make room for a new file at `rel_path` in the target. Missing parent directories are
+    /// created, and an existing file is moved into `backup_dir`. Both are recorded, unless
+    /// [`Manifest::add`] already did. Returns the absolute path the new file should be created
+    /// at.
+    pub fn prepare(&mut self, rel_path: &Path, backup_dir: &Path) -> io::Result<PathBuf> {
+        let dest = self.target.join(rel_path);
+
+        if let Some(parent) = rel_path.parent() {
+            let mut rel_dir = PathBuf::new();
+
+            for component in parent.components() {
+                rel_dir.push(component);
+
+                let dir = self.target.join(&rel_dir);
+                if !dir.exists() {
+                    fs::create_dir(&dir)?;
+                    if !self.created_dirs.contains(&rel_dir) {
+                        self.created_dirs.push(rel_dir.clone());
+                    }
+                }
+            }
+        }
+
+        if dest.symlink_metadata().is_ok() {
+            let backup = backup_dir.join(rel_path);
+            if let Some(parent) = backup.parent() {
+                fs::create_dir_all(parent)?;
+            }
+
+            move_file(&dest, &backup)?;
+            if !self.shadowed.iter().any(|p| p == rel_path) {
+                self.shadowed.push(rel_path.to_path_buf());
+            }
+        }
+
+        Ok(dest)
+    }
+
+    /// Move the files that were created in the target since it was deployed to, other than the
+    /// deployed files themselves, into `dir`. What the game and its tools write to the target
+    /// then ends up in the profile's overwrite directory, the same as with OverlayFS.
+    pub fn collect_new_files(&self, dir: &Path) -> io::Result<()> {
+        let known: HashSet<&Path> = self
+            .existing
+            .iter()
+            .chain(self.files.iter().map(|f| &f.path))
+            .map(PathBuf::as_path)
+            .collect();
+
+        for rel_path in list_files(&self.target) {
+            if known.contains(rel_path.as_path()) {
+                continue;
+            }
+
+            let dest = dir.join(&rel_path);
+            if let Some(parent) = dest.parent() {
+                fs::create_dir_all(parent)?;
+            }
+
+            move_file(&self.target.join(&rel_path), &dest)?;
+        }
+
+        Ok(())
+    }
+
+    /// Revert the changes recorded in the manifest. Files for which `is_unchanged` returns false
+    /// were modified after they were deployed, so they're left alone, along with any original
+    /// file they shadow. Changes that were recorded but never made, because the deployment was
+    /// interrupted, are skipped.
+    pub fn restore(
+        &self,
+        backup_dir: &Path,
+        is_unchanged: impl Fn(&Path, &ManifestFile) -> bool,
+    ) -> io::Result<()> {
+        let mut complete = true;
+        let is_backed_up = |rel_path: &Path| backup_dir.join(rel_path).symlink_metadata().is_ok();
+
+        for file in &self.files {
+            let dest = self.target.join(&file.path);
+
+            // Either the file was never placed, or the original was never moved out of its way
+            if dest.symlink_metadata().is_err()
+                || (self.shadowed.contains(&file.path) && !is_backed_up(&file.path))
+            {
+                continue;
+            }
+
+            if is_unchanged(&dest, file) {
+                fs::remove_file(&dest)?;
+            } else {
+                warn!(
+                    "Leaving {} in place, since it was changed after being deployed",
+                    dest.display()
+                );
+            }
+        }
+
+        for rel_path in &self.shadowed {
+            let dest = self.target.join(rel_path);
+
+            if !is_backed_up(rel_path) {
+                continue;
+            }
+
+            if dest.symlink_metadata().is_ok() {
+                warn!(
+                    "Not restoring original {}, since the path is occupied",
+                    dest.display()
+                );
+                complete = false;
+                continue;
+            }
+
+            move_file(&backup_dir.join(rel_path), &dest)?;
+        }
+
+        // Children come after their parents, and only empty directories can be removed, which
+        // keeps anything that was added to them since
+        for rel_dir in self.created_dirs.iter().rev() {
+            fs::remove_dir(self.target.join(rel_dir)).ok();
+        }
+
+        if complete && backup_dir.exists() {
+            fs::remove_dir_all(backup_dir)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use tempfile::tempdir;
+
+    use super::*;
+
+    #[test]
+    fn test_prepare_restore() {
+        let target = tempdir().unwrap();
+        let backup_root = tempdir().unwrap();
+        let backup_dir = backup_root.path().join("backup");
+
+        fs::write(target.path().join("game.esm"), "vanilla").unwrap();
+
+        let mut manifest = Manifest::new(target.path());
+
+        let dest = manifest
+            .prepare(Path::new("game.esm"), &backup_dir)
+            .unwrap();
+        fs::write(&dest, "modded").unwrap();
+        manifest.files.push(ManifestFile {
+            path: PathBuf::from("game.esm"),
+            source: PathBuf::new(),
+            hash: None,
+        });
+
+        let dest = manifest
+            .prepare(Path::new("textures/new/rock.dds"), &backup_dir)
+            .unwrap();
+        fs::write(&dest, "rock").unwrap();
+        manifest.files.push(ManifestFile {
+            path: PathBuf::from("textures/new/rock.dds"),
+            source: PathBuf::new(),
+            hash: None,
+        });
+
+        assert_eq!(manifest.shadowed, vec![PathBuf::from("game.esm")]);
+        assert_eq!(
+            manifest.created_dirs,
+            vec![PathBuf::from("textures"), PathBuf::from("textures/new")]
+        );
+        assert_eq!(
+            fs::read_to_string(backup_dir.join("game.esm")).unwrap(),
+            "vanilla"
+        );
+
+        manifest.restore(&backup_dir, |_, _| true).unwrap();
+
+        assert_eq!(
+            fs::read_to_string(target.path().join("game.esm")).unwrap(),
+            "vanilla"
+        );
+        assert!(!target.path().join("textures").exists());
+        assert!(!backup_dir.exists());
+    }
+}
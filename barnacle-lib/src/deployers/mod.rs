@@ -1 +1,316 @@
-pub mod generic;
+//! Deployers make the files of a [`Profile`]'s enabled mods show up in a game's target
+//! directory.
+//!
+//! Each [`DeployKind`](crate::repository::DeployKind) is backed by a [`Deployer`]. Deployers
+//! keep whatever state they need to undo a deployment on disk, so a deployer built from the
+//! same [`Setup`] later on, even after a crash, is able to undeploy it.
+
+use std::{
+    collections::BTreeMap,
+    io,
+    path::{Path, PathBuf},
+};
+
+use thiserror::Error;
+
+use crate::{
+    conflicts::list_files,
+    definitions::is_root_file,
+    formats,
+    repository::{DeployKind, DeployMethod, Profile, entities},
+};
+
+mod bg3;
+mod copy;
+mod creation_engine;
+mod gamebryo;
+mod links;
+mod manifest;
+mod openmw;
+mod overlay;
+mod plugin_lists;
+mod root_files;
+
+pub use bg3::Bg3Deployer;
+pub use copy::CopyDeployer;
+pub use creation_engine::CreationEngineDeployer;
+pub(crate) use creation_engine::CreationGame;
+pub use gamebryo::GamebryoDeployer;
+pub(crate) use gamebryo::{gamebryo_game, masters_in};
+pub use links::{LinkDeployer, LinkKind};
+pub use openmw::OpenMwDeployer;
+pub use overlay::OverlayDeployer;
+use root_files::RootFilesDeployer;
+
+/// Name of the directory in a game's directory that the user's own plugin lists are kept in
+/// while deployed
+const PLUGIN_LISTS_BACKUP_DIR_NAME: &str = "plugin_lists";
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("The game has no target directory to deploy to")]
+    NoTarget,
+    #[error("The game has no AppData directory to write plugin lists to")]
+    NoAppDataDir,
+    #[error("The target doesn't contain the base master of a supported game")]
+    UnknownGame,
+    #[error("Target {0} is already in use by another deployment")]
+    TargetInUse(PathBuf),
+    #[error("Failed to mount overlay: {0}")]
+    Mount(String),
+    #[error("Failed to read deployment manifest: {0}")]
+    ManifestRead(#[from] toml::de::Error),
+    #[error("Failed to write deployment manifest: {0}")]
+    ManifestWrite(#[from] toml::ser::Error),
+    #[error("Failed to read a game file: {0}")]
+    Format(#[from] formats::Error),
+    #[error("I/O error: {0}")]
+    Io(#[from] io::Error),
+    #[error("Entity error: {0}")]
+    Entity(#[from] entities::Error),
+}
+
+pub trait Deployer {
+    /// Make the mods visible in the target directory.
+    fn deploy(&mut self) -> Result<()>;
+
+    /// Restore the target directory to the state it was in before [`Deployer::deploy`].
+    fn undeploy(&mut self) -> Result<()>;
+
+    /// Directories this deployer mounts a filesystem over while deployed.
+    fn mount_points(&self) -> Vec<PathBuf> {
+        Vec::new()
+    }
+}
+
+/// What a deployment is made from: the game's settings and the profile's enabled mods at the
+/// time it's deployed. [`Deployment`](crate::repository::Deployment)s record it, so that they're
+/// undone the way they were made, even if the game or the profile has changed since.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Setup {
+    pub kind: DeployKind,
+    pub method: DeployMethod,
+    pub targets: Vec<PathBuf>,
+    /// The resolved AppData directory, empty if the game has none
+    pub appdata_dir: PathBuf,
+    /// Directories of the enabled mods, lowest priority first
+    pub mod_dirs: Vec<PathBuf>,
+    /// Patterns of the files at the top of a mod that are deployed to the install directory,
+    /// the one above the primary target
+    pub root_files: Vec<String>,
+}
+
+impl Setup {
+    /// Take the current setup of a [`Profile`] and its game.
+    pub fn new(profile: &Profile) -> Result<Self> {
+        let game = profile.parent()?;
+        let kind = game.deploy_kind()?;
+        let appdata_dir = game.appdata_dir()?;
+
+        let targets = match kind {
+            // OpenMW leaves the game's files alone, and writes to its configuration instead,
+            // which makes that the target no two deployments may share
+            DeployKind::OpenMW => vec![openmw::config_path(&appdata_dir)?],
+            _ => game.targets()?,
+        };
+
+        Ok(Self {
+            kind,
+            method: game.deploy_method()?,
+            targets,
+            appdata_dir,
+            mod_dirs: enabled_mod_dirs(profile)?,
+            root_files: game.root_files()?,
+        })
+    }
+
+    /// The directory mods are deployed to
+    pub fn primary_target(&self) -> Result<PathBuf> {
+        self.targets.first().cloned().ok_or(Error::NoTarget)
+    }
+}
+
+/// Create the [`Deployer`] appropriate for the [`Setup`]'s kind.
+pub(crate) fn new_deployer(profile: &Profile, setup: &Setup) -> Result<Box<dyn Deployer>> {
+    Ok(match setup.kind {
+        DeployKind::Gamebryo => Box::new(GamebryoDeployer::with_setup(
+            profile,
+            setup,
+            file_deployer(profile, setup, setup.mod_dirs.clone())?,
+        )?),
+        DeployKind::CreationEngine => Box::new(CreationEngineDeployer::with_setup(
+            profile,
+            setup,
+            file_deployer(profile, setup, setup.mod_dirs.clone())?,
+        )?),
+        DeployKind::OpenMW => Box::new(OpenMwDeployer::with_setup(setup)?),
+        DeployKind::BaldursGate3 => Box::new(Bg3Deployer::with_setup(profile, setup)?),
+        DeployKind::Overlay => file_deployer(profile, setup, setup.mod_dirs.clone())?,
+    })
+}
+
+/// Create the [`Deployer`] that places the files of the given mod directories, according to the
+/// [`Setup`]'s [`DeployMethod`]. Files matching the [`Setup`]'s root file patterns are placed in
+/// the install directory instead of the target.
+fn file_deployer(
+    profile: &Profile,
+    setup: &Setup,
+    mod_dirs: Vec<PathBuf>,
+) -> Result<Box<dyn Deployer>> {
+    let target = setup.primary_target()?;
+    if setup.root_files.is_empty() {
+        return part_deployer(profile, setup.method, target, mod_dirs, Part::default());
+    }
+
+    let install_dir = target.parent().ok_or(Error::NoTarget)?.to_path_buf();
+    let data = part_deployer(
+        profile,
+        setup.method,
+        target,
+        mod_dirs.clone(),
+        Part::Data(setup.root_files.clone()),
+    )?;
+    // Nothing is mounted over the install directory, so root files are linked instead
+    let root_method = match setup.method {
+        DeployMethod::Overlay => DeployMethod::Symlink,
+        method => method,
+    };
+    let root = part_deployer(
+        profile,
+        root_method,
+        install_dir,
+        mod_dirs,
+        Part::Root(setup.root_files.clone()),
+    )?;
+
+    Ok(Box::new(RootFilesDeployer::new(data, root)))
+}
+
+/// Create the [`Deployer`] that places a [`Part`] of the files of the given mod directories in
+/// the target with the given method. OverlayFS layers whole mod directories, so it places every
+/// file regardless of the part.
+fn part_deployer(
+    profile: &Profile,
+    method: DeployMethod,
+    target: PathBuf,
+    mod_dirs: Vec<PathBuf>,
+    part: Part,
+) -> Result<Box<dyn Deployer>> {
+    Ok(match method {
+        DeployMethod::Overlay => {
+            Box::new(OverlayDeployer::with_mod_dirs(profile, target, mod_dirs)?)
+        }
+        DeployMethod::Symlink => Box::new(LinkDeployer::with_mod_dirs(
+            profile,
+            LinkKind::Symlink,
+            target,
+            mod_dirs,
+            part,
+        )?),
+        DeployMethod::Hardlink => Box::new(LinkDeployer::with_mod_dirs(
+            profile,
+            LinkKind::Hardlink,
+            target,
+            mod_dirs,
+            part,
+        )?),
+        DeployMethod::Copy => Box::new(CopyDeployer::with_mod_dirs(
+            profile, target, mod_dirs, part,
+        )?),
+    })
+}
+
+/// Which of the mods' files a file based deployer places, split by the game's root file
+/// patterns.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Part {
+    /// The files that don't match the patterns, which is all of them without any patterns
+    Data(Vec<String>),
+    /// The files that match the patterns, which belong in the install directory
+    Root(Vec<String>),
+}
+
+impl Default for Part {
+    fn default() -> Self {
+        Self::Data(Vec::new())
+    }
+}
+
+impl Part {
+    /// Resolve the files of the given mod directories that are part of this, like
+    /// [`resolve_files`] does.
+    pub fn resolve_files(&self, mod_dirs: &[PathBuf]) -> Vec<(PathBuf, PathBuf)> {
+        let mut files = resolve_files(mod_dirs);
+        files.retain(|(rel_path, _)| self.contains(rel_path));
+
+        files
+    }
+
+    fn contains(&self, rel_path: &Path) -> bool {
+        match self {
+            Self::Data(patterns) => !is_root_file(patterns, rel_path),
+            Self::Root(patterns) => is_root_file(patterns, rel_path),
+        }
+    }
+
+    /// Name of a file or directory a deployer of this part keeps in the game's directory. The
+    /// root files get their own, so that both parts can be deployed at once.
+    pub fn file_name(&self, name: &str) -> String {
+        match self {
+            Self::Data(_) => name.to_string(),
+            Self::Root(_) => format!("root_{name}"),
+        }
+    }
+
+    /// Whether files created in the target while deployed are moved into the profile's
+    /// overwrite directory. Files created in the install directory belong to the game, not to
+    /// the data directory the overwrite directory stands for, so they're left alone.
+    pub fn collects_new_files(&self) -> bool {
+        matches!(self, Self::Data(_))
+    }
+}
+
+/// Returns the directories of the [`Profile`]'s enabled mods, lowest priority first.
+pub(crate) fn enabled_mod_dirs(profile: &Profile) -> Result<Vec<PathBuf>> {
+    let mut dirs = Vec::new();
+
+    for entry in profile.mod_entries()? {
+        if entry.enabled()? {
+            dirs.push(entry.mod_().dir()?);
+        }
+    }
+
+    Ok(dirs)
+}
+
+/// Returns the [`Profile`]'s plugin order, as the plugin names and whether they're enabled.
+pub(crate) fn plugin_order(profile: &Profile) -> Result<Vec<(String, bool)>> {
+    let mut order = Vec::new();
+
+    for entry in profile.plugin_entries()? {
+        order.push((entry.name()?, entry.enabled()?));
+    }
+
+    Ok(order)
+}
+
+/// Resolve which mod provides each file, given mod directories lowest priority first. Returns
+/// the path of every file relative to the mod directories, along with the path of the copy
+/// that wins. Paths are compared case-insensitively.
+pub(crate) fn resolve_files(mod_dirs: &[PathBuf]) -> Vec<(PathBuf, PathBuf)> {
+    let mut files = BTreeMap::new();
+
+    for dir in mod_dirs {
+        for rel_path in list_files(dir) {
+            let source = dir.join(&rel_path);
+            files.insert(
+                rel_path.to_string_lossy().to_lowercase(),
+                (rel_path, source),
+            );
+        }
+    }
+
+    files.into_values().collect()
+}
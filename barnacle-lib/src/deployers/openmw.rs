@@ -0,0 +1,224 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    deployers::{Deployer, Error, Result, Setup},
+    repository::Profile,
+};
+
+const SECTION_START: &str = "# Begin of section managed by Barnacle, changes will be lost";
+const SECTION_END: &str = "# End of section managed by Barnacle";
+
+/// Extensions of the content files OpenMW loads
+const CONTENT_EXTENSIONS: [&str; 5] = ["esm", "esp", "omwgame", "omwaddon", "omwscripts"];
+
+/// Deploys by adding the enabled mods to `openmw.cfg`, without mounting or placing any files.
+///
+/// Every mod directory becomes a `data=` entry in mod order, so later mods override the files of
+/// earlier ones. The content files and archives at the top of the mod directories are enabled
+/// with `content=` and `fallback-archive=` entries. Barnacle only ever touches its own section
+/// at the end of the file, which is removed again when undeploying.
+#[derive(Debug)]
+pub struct OpenMwDeployer {
+    config_path: PathBuf,
+    mod_dirs: Vec<PathBuf>,
+}
+
+impl OpenMwDeployer {
+    pub fn new(profile: &Profile) -> Result<Self> {
+        Self::with_setup(&Setup::new(profile)?)
+    }
+
+    /// The [`Setup`]'s only target is the `openmw.cfg` to write to, see [`config_path`].
+    pub(crate) fn with_setup(setup: &Setup) -> Result<Self> {
+        Ok(Self {
+            config_path: setup.primary_target()?,
+            mod_dirs: setup.mod_dirs.clone(),
+        })
+    }
+
+    fn read_config(&self) -> Result<String> {
+        if !self.config_path.exists() {
+            return Ok(String::new());
+        }
+
+        Ok(fs::read_to_string(&self.config_path)?)
+    }
+}
+
+/// Returns the `openmw.cfg` a game with the given AppData directory deploys to. The AppData
+/// directory doubles as OpenMW's configuration directory.
+pub(crate) fn config_path(appdata_dir: &Path) -> Result<PathBuf> {
+    let mut config_dir = appdata_dir.to_path_buf();
+    if config_dir.as_os_str().is_empty() {
+        config_dir = xdg::BaseDirectories::with_prefix("openmw")
+            .get_config_home()
+            .ok_or(Error::NoAppDataDir)?;
+    }
+
+    Ok(config_dir.join("openmw.cfg"))
+}
+
+impl Deployer for OpenMwDeployer {
+    fn deploy(&mut self) -> Result<()> {
+        let mut mods = Vec::new();
+        for dir in &self.mod_dirs {
+            let mut files = Vec::new();
+            for entry in fs::read_dir(dir)? {
+                let entry = entry?;
+                if entry.file_type()?.is_file() {
+                    files.push(entry.file_name().to_string_lossy().into_owned());
+                }
+            }
+            files.sort_by_key(|name| name.to_lowercase());
+
+            mods.push((dir.clone(), files));
+        }
+
+        let mut config = strip_section(&self.read_config()?);
+        config.push_str(&section(&mods, &config));
+
+        if let Some(parent) = self.config_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&self.config_path, config)?;
+
+        Ok(())
+    }
+
+    fn undeploy(&mut self) -> Result<()> {
+        if self.config_path.exists() {
+            fs::write(&self.config_path, strip_section(&self.read_config()?))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Remove Barnacle's section from the contents of `openmw.cfg`.
+fn strip_section(config: &str) -> String {
+    let mut stripped = String::new();
+    let mut in_section = false;
+
+    for line in config.lines() {
+        match line.trim() {
+            SECTION_START => in_section = true,
+            SECTION_END => in_section = false,
+            _ if !in_section => {
+                stripped.push_str(line);
+                stripped.push('\n');
+            }
+            _ => {}
+        }
+    }
+
+    stripped
+}
+
+/// Build Barnacle's section for the given mod directories and the names of the files at their
+/// top, lowest priority first. Content files and archives that the rest of the configuration
+/// already enables are skipped, since OpenMW refuses to load anything twice.
+fn section(mods: &[(PathBuf, Vec<String>)], config: &str) -> String {
+    let enabled = |key: &str, name: &str| {
+        config.lines().any(|line| {
+            line.split_once('=').is_some_and(|(k, v)| {
+                k.trim() == key && v.trim().trim_matches('"').eq_ignore_ascii_case(name)
+            })
+        })
+    };
+
+    let mut data = Vec::new();
+    let mut masters = Vec::new();
+    let mut content = Vec::new();
+    let mut archives = Vec::new();
+
+    for (dir, files) in mods {
+        data.push(format!("data={}", quote(dir)));
+
+        for name in files {
+            let extension = Path::new(name)
+                .extension()
+                .map(|e| e.to_string_lossy().to_lowercase())
+                .unwrap_or_default();
+
+            if extension == "bsa" && !enabled("fallback-archive", name) {
+                archives.push(format!("fallback-archive={name}"));
+            } else if CONTENT_EXTENSIONS.contains(&extension.as_str()) && !enabled("content", name)
+            {
+                // Game files and masters have to come before the plugins that depend on them
+                if extension == "esm" || extension == "omwgame" {
+                    masters.push(format!("content={name}"));
+                } else {
+                    content.push(format!("content={name}"));
+                }
+            }
+        }
+    }
+
+    let mut section = String::new();
+    for line in [SECTION_START.to_string()]
+        .into_iter()
+        .chain(data)
+        .chain(archives)
+        .chain(masters)
+        .chain(content)
+        .chain([SECTION_END.to_string()])
+    {
+        section.push_str(&line);
+        section.push('\n');
+    }
+
+    section
+}
+
+/// Quote a path the way OpenMW expects, escaping quotes and ampersands with an ampersand.
+fn quote(path: &Path) -> String {
+    let escaped = path
+        .to_string_lossy()
+        .replace('&', "&&")
+        .replace('"', "&\"");
+
+    format!("\"{escaped}\"")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_section() {
+        let config = "data=\"/games/Morrowind/Data Files\"\ncontent=Morrowind.esm\n";
+        let mods = vec![
+            (
+                PathBuf::from("/mods/patch & fix"),
+                vec!["Patch.esp".to_string(), "readme.txt".to_string()],
+            ),
+            (
+                PathBuf::from("/mods/tamriel_data"),
+                vec![
+                    "morrowind.esm".to_string(),
+                    "TR_Data.esm".to_string(),
+                    "TR_Data.bsa".to_string(),
+                ],
+            ),
+        ];
+
+        let deployed = format!("{config}{}", section(&mods, config));
+
+        assert_eq!(
+            deployed,
+            format!(
+                "{config}{SECTION_START}\n\
+                 data=\"/mods/patch && fix\"\n\
+                 data=\"/mods/tamriel_data\"\n\
+                 fallback-archive=TR_Data.bsa\n\
+                 content=TR_Data.esm\n\
+                 content=Patch.esp\n\
+                 {SECTION_END}\n"
+            )
+        );
+        assert_eq!(strip_section(&deployed), config);
+    }
+}
@@ -0,0 +1,98 @@
+use std::{
+    fs::create_dir_all,
+    iter::once,
+    path::{Path, PathBuf},
+};
+
+use damascus::{Filesystem, OverlayFs, overlay::OverlayFsOption};
+
+use crate::{
+    deployers::{Deployer, Error, Result, Setup},
+    repository::Profile,
+};
+
+/// Deploys directly to the game directory with OverlayFS.
+///
+/// The game directory becomes the lowest layer, with every enabled mod layered on top of it in
+/// order. Anything written to the target while deployed ends up in the profile's overwrite
+/// directory instead of the game directory.
+#[derive(Debug)]
+pub struct OverlayDeployer {
+    target: PathBuf,
+    lower_dirs: Vec<PathBuf>,
+    upper_dir: PathBuf,
+    work_dir: PathBuf,
+}
+
+impl OverlayDeployer {
+    pub fn new(profile: &Profile) -> Result<Self> {
+        let setup = Setup::new(profile)?;
+
+        Self::with_mod_dirs(profile, setup.primary_target()?, setup.mod_dirs)
+    }
+
+    /// Layer the given mod directories, lowest priority first, over the given target instead of
+    /// the game's.
+    pub(crate) fn with_mod_dirs(
+        profile: &Profile,
+        target: PathBuf,
+        mod_dirs: Vec<PathBuf>,
+    ) -> Result<Self> {
+        let lower_dirs = once(target.clone()).chain(mod_dirs).collect();
+
+        // Initialize overlay directories if missing
+        let upper_dir = profile.overwrite_dir()?;
+        let work_dir = profile.dir()?.join("overlay").join("work");
+        create_dir_all(&upper_dir)?;
+        create_dir_all(&work_dir)?;
+
+        Ok(Self {
+            target,
+            lower_dirs,
+            upper_dir,
+            work_dir,
+        })
+    }
+
+    fn overlay(&self) -> Result<OverlayFs> {
+        let mut overlay = OverlayFs::new(
+            self.lower_dirs.iter().map(PathBuf::as_path),
+            Some(self.upper_dir.as_path()),
+            Some(self.work_dir.as_path()),
+            self.target.as_path(),
+            true,
+        )
+        .map_err(|e| Error::Mount(e.to_string()))?;
+
+        overlay.set_option(OverlayFsOption::UserXattr);
+
+        Ok(overlay)
+    }
+
+    /// Directory that collects files written to the target while deployed
+    pub fn upper_dir(&self) -> &Path {
+        &self.upper_dir
+    }
+}
+
+impl Deployer for OverlayDeployer {
+    fn deploy(&mut self) -> Result<()> {
+        self.overlay()?
+            .mount()
+            .map_err(|e| Error::Mount(e.to_string()))?;
+
+        Ok(())
+    }
+
+    fn undeploy(&mut self) -> Result<()> {
+        self.overlay()?
+            .unmount()
+            .map_err(|e| Error::Mount(e.to_string()))?;
+
+        Ok(())
+    }
+
+    fn mount_points(&self) -> Vec<PathBuf> {
+        vec![self.target.clone()]
+    }
+}
@@ -0,0 +1,143 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+/// Writes the plugin lists a game reads from its AppData directory, keeping the user's own
+/// lists in a backup until they're restored.
+#[derive(Debug)]
+pub(crate) struct PluginLists {
+    dir: PathBuf,
+    backup_dir: PathBuf,
+}
+
+impl PluginLists {
+    pub fn new(dir: &Path, backup_dir: &Path) -> Self {
+        Self {
+            dir: dir.to_path_buf(),
+            backup_dir: backup_dir.to_path_buf(),
+        }
+    }
+
+    /// Write every list as one line per plugin. The existing lists are backed up the first time
+    /// around, so writing again while deployed keeps the original backup.
+    pub fn write(&self, lists: &[(&str, Vec<String>)]) -> io::Result<()> {
+        let files: Vec<(&str, String)> = lists
+            .iter()
+            .map(|(name, lines)| {
+                let mut contents = String::new();
+                for line in lines {
+                    contents.push_str(line);
+                    contents.push_str("\r\n");
+                }
+
+                (*name, contents)
+            })
+            .collect();
+
+        self.write_files(&files)
+    }
+
+    /// Write the given files, named relative to the directory, backing up the existing ones the
+    /// same way as [`PluginLists::write`].
+    pub fn write_files(&self, files: &[(&str, String)]) -> io::Result<()> {
+        if !self.backup_dir.exists() {
+            for (name, _) in files {
+                let path = self.dir.join(name);
+                if path.exists() {
+                    copy_file(&path, &self.backup_dir.join(name))?;
+                }
+            }
+            fs::create_dir_all(&self.backup_dir)?;
+        }
+
+        for (name, contents) in files {
+            let path = self.dir.join(name);
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+
+            fs::write(path, contents)?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns the contents the file had before the first write, if it existed.
+    pub fn original(&self, name: &str) -> io::Result<Option<String>> {
+        let path = if self.backup_dir.exists() {
+            self.backup_dir.join(name)
+        } else {
+            self.dir.join(name)
+        };
+
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        fs::read_to_string(path).map(Some)
+    }
+
+    /// Put back the lists that existed before the first [`PluginLists::write`], removing the
+    /// ones that didn't.
+    pub fn restore(&self, names: &[&str]) -> io::Result<()> {
+        if !self.backup_dir.exists() {
+            return Ok(());
+        }
+
+        for name in names {
+            let backup = self.backup_dir.join(name);
+            let path = self.dir.join(name);
+
+            if backup.exists() {
+                copy_file(&backup, &path)?;
+            } else if path.exists() {
+                fs::remove_file(&path)?;
+            }
+        }
+
+        fs::remove_dir_all(&self.backup_dir)
+    }
+}
+
+fn copy_file(from: &Path, to: &Path) -> io::Result<()> {
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    fs::copy(from, to).map(|_| ())
+}
+
+#[cfg(test)]
+mod test {
+    use tempfile::tempdir;
+
+    use super::*;
+
+    #[test]
+    fn test_write_restore() {
+        let dir = tempdir().unwrap();
+        let appdata_dir = dir.path().join("FalloutNV");
+        let lists = PluginLists::new(&appdata_dir, &dir.path().join("backup"));
+
+        fs::create_dir_all(&appdata_dir).unwrap();
+        fs::write(appdata_dir.join("plugins.txt"), "FalloutNV.esm\r\n").unwrap();
+
+        let plugins = vec!["FalloutNV.esm".to_string(), "Mod.esp".to_string()];
+        lists
+            .write(&[("plugins.txt", plugins.clone()), ("loadorder.txt", plugins)])
+            .unwrap();
+        // Writing again must not replace the backup of the user's own list
+        lists
+            .write(&[("plugins.txt", Vec::new()), ("loadorder.txt", Vec::new())])
+            .unwrap();
+
+        lists.restore(&["plugins.txt", "loadorder.txt"]).unwrap();
+
+        assert_eq!(
+            fs::read_to_string(appdata_dir.join("plugins.txt")).unwrap(),
+            "FalloutNV.esm\r\n"
+        );
+        assert!(!appdata_dir.join("loadorder.txt").exists());
+    }
+}
@@ -0,0 +1,42 @@
+use std::path::PathBuf;
+
+use crate::deployers::{Deployer, Result};
+
+/// Deploys the files of the mods that belong in the game's install directory, like script
+/// extender loaders, alongside the deployment of the rest of the files to the target.
+///
+/// Each part is placed by a file based [`Deployer`] of its own, set up with the matching
+/// [`Part`](crate::deployers::Part) of the files.
+pub(crate) struct RootFilesDeployer {
+    data: Box<dyn Deployer>,
+    root: Box<dyn Deployer>,
+}
+
+impl RootFilesDeployer {
+    pub fn new(data: Box<dyn Deployer>, root: Box<dyn Deployer>) -> Self {
+        Self { data, root }
+    }
+}
+
+impl Deployer for RootFilesDeployer {
+    fn deploy(&mut self) -> Result<()> {
+        self.data.deploy()?;
+
+        if let Err(e) = self.root.deploy() {
+            // Don't leave a half finished deployment behind
+            self.data.undeploy()?;
+            return Err(e);
+        }
+
+        Ok(())
+    }
+
+    fn undeploy(&mut self) -> Result<()> {
+        self.root.undeploy()?;
+        self.data.undeploy()
+    }
+
+    fn mount_points(&self) -> Vec<PathBuf> {
+        self.data.mount_points()
+    }
+}
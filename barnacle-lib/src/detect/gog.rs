@@ -0,0 +1,137 @@
+//! GOG games, found through the `goggame-<id>.info` file GOG's installers put in the install
+//! directory.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde::Deserialize;
+use tracing::warn;
+
+use crate::{
+    definitions::Definition,
+    detect::{self, DetectedGame, Error, Result, Source},
+    runtime::Runtime,
+};
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GameInfo {
+    game_id: String,
+}
+
+/// Directories games are commonly installed into, each holding one directory per game.
+pub fn games_dirs(home: &Path) -> Vec<PathBuf> {
+    ["GOG Games", "Games"]
+        .iter()
+        .map(|dir| home.join(dir))
+        .filter(|dir| dir.is_dir())
+        .collect()
+}
+
+/// Find the supported games installed directly inside any of the given directories. Games
+/// installed inside a Wine prefix run through Wine.
+pub fn detect(games_dirs: &[PathBuf], definitions: &[Definition]) -> Result<Vec<DetectedGame>> {
+    let mut games = Vec::new();
+
+    for games_dir in games_dirs {
+        for entry in fs::read_dir(games_dir)? {
+            let install_dir = entry?.path();
+            if !install_dir.is_dir() {
+                continue;
+            }
+
+            match detect_game(&install_dir, definitions) {
+                Ok(Some(game)) => games.push(game),
+                Ok(None) => {}
+                Err(e) => warn!("Skipping {}: {e}", install_dir.display()),
+            }
+        }
+    }
+    games.sort_by(|a, b| a.definition.name.cmp(&b.definition.name));
+
+    Ok(games)
+}
+
+fn detect_game(install_dir: &Path, definitions: &[Definition]) -> Result<Option<DetectedGame>> {
+    for entry in fs::read_dir(install_dir)? {
+        let path = entry?.path();
+        let is_info = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| name.starts_with("goggame-") && name.ends_with(".info"));
+        if !is_info {
+            continue;
+        }
+
+        let text = fs::read_to_string(&path)?;
+        let info: GameInfo = serde_json::from_str(&text).map_err(|e| Error::Json(path, e))?;
+        let Ok(game_id) = info.game_id.parse::<u64>() else {
+            continue;
+        };
+        let Some(definition) = definitions.iter().find(|d| d.gog_ids.contains(&game_id)) else {
+            continue;
+        };
+
+        let runtime = match detect::wine_prefix(install_dir) {
+            Some(prefix) => Runtime::Wine {
+                binary: PathBuf::new(),
+                prefix: prefix.to_path_buf(),
+            },
+            None => Runtime::Native,
+        };
+
+        return Ok(Some(DetectedGame::new(
+            definition,
+            install_dir.to_path_buf(),
+            runtime,
+            Source::Gog { game_id },
+        )));
+    }
+
+    Ok(None)
+}
+
+#[cfg(test)]
+mod test {
+    use tempfile::tempdir;
+
+    use crate::{definitions, repository::DeployKind};
+
+    use super::*;
+
+    #[test]
+    fn test_detect() {
+        let prefix = tempdir().unwrap();
+        let games_dir = prefix.path().join("drive_c/GOG Games");
+        let install_dir = games_dir.join("Baldurs Gate 3");
+        fs::create_dir_all(&install_dir).unwrap();
+        fs::create_dir_all(games_dir.join("Empty")).unwrap();
+        fs::write(
+            install_dir.join("goggame-1456460669.info"),
+            r#"{ "gameId": "1456460669", "name": "Baldur's Gate 3", "rootGameId": "1456460669" }"#,
+        )
+        .unwrap();
+
+        let games = detect(&[games_dir], &definitions::builtin()).unwrap();
+        assert_eq!(games.len(), 1);
+
+        let game = games.first().unwrap();
+        assert_eq!(game.definition.deploy_kind, DeployKind::BaldursGate3);
+        assert_eq!(game.target(), install_dir.join("Data"));
+        assert_eq!(
+            game.source,
+            Source::Gog {
+                game_id: 1456460669
+            }
+        );
+        assert_eq!(
+            game.runtime,
+            Runtime::Wine {
+                binary: PathBuf::new(),
+                prefix: prefix.path().to_path_buf(),
+            }
+        );
+    }
+}
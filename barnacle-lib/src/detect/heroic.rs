@@ -0,0 +1,273 @@
+//! Games installed through Heroic, from its GOG and Epic (Legendary) install lists.
+
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, de::DeserializeOwned};
+
+use crate::{
+    definitions::Definition,
+    detect::{DetectedGame, Error, Result, Source},
+    runtime::Runtime,
+};
+
+#[derive(Debug, Deserialize)]
+struct GogInstalled {
+    installed: Vec<GogEntry>,
+}
+
+/// An entry of Heroic's GOG install list, which mixes snake and camel case keys
+#[derive(Debug, Deserialize)]
+struct GogEntry {
+    #[serde(rename = "appName")]
+    app_name: String,
+    install_path: PathBuf,
+    #[serde(default)]
+    is_dlc: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct LegendaryEntry {
+    app_name: String,
+    install_path: PathBuf,
+    #[serde(default)]
+    is_dlc: bool,
+}
+
+/// A game's settings in `GamesConfig`
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GameConfig {
+    wine_prefix: Option<PathBuf>,
+    wine_version: Option<WineVersion>,
+}
+
+#[derive(Debug, Deserialize)]
+struct WineVersion {
+    #[serde(default)]
+    bin: PathBuf,
+    #[serde(rename = "type", default)]
+    kind: String,
+}
+
+/// Where Heroic keeps its configuration, for native and Flatpak installs.
+pub fn config_dirs(home: &Path) -> Vec<PathBuf> {
+    [
+        ".config/heroic",
+        ".var/app/com.heroicgameslauncher.hgl/config/heroic",
+    ]
+    .iter()
+    .map(|dir| home.join(dir))
+    .filter(|dir| dir.is_dir())
+    .collect()
+}
+
+/// Find the supported games in Heroic's GOG and Epic install lists.
+pub fn detect(config_dir: &Path, definitions: &[Definition]) -> Result<Vec<DetectedGame>> {
+    let mut installed = Vec::new();
+
+    let gog = config_dir.join("gog_store/installed.json");
+    if gog.exists() {
+        let list: GogInstalled = read(&gog)?;
+        installed.extend(
+            list.installed
+                .into_iter()
+                .filter(|e| !e.is_dlc)
+                .filter_map(|e| {
+                    let game_id: u64 = e.app_name.parse().ok()?;
+                    let definition = definitions.iter().find(|d| d.gog_ids.contains(&game_id))?;
+                    Some((definition, e.app_name, e.install_path))
+                }),
+        );
+    }
+
+    let legendary = config_dir.join("legendaryConfig/legendary/installed.json");
+    if legendary.exists() {
+        let list: HashMap<String, LegendaryEntry> = read(&legendary)?;
+        installed.extend(list.into_values().filter(|e| !e.is_dlc).filter_map(|e| {
+            let definition = definitions
+                .iter()
+                .find(|d| d.epic_app_names.contains(&e.app_name))?;
+            Some((definition, e.app_name, e.install_path))
+        }));
+    }
+
+    let mut games = Vec::new();
+    for (definition, app_name, install_dir) in installed {
+        if !install_dir.is_dir() {
+            continue;
+        }
+        let runtime = runtime(config_dir, &app_name)?;
+        games.push(DetectedGame::new(
+            definition,
+            install_dir,
+            runtime,
+            Source::Heroic { app_name },
+        ));
+    }
+    games.sort_by(|a, b| a.definition.name.cmp(&b.definition.name));
+
+    Ok(games)
+}
+
+/// Returns how Heroic runs a game, from its settings in `GamesConfig`. Games without settings
+/// run natively.
+fn runtime(config_dir: &Path, app_name: &str) -> Result<Runtime> {
+    let path = config_dir.join(format!("GamesConfig/{app_name}.json"));
+    if !path.exists() {
+        return Ok(Runtime::Native);
+    }
+
+    let mut configs: HashMap<String, serde_json::Value> = read(&path)?;
+    let Some(config) = configs.remove(app_name) else {
+        return Ok(Runtime::Native);
+    };
+    let config: GameConfig =
+        serde_json::from_value(config).map_err(|e| Error::Json(path.clone(), e))?;
+
+    let Some(prefix) = config.wine_prefix else {
+        return Ok(Runtime::Native);
+    };
+    let version = config.wine_version.unwrap_or(WineVersion {
+        bin: PathBuf::new(),
+        kind: String::new(),
+    });
+
+    Ok(if version.kind == "proton" {
+        // Heroic uses the prefix as Proton's compat data directory
+        Runtime::Proton {
+            proton_dir: version
+                .bin
+                .parent()
+                .map(Path::to_path_buf)
+                .unwrap_or_default(),
+            compat_data: prefix,
+            app_id: 0,
+            steam_dir: PathBuf::new(),
+        }
+    } else {
+        Runtime::Wine {
+            binary: version.bin,
+            prefix,
+        }
+    })
+}
+
+fn read<T: DeserializeOwned>(path: &Path) -> Result<T> {
+    let text = fs::read_to_string(path)?;
+    serde_json::from_str(&text).map_err(|e| Error::Json(path.to_path_buf(), e))
+}
+
+#[cfg(test)]
+mod test {
+    use tempfile::tempdir;
+
+    use crate::{definitions, repository::DeployKind};
+
+    use super::*;
+
+    #[test]
+    fn test_detect() {
+        let config = tempdir().unwrap();
+        let games_dir = tempdir().unwrap();
+        let new_vegas = games_dir.path().join("Fallout New Vegas");
+        let fallout_3 = games_dir.path().join("Fallout 3");
+        fs::create_dir_all(&new_vegas).unwrap();
+        fs::create_dir_all(&fallout_3).unwrap();
+
+        fs::create_dir_all(config.path().join("gog_store")).unwrap();
+        fs::write(
+            config.path().join("gog_store/installed.json"),
+            serde_json::json!({
+                "installed": [
+                    {
+                        "platform": "windows",
+                        "executable": "",
+                        "install_path": new_vegas,
+                        "install_size": "10.13 GiB",
+                        "is_dlc": false,
+                        "version": "1.4.0.525",
+                        "appName": "1454587428",
+                        "installedWith": "gogdl",
+                        "language": "en-US",
+                        "versionEtag": "\"2d64bd0d3e8a5be8e3f0a16e1d6a9ed6\"",
+                        "buildId": "51346934412359466",
+                        "pinnedVersion": false
+                    },
+                    {
+                        "platform": "windows",
+                        "executable": "",
+                        "install_path": games_dir.path(),
+                        "install_size": "0 MiB",
+                        "is_dlc": false,
+                        "version": "1.0",
+                        "appName": "1",
+                        "installedWith": "gogdl",
+                        "language": "en-US",
+                        "versionEtag": "",
+                        "buildId": "",
+                        "pinnedVersion": false
+                    },
+                ]
+            })
+            .to_string(),
+        )
+        .unwrap();
+
+        fs::create_dir_all(config.path().join("legendaryConfig/legendary")).unwrap();
+        fs::write(
+            config
+                .path()
+                .join("legendaryConfig/legendary/installed.json"),
+            serde_json::json!({
+                "adeae8bbfc94427db57c7dfecce3f1d4": {
+                    "app_name": "adeae8bbfc94427db57c7dfecce3f1d4",
+                    "install_path": fallout_3,
+                    "title": "Fallout 3: Game of the Year Edition",
+                }
+            })
+            .to_string(),
+        )
+        .unwrap();
+
+        fs::create_dir_all(config.path().join("GamesConfig")).unwrap();
+        fs::write(
+            config.path().join("GamesConfig/1454587428.json"),
+            serde_json::json!({
+                "1454587428": {
+                    "winePrefix": "/prefixes/New Vegas",
+                    "wineVersion": { "bin": "/usr/bin/wine", "name": "Wine", "type": "wine" },
+                },
+                "version": "v0",
+            })
+            .to_string(),
+        )
+        .unwrap();
+
+        let games = detect(config.path(), &definitions::builtin()).unwrap();
+        assert_eq!(games.len(), 2);
+
+        let fallout_3_game = games.first().unwrap();
+        assert_eq!(fallout_3_game.target(), fallout_3.join("Data"));
+        assert_eq!(fallout_3_game.runtime, Runtime::Native);
+
+        let new_vegas_game = games.last().unwrap();
+        assert_eq!(new_vegas_game.definition.deploy_kind, DeployKind::Gamebryo);
+        assert_eq!(
+            new_vegas_game.source,
+            Source::Heroic {
+                app_name: "1454587428".to_string()
+            }
+        );
+        assert_eq!(
+            new_vegas_game.runtime,
+            Runtime::Wine {
+                binary: PathBuf::from("/usr/bin/wine"),
+                prefix: PathBuf::from("/prefixes/New Vegas"),
+            }
+        );
+    }
+}
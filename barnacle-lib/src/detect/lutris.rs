@@ -0,0 +1,373 @@
+//! Games installed through Lutris, from its `pga.db` game list and each game's YAML config.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    slice,
+};
+
+use rusqlite::{Connection, OpenFlags};
+use serde::Deserialize;
+use tracing::warn;
+
+use crate::{
+    definitions::Definition,
+    detect::{self, DetectedGame, Error, Result, Source},
+    runtime::Runtime,
+};
+
+/// A row of the `games` table
+#[derive(Debug)]
+struct Row {
+    slug: String,
+    runner: Option<String>,
+    directory: Option<PathBuf>,
+    config_path: Option<String>,
+    /// The store the game was imported from, like `steam` or `gog`
+    service: Option<String>,
+    service_id: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct GameConfig {
+    game: GameSection,
+    wine: WineSection,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct GameSection {
+    exe: Option<PathBuf>,
+    prefix: Option<PathBuf>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct WineSection {
+    version: Option<String>,
+}
+
+/// Where Lutris keeps its data and its config, for native and Flatpak installs.
+pub fn data_dirs(home: &Path) -> Vec<(PathBuf, PathBuf)> {
+    [
+        (".local/share/lutris", ".config/lutris"),
+        (
+            ".var/app/net.lutris.Lutris/data/lutris",
+            ".var/app/net.lutris.Lutris/config/lutris",
+        ),
+    ]
+    .iter()
+    .map(|(data_dir, config_dir)| (home.join(data_dir), home.join(config_dir)))
+    .filter(|(data_dir, _)| data_dir.join("pga.db").is_file())
+    .collect()
+}
+
+/// Find the supported games among the installed ones in Lutris's database.
+pub fn detect(
+    data_dir: &Path,
+    config_dir: &Path,
+    definitions: &[Definition],
+) -> Result<Vec<DetectedGame>> {
+    let db =
+        Connection::open_with_flags(data_dir.join("pga.db"), OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    let mut statement = db.prepare(
+        "SELECT slug, runner, directory, configpath, service, service_id FROM games \
+         WHERE installed = 1",
+    )?;
+    let rows = statement
+        .query_map([], |row| {
+            Ok(Row {
+                slug: row.get(0)?,
+                runner: row.get(1)?,
+                directory: row.get::<_, Option<String>>(2)?.map(PathBuf::from),
+                config_path: row.get(3)?,
+                service: row.get(4)?,
+                service_id: row.get(5)?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    let mut games = Vec::new();
+    for row in rows {
+        let config = match row.config_path.as_deref() {
+            Some(config_path) => match game_config(data_dir, config_dir, config_path) {
+                Ok(config) => config,
+                Err(e) => {
+                    warn!("Skipping Lutris game {}: {e}", row.slug);
+                    continue;
+                }
+            },
+            None => GameConfig::default(),
+        };
+
+        let Some((definition, install_dir)) = locate(definitions, &row, &config) else {
+            continue;
+        };
+
+        let runtime = if row.runner.as_deref() == Some("wine") {
+            let prefix = config
+                .game
+                .prefix
+                .clone()
+                .or_else(|| detect::wine_prefix(&install_dir).map(Path::to_path_buf))
+                .unwrap_or_default();
+            Runtime::Wine {
+                binary: wine_binary(data_dir, config.wine.version.as_deref()),
+                prefix,
+            }
+        } else {
+            Runtime::Native
+        };
+
+        games.push(DetectedGame::new(
+            definition,
+            install_dir,
+            runtime,
+            Source::Lutris {
+                slug: row.slug.clone(),
+            },
+        ));
+    }
+    games.sort_by(|a, b| a.definition.name.cmp(&b.definition.name));
+
+    Ok(games)
+}
+
+/// Match a game by the store it was imported from, falling back to its slug.
+fn find_definition<'a>(definitions: &'a [Definition], row: &Row) -> Option<&'a Definition> {
+    let service_id = row.service_id.as_deref().unwrap_or_default();
+    let by_service = definitions.iter().find(|d| match row.service.as_deref() {
+        Some("steam") => service_id
+            .parse()
+            .is_ok_and(|id| d.steam_app_ids.contains(&id)),
+        Some("gog") => service_id.parse().is_ok_and(|id| d.gog_ids.contains(&id)),
+        Some("egs") => d.epic_app_names.iter().any(|name| name == service_id),
+        _ => false,
+    });
+
+    by_service.or_else(|| {
+        definitions
+            .iter()
+            .find(|d| d.lutris_slugs.contains(&row.slug))
+    })
+}
+
+/// Read a game's YAML config, which newer versions of Lutris keep in their data directory and
+/// older ones in their config directory.
+fn game_config(data_dir: &Path, config_dir: &Path, config_path: &str) -> Result<GameConfig> {
+    let file_name = format!("games/{config_path}.yml");
+    let Some(path) = [data_dir.join(&file_name), config_dir.join(&file_name)]
+        .into_iter()
+        .find(|path| path.is_file())
+    else {
+        return Ok(GameConfig::default());
+    };
+
+    let text = fs::read_to_string(&path)?;
+    serde_yaml::from_str(&text).map_err(|e| Error::Yaml(path, e))
+}
+
+/// Returns a game's definition and install directory. The install directory is what's left of
+/// the game's executable once the definition's path to it, like `bin/bg3.exe`, is stripped off.
+/// Games without a recognizable executable fall back to the game's directory.
+fn locate<'a>(
+    definitions: &'a [Definition],
+    row: &Row,
+    config: &GameConfig,
+) -> Option<(&'a Definition, PathBuf)> {
+    let known = find_definition(definitions, row);
+    let exe = match (&config.game.exe, &row.directory) {
+        (Some(exe), _) if exe.is_absolute() => Some(exe.clone()),
+        (Some(exe), Some(directory)) => Some(directory.join(exe)),
+        _ => None,
+    };
+
+    let by_exe = exe.and_then(|exe| {
+        known
+            .map_or(definitions, slice::from_ref)
+            .iter()
+            .find_map(|d| {
+                d.executables
+                    .iter()
+                    .find_map(|rel_path| strip_executable(&exe, rel_path))
+                    .map(|install_dir| (d, install_dir))
+            })
+    });
+
+    by_exe
+        .or_else(|| {
+            let install_dir = row.directory.clone()?;
+            // Games imported under another name can still be recognized by their executables
+            let definition = known.or_else(|| detect::by_executable(definitions, &install_dir))?;
+            Some((definition, install_dir))
+        })
+        .filter(|(_, install_dir)| install_dir.is_dir())
+}
+
+/// The directory an executable is in when it's at `rel_path` relative to it
+fn strip_executable(exe: &Path, rel_path: &Path) -> Option<PathBuf> {
+    if !exe.ends_with(rel_path) {
+        return None;
+    }
+
+    rel_path
+        .components()
+        .try_fold(exe, |path, _| path.parent())
+        .map(Path::to_path_buf)
+}
+
+/// The `wine` binary of one of Lutris's Wine versions. Empty for the system's.
+fn wine_binary(data_dir: &Path, version: Option<&str>) -> PathBuf {
+    version
+        .map(|version| data_dir.join("runners/wine").join(version).join("bin/wine"))
+        .filter(|binary| binary.is_file())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod test {
+    use tempfile::tempdir;
+
+    use crate::definitions;
+
+    use super::*;
+
+    #[test]
+    fn test_detect() {
+        let data_dir = tempdir().unwrap();
+        let prefix = tempdir().unwrap();
+        let install_dir = prefix
+            .path()
+            .join("drive_c/Program Files/Steam/steamapps/common/Skyrim Special Edition");
+        fs::create_dir_all(&install_dir).unwrap();
+        let fallout_dir = tempdir().unwrap();
+        fs::write(fallout_dir.path().join("Fallout4.exe"), "").unwrap();
+
+        let db = Connection::open(data_dir.path().join("pga.db")).unwrap();
+        db.execute_batch(
+            "CREATE TABLE games (id INTEGER PRIMARY KEY, name TEXT, slug TEXT, runner TEXT, \
+             directory TEXT, installed INTEGER, configpath TEXT, service TEXT, service_id TEXT);",
+        )
+        .unwrap();
+        let bg3_dir = prefix.path().join("drive_c/GOG Games/Baldurs Gate 3");
+        fs::create_dir_all(bg3_dir.join("bin")).unwrap();
+
+        let rows: [(&str, &str, &str, i64, &str); 5] = [
+            (
+                "the-elder-scrolls-v-skyrim-special-edition",
+                "wine",
+                "",
+                1,
+                "skyrim-1",
+            ),
+            ("fallout-4", "wine", "", 0, "fallout-2"),
+            (
+                "baldurs-gate-3",
+                "wine",
+                prefix.path().to_str().unwrap(),
+                1,
+                "bg3-5",
+            ),
+            ("some-other-game", "linux", "/games/other", 1, "other-3"),
+            (
+                "my-fallout",
+                "linux",
+                fallout_dir.path().to_str().unwrap(),
+                1,
+                "my-fallout-4",
+            ),
+        ];
+        for (slug, runner, directory, installed, config_path) in rows {
+            db.execute(
+                "INSERT INTO games (slug, runner, directory, installed, configpath) \
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                (slug, runner, directory, installed, config_path),
+            )
+            .unwrap();
+        }
+
+        fs::create_dir_all(data_dir.path().join("games")).unwrap();
+        fs::write(
+            data_dir.path().join("games/skyrim-1.yml"),
+            format!(
+                "game:\n  exe: {}\n  prefix: {}\nwine:\n  version: lutris-7.2\n",
+                install_dir.join("SkyrimSE.exe").display(),
+                prefix.path().display()
+            ),
+        )
+        .unwrap();
+
+        // Lutris's directory for a Wine game is its prefix, with the executable relative to it
+        fs::write(
+            data_dir.path().join("games/bg3-5.yml"),
+            format!(
+                "game:\n  exe: drive_c/GOG Games/Baldurs Gate 3/bin/bg3.exe\n  prefix: {}\n",
+                prefix.path().display()
+            ),
+        )
+        .unwrap();
+
+        let config_dir = tempdir().unwrap();
+        let games = detect(data_dir.path(), config_dir.path(), &definitions::builtin()).unwrap();
+        let [bg3, fallout, skyrim] = games.as_slice() else {
+            panic!("expected three games, found {games:?}");
+        };
+
+        assert_eq!(bg3.definition.id, "bg3");
+        assert_eq!(bg3.install_dir, bg3_dir);
+
+        // Recognized by its executable
+        assert_eq!(fallout.definition.id, "fallout4");
+        assert_eq!(fallout.install_dir, fallout_dir.path());
+        assert_eq!(fallout.runtime, Runtime::Native);
+
+        assert_eq!(skyrim.definition.name, "Skyrim Special Edition");
+        assert_eq!(skyrim.install_dir, install_dir);
+        assert_eq!(
+            skyrim.runtime,
+            Runtime::Wine {
+                binary: PathBuf::new(),
+                prefix: prefix.path().to_path_buf(),
+            }
+        );
+        assert_eq!(
+            skyrim.source,
+            Source::Lutris {
+                slug: "the-elder-scrolls-v-skyrim-special-edition".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn test_game_config() {
+        let data_dir = tempdir().unwrap();
+        let config_dir = tempdir().unwrap();
+        fs::create_dir_all(config_dir.path().join("games")).unwrap();
+        fs::write(
+            config_dir.path().join("games/skyrim-1.yml"),
+            "game:\n  exe: /games/skyrim/SkyrimSE.exe\n",
+        )
+        .unwrap();
+
+        // Older versions of Lutris keep configs in their config directory
+        let config = game_config(data_dir.path(), config_dir.path(), "skyrim-1").unwrap();
+        assert_eq!(
+            config.game.exe,
+            Some(PathBuf::from("/games/skyrim/SkyrimSE.exe"))
+        );
+
+        // The data directory takes precedence
+        fs::create_dir_all(data_dir.path().join("games")).unwrap();
+        fs::write(
+            data_dir.path().join("games/skyrim-1.yml"),
+            "game:\n  exe: /other/SkyrimSE.exe\n",
+        )
+        .unwrap();
+        let config = game_config(data_dir.path(), config_dir.path(), "skyrim-1").unwrap();
+        assert_eq!(config.game.exe, Some(PathBuf::from("/other/SkyrimSE.exe")));
+
+        let config = game_config(data_dir.path(), config_dir.path(), "missing").unwrap();
+        assert_eq!(config.game.exe, None);
+    }
+}
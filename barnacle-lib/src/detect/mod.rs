@@ -0,0 +1,158 @@
+//! Detection of installed games, from the files launchers keep about them.
+//!
+//! Detected games are matched against the [`Definition`]s of the games Barnacle knows how to
+//! mod, and can be added to the [`Repository`](crate::Repository) as they are.
+
+use std::{
+    env, io,
+    path::{Path, PathBuf},
+};
+
+use thiserror::Error;
+use tracing::warn;
+
+use crate::{definitions::Definition, formats, runtime::Runtime};
+
+pub mod gog;
+pub mod heroic;
+pub mod lutris;
+pub mod steam;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Failed to parse {0}: {1}")]
+    Parse(PathBuf, formats::Error),
+    #[error("Failed to parse {0}: {1}")]
+    Json(PathBuf, serde_json::Error),
+    #[error("Failed to parse {0}: {1}")]
+    Yaml(PathBuf, serde_yaml::Error),
+    #[error("Database error: {0}")]
+    Database(#[from] rusqlite::Error),
+    #[error("I/O error: {0}")]
+    Io(#[from] io::Error),
+}
+
+/// Where a game was found.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Source {
+    Steam {
+        app_id: u32,
+    },
+    /// Heroic's GOG or Epic library, by the store's name for the game
+    Heroic {
+        app_name: String,
+    },
+    Lutris {
+        slug: String,
+    },
+    /// A `goggame-*.info` file in the install directory
+    Gog {
+        game_id: u64,
+    },
+}
+
+/// An installed game that Barnacle knows how to mod.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DetectedGame {
+    pub definition: Definition,
+    /// The directory the game is installed in
+    pub install_dir: PathBuf,
+    pub runtime: Runtime,
+    pub source: Source,
+}
+
+impl DetectedGame {
+    pub(crate) fn new(
+        definition: &Definition,
+        install_dir: PathBuf,
+        runtime: Runtime,
+        source: Source,
+    ) -> Self {
+        Self {
+            definition: definition.clone(),
+            install_dir,
+            runtime,
+            source,
+        }
+    }
+
+    /// The directory to deploy mods to
+    pub fn target(&self) -> PathBuf {
+        self.install_dir.join(&self.definition.data_dir)
+    }
+}
+
+/// Find the installed games of every supported launcher that match one of the given
+/// definitions. Launchers whose files can't be read are skipped, and a game found by several
+/// launchers is only returned once.
+pub fn detect(definitions: &[Definition]) -> Vec<DetectedGame> {
+    let Some(home) = env::var_os("HOME").map(PathBuf::from) else {
+        return Vec::new();
+    };
+
+    let mut found = Vec::new();
+    for steam_dir in steam::steam_dirs(&home) {
+        found.push((
+            "Steam",
+            steam_dir.clone(),
+            steam::detect(&steam_dir, definitions),
+        ));
+    }
+    for config_dir in heroic::config_dirs(&home) {
+        found.push((
+            "Heroic",
+            config_dir.clone(),
+            heroic::detect(&config_dir, definitions),
+        ));
+    }
+    for (data_dir, config_dir) in lutris::data_dirs(&home) {
+        found.push((
+            "Lutris",
+            data_dir.clone(),
+            lutris::detect(&data_dir, &config_dir, definitions),
+        ));
+    }
+    let gog_dirs = gog::games_dirs(&home);
+    found.push(("GOG", home.clone(), gog::detect(&gog_dirs, definitions)));
+
+    let mut games: Vec<DetectedGame> = Vec::new();
+    for (launcher, dir, result) in found {
+        match result {
+            Ok(detected) => {
+                for game in detected {
+                    if !games.iter().any(|g| g.install_dir == game.install_dir) {
+                        games.push(game);
+                    }
+                }
+            }
+            Err(e) => warn!(
+                "Failed to detect {launcher} games in {}: {e}",
+                dir.display()
+            ),
+        }
+    }
+
+    games
+}
+
+/// Returns the definition of the game installed in a directory, going by the executables it
+/// contains.
+pub(crate) fn by_executable<'a>(
+    definitions: &'a [Definition],
+    install_dir: &Path,
+) -> Option<&'a Definition> {
+    definitions.iter().find(|d| {
+        d.executables
+            .iter()
+            .any(|exe| install_dir.join(exe).is_file())
+    })
+}
+
+/// Returns the Wine prefix a path is inside of, the parent of its `drive_c` directory.
+pub(crate) fn wine_prefix(path: &Path) -> Option<&Path> {
+    path.ancestors()
+        .find(|dir| dir.file_name().is_some_and(|name| name == "drive_c"))?
+        .parent()
+}
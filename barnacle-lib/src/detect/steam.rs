@@ -0,0 +1,285 @@
+//! Steam games, found through `libraryfolders.vdf` and the app manifests in each library.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use tracing::warn;
+
+use crate::{
+    definitions::Definition,
+    detect::{DetectedGame, Error, Result, Source},
+    formats::vdf::{self, Value},
+    runtime::Runtime,
+};
+
+/// Where Steam is installed, for native and Flatpak installs.
+pub fn steam_dirs(home: &Path) -> Vec<PathBuf> {
+    let mut dirs: Vec<PathBuf> = [
+        ".steam/steam",
+        ".local/share/Steam",
+        ".var/app/com.valvesoftware.Steam/.local/share/Steam",
+    ]
+    .iter()
+    .map(|dir| home.join(dir))
+    .filter(|dir| dir.join("steamapps").is_dir())
+    .collect();
+
+    // `~/.steam/steam` is usually a symlink to `~/.local/share/Steam`
+    dedup_canonical(&mut dirs);
+
+    dirs
+}
+
+/// Remove the directories that are the same as an earlier one once symlinks are resolved.
+fn dedup_canonical(dirs: &mut Vec<PathBuf>) {
+    let mut seen = Vec::new();
+    dirs.retain(|dir| {
+        let canonical = fs::canonicalize(dir).unwrap_or_else(|_| dir.clone());
+        if seen.contains(&canonical) {
+            false
+        } else {
+            seen.push(canonical);
+            true
+        }
+    });
+}
+
+/// Find the supported games installed in any of the libraries of the given Steam installation.
+/// Manifests that can't be read are skipped.
+pub fn detect(steam_dir: &Path, definitions: &[Definition]) -> Result<Vec<DetectedGame>> {
+    let mut games = Vec::new();
+
+    for library in libraries(steam_dir)? {
+        let steamapps = library.join("steamapps");
+        let Ok(entries) = fs::read_dir(&steamapps) else {
+            warn!("Steam library {} can't be read", library.display());
+            continue;
+        };
+
+        for entry in entries {
+            let path = entry?.path();
+            let is_manifest = path.extension().is_some_and(|ext| ext == "acf")
+                && path
+                    .file_name()
+                    .and_then(|name| name.to_str())
+                    .is_some_and(|name| name.starts_with("appmanifest_"));
+            if !is_manifest {
+                continue;
+            }
+
+            match detect_app(steam_dir, &steamapps, &path, definitions) {
+                Ok(Some(game)) => games.push(game),
+                Ok(None) => {}
+                Err(e) => warn!("Skipping Steam app manifest: {e}"),
+            }
+        }
+    }
+
+    games.sort_by(|a, b| a.definition.name.cmp(&b.definition.name));
+
+    Ok(games)
+}
+
+/// Returns the library directories listed in `libraryfolders.vdf`. Steam's own directory is
+/// always a library, even when the file is missing.
+fn libraries(steam_dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut libraries = vec![steam_dir.to_path_buf()];
+
+    let path = steam_dir.join("steamapps/libraryfolders.vdf");
+    if !path.exists() {
+        return Ok(libraries);
+    }
+
+    let value = read(&path)?;
+    let folders = value
+        .get("libraryfolders")
+        .map(Value::entries)
+        .unwrap_or_default();
+    libraries.extend(
+        folders
+            .iter()
+            .filter_map(|(_, folder)| folder.get_str("path").map(PathBuf::from)),
+    );
+    // Steam lists its own directory by its real path, which the given one may be a symlink to
+    dedup_canonical(&mut libraries);
+
+    Ok(libraries)
+}
+
+fn detect_app(
+    steam_dir: &Path,
+    steamapps: &Path,
+    manifest: &Path,
+    definitions: &[Definition],
+) -> Result<Option<DetectedGame>> {
+    let value = read(manifest)?;
+    let state = value.get("AppState");
+    let field = |key| state.and_then(|state| state.get_str(key));
+
+    let (Some(app_id), Some(install_dir)) = (
+        field("appid").and_then(|id| id.parse::<u32>().ok()),
+        field("installdir"),
+    ) else {
+        return Err(Error::Parse(
+            manifest.to_path_buf(),
+            crate::formats::Error::Missing("appid or installdir", "app manifest"),
+        ));
+    };
+
+    let Some(definition) = definitions
+        .iter()
+        .find(|d| d.steam_app_ids.contains(&app_id))
+    else {
+        return Ok(None);
+    };
+
+    let install_dir = steamapps.join("common").join(install_dir);
+    if !install_dir.is_dir() {
+        return Ok(None);
+    }
+
+    let compat_data = steamapps.join("compatdata").join(app_id.to_string());
+    let runtime = if compat_data.is_dir() {
+        Runtime::Proton {
+            proton_dir: proton_dir(&compat_data).unwrap_or_default(),
+            compat_data,
+            app_id,
+            steam_dir: steam_dir.to_path_buf(),
+        }
+    } else {
+        Runtime::Native
+    };
+
+    Ok(Some(DetectedGame::new(
+        definition,
+        install_dir,
+        runtime,
+        Source::Steam { app_id },
+    )))
+}
+
+/// Returns the Proton version a game last ran with, from the `config_info` file Proton writes
+/// into its compat data. Its lines include paths inside the Proton directory's `files`.
+fn proton_dir(compat_data: &Path) -> Option<PathBuf> {
+    let config_info = fs::read_to_string(compat_data.join("config_info")).ok()?;
+
+    config_info
+        .lines()
+        .find_map(|line| line.split_once("/files/"))
+        .map(|(dir, _)| PathBuf::from(dir))
+}
+
+fn read(path: &Path) -> Result<Value> {
+    let text = fs::read_to_string(path)?;
+    vdf::parse(&text).map_err(|e| Error::Parse(path.to_path_buf(), e))
+}
+
+#[cfg(test)]
+mod test {
+    use tempfile::tempdir;
+
+    use crate::{definitions, repository::DeployKind};
+
+    use super::*;
+
+    fn manifest(steamapps: &Path, app_id: u32, install_dir: &str) {
+        fs::create_dir_all(steamapps.join("common").join(install_dir)).unwrap();
+        fs::write(
+            steamapps.join(format!("appmanifest_{app_id}.acf")),
+            format!(
+                "\"AppState\"\n{{\n\t\"appid\"\t\t\"{app_id}\"\n\t\"installdir\"\t\t\"{install_dir}\"\n}}\n"
+            ),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_detect() {
+        let steam = tempdir().unwrap();
+        let library = tempdir().unwrap();
+        let steamapps = steam.path().join("steamapps");
+        let library_steamapps = library.path().join("steamapps");
+
+        manifest(&steamapps, 489830, "Skyrim Special Edition");
+        manifest(&steamapps, 440, "Team Fortress 2");
+        manifest(&library_steamapps, 22320, "Morrowind");
+        fs::write(steamapps.join("appmanifest_1.acf"), "\"AppState\" {").unwrap();
+
+        let compat_data = steamapps.join("compatdata/489830");
+        fs::create_dir_all(&compat_data).unwrap();
+        fs::write(
+            compat_data.join("config_info"),
+            "Proton 9.0\n/steam/steamapps/common/Proton 9.0/files/share/fonts/\n",
+        )
+        .unwrap();
+
+        fs::write(
+            steamapps.join("libraryfolders.vdf"),
+            format!(
+                "\"libraryfolders\"\n{{\n\t\"0\"\n\t{{\n\t\t\"path\"\t\t\"{}\"\n\t}}\n\t\"1\"\n\t{{\n\t\t\"path\"\t\t\"{}\"\n\t}}\n}}\n",
+                steam.path().display(),
+                library.path().display()
+            ),
+        )
+        .unwrap();
+
+        let games = detect(steam.path(), &definitions::builtin()).unwrap();
+        assert_eq!(games.len(), 2);
+
+        let morrowind = games.first().unwrap();
+        assert_eq!(morrowind.definition.deploy_kind, DeployKind::OpenMW);
+        assert_eq!(
+            morrowind.target(),
+            library_steamapps.join("common/Morrowind/Data Files")
+        );
+        assert_eq!(morrowind.runtime, Runtime::Native);
+
+        let skyrim = games.last().unwrap();
+        assert_eq!(skyrim.definition.name, "Skyrim Special Edition");
+        assert_eq!(skyrim.source, Source::Steam { app_id: 489830 });
+        assert_eq!(
+            skyrim.install_dir,
+            steamapps.join("common/Skyrim Special Edition")
+        );
+        assert_eq!(
+            skyrim.runtime,
+            Runtime::Proton {
+                proton_dir: PathBuf::from("/steam/steamapps/common/Proton 9.0"),
+                compat_data,
+                app_id: 489830,
+                steam_dir: steam.path().to_path_buf(),
+            }
+        );
+    }
+    #[test]
+    fn test_symlinked_steam_dir() {
+        let home = tempdir().unwrap();
+        let steam = home.path().join(".local/share/Steam");
+        let link = home.path().join(".steam/steam");
+        let steamapps = steam.join("steamapps");
+
+        manifest(&steamapps, 489830, "Skyrim Special Edition");
+        fs::write(
+            steamapps.join("libraryfolders.vdf"),
+            format!(
+                "\"libraryfolders\"\n{{\n\t\"0\"\n\t{{\n\t\t\"path\"\t\t\"{}\"\n\t}}\n}}\n",
+                steam.display()
+            ),
+        )
+        .unwrap();
+        fs::create_dir_all(link.parent().unwrap()).unwrap();
+        std::os::unix::fs::symlink(&steam, &link).unwrap();
+
+        assert_eq!(steam_dirs(home.path()), vec![link.clone()]);
+
+        // The library Steam lists is the directory the symlink points to, not another one
+        let games = detect(&link, &definitions::builtin()).unwrap();
+        assert_eq!(games.len(), 1);
+        assert_eq!(
+            games.first().unwrap().install_dir,
+            link.join("steamapps/common/Skyrim Special Edition")
+        );
+    }
+}
@@ -0,0 +1,109 @@
+//! Bethesda's BA2 archives, the `.ba2` files of Fallout 4 and Starfield.
+//!
+//! Both general archives and texture archives end with a table of the names of their files, which
+//! the header points to. That's the only part that's read, so every version is supported.
+
+use std::{
+    fs::File,
+    io::{BufReader, Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+};
+
+use crate::formats::{Error, Result};
+
+const MAGIC: &[u8; 4] = b"BTDX";
+/// Size of the part of the header following the magic that all versions share
+const HEADER_SIZE: usize = 20;
+
+/// Returns the paths of the files stored in the archive at the given path, with forward slashes.
+pub fn list(path: &Path) -> Result<Vec<PathBuf>> {
+    let mut file = BufReader::new(File::open(path)?);
+
+    let mut magic = [0; 4];
+    file.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+        return Err(invalid());
+    }
+
+    let mut header = [0; HEADER_SIZE];
+    file.read_exact(&mut header)?;
+    let file_count = read_u32(&header, 8)?;
+    let name_table_offset = read_u64(&header, 12)?;
+
+    file.seek(SeekFrom::Start(name_table_offset))?;
+    let mut paths = Vec::new();
+    for _ in 0..file_count {
+        let mut length = [0; 2];
+        file.read_exact(&mut length)?;
+        let mut name = vec![0; usize::from(u16::from_le_bytes(length))];
+        file.read_exact(&mut name)?;
+
+        paths.push(PathBuf::from(
+            String::from_utf8_lossy(&name).replace('\\', "/"),
+        ));
+    }
+
+    Ok(paths)
+}
+
+fn invalid() -> Error {
+    Error::Invalid("BA2")
+}
+
+fn read_u32(bytes: &[u8], at: usize) -> Result<u32> {
+    let end = at.checked_add(4).ok_or_else(invalid)?;
+    let bytes = bytes.get(at..end).ok_or_else(invalid)?;
+
+    Ok(u32::from_le_bytes(bytes.try_into().map_err(|_| invalid())?))
+}
+
+fn read_u64(bytes: &[u8], at: usize) -> Result<u64> {
+    let end = at.checked_add(8).ok_or_else(invalid)?;
+    let bytes = bytes.get(at..end).ok_or_else(invalid)?;
+
+    Ok(u64::from_le_bytes(bytes.try_into().map_err(|_| invalid())?))
+}
+
+/// Build a general archive that lists the given files, without storing any of their contents.
+#[cfg(test)]
+pub(crate) fn build(path: &Path, files: &[&str]) {
+    let mut archive = MAGIC.to_vec();
+    archive.extend(1u32.to_le_bytes());
+    archive.extend(b"GNRL");
+    archive.extend(u32::try_from(files.len()).unwrap().to_le_bytes());
+    let name_table_offset = MAGIC.len().checked_add(HEADER_SIZE).unwrap();
+    archive.extend(u64::try_from(name_table_offset).unwrap().to_le_bytes());
+
+    for file in files {
+        let name = file.replace('/', "\\");
+        archive.extend(u16::try_from(name.len()).unwrap().to_le_bytes());
+        archive.extend(name.as_bytes());
+    }
+
+    std::fs::write(path, archive).unwrap();
+}
+
+#[cfg(test)]
+mod test {
+    use tempfile::tempdir;
+
+    use super::*;
+
+    #[test]
+    fn test_list() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("Test - Main.ba2");
+        build(&path, &["meshes/rock.nif", "textures/rock.dds"]);
+
+        assert_eq!(
+            list(&path).unwrap(),
+            [
+                PathBuf::from("meshes/rock.nif"),
+                PathBuf::from("textures/rock.dds"),
+            ]
+        );
+
+        std::fs::write(&path, b"BSA\0").unwrap();
+        assert!(matches!(list(&path), Err(Error::Invalid("BA2"))));
+    }
+}
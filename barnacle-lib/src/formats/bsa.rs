@@ -0,0 +1,201 @@
+//! Bethesda's BSA archives, the `.bsa` files of Oblivion, Fallout 3, New Vegas and Skyrim.
+//!
+//! Versions 103 (Oblivion), 104 (Fallout 3, New Vegas and Skyrim) and 105 (Skyrim Special
+//! Edition) are supported. Only the names of the stored files are read, which an archive keeps
+//! as a list of folders followed by the names of every file, in folder order.
+
+use std::{
+    fs::File,
+    io::{BufReader, Read},
+    path::{Path, PathBuf},
+};
+
+use crate::formats::{Error, Result};
+
+const MAGIC: &[u8; 4] = b"BSA\0";
+/// Size of the header following the magic
+const HEADER_SIZE: usize = 32;
+/// Size of a file record, which holds a name hash, a size and an offset
+const FILE_RECORD_SIZE: u64 = 16;
+/// Set on archives that store the names of their folders
+const FOLDER_NAMES_FLAG: u32 = 0x1;
+/// Set on archives that store the names of their files
+const FILE_NAMES_FLAG: u32 = 0x2;
+
+/// Returns the paths of the files stored in the archive at the given path, with forward slashes.
+pub fn list(path: &Path) -> Result<Vec<PathBuf>> {
+    let mut file = BufReader::new(File::open(path)?);
+
+    let mut magic = [0; 4];
+    file.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+        return Err(invalid());
+    }
+
+    let mut header = [0; HEADER_SIZE];
+    file.read_exact(&mut header)?;
+    let version = read_u32(&header, 0)?;
+    // Version 105 widened the offset of a folder's files to eight bytes, plus padding
+    let folder_record_size = match version {
+        103 | 104 => 16,
+        105 => 24,
+        _ => return Err(Error::UnsupportedVersion("BSA", version)),
+    };
+    let flags = read_u32(&header, 8)?;
+    if flags & FOLDER_NAMES_FLAG == 0 || flags & FILE_NAMES_FLAG == 0 {
+        return Err(Error::Missing("file names", "BSA"));
+    }
+    let folder_count = read_u32(&header, 12)?;
+    let file_names_size = read_u32(&header, 24)?;
+
+    let mut file_counts = Vec::new();
+    for _ in 0..folder_count {
+        let mut record = vec![0; folder_record_size];
+        file.read_exact(&mut record)?;
+        file_counts.push(read_u32(&record, 8)?);
+    }
+
+    // Each folder's name is followed by the records of its files
+    let mut folders = Vec::new();
+    for count in &file_counts {
+        let mut length = [0; 1];
+        file.read_exact(&mut length)?;
+        let mut name = vec![0; usize::from(length[0])];
+        file.read_exact(&mut name)?;
+        folders.push(string(&name));
+
+        let records = u64::from(*count)
+            .checked_mul(FILE_RECORD_SIZE)
+            .ok_or_else(invalid)?;
+        let skipped = std::io::copy(&mut file.by_ref().take(records), &mut std::io::sink())?;
+        if skipped != records {
+            return Err(invalid());
+        }
+    }
+
+    let mut names = Vec::new();
+    file.take(u64::from(file_names_size))
+        .read_to_end(&mut names)?;
+    let mut names = names.split(|b| *b == 0).map(string);
+
+    let mut paths = Vec::new();
+    for (folder, count) in folders.iter().zip(file_counts) {
+        for _ in 0..count {
+            let name = names.next().ok_or_else(invalid)?;
+            paths.push(Path::new(folder).join(name));
+        }
+    }
+
+    Ok(paths)
+}
+
+/// Decode a zero terminated name, turning its backslashes into forward slashes.
+fn string(bytes: &[u8]) -> String {
+    let len = bytes.iter().position(|b| *b == 0).unwrap_or(bytes.len());
+    let bytes = bytes.get(..len).unwrap_or_default();
+
+    String::from_utf8_lossy(bytes).replace('\\', "/")
+}
+
+fn invalid() -> Error {
+    Error::Invalid("BSA")
+}
+
+fn read_u32(bytes: &[u8], at: usize) -> Result<u32> {
+    let end = at.checked_add(4).ok_or_else(invalid)?;
+    let bytes = bytes.get(at..end).ok_or_else(invalid)?;
+
+    Ok(u32::from_le_bytes(bytes.try_into().map_err(|_| invalid())?))
+}
+
+/// Build an archive of the given version that lists the given files, all of them empty.
+#[cfg(test)]
+pub(crate) fn build(path: &Path, version: u32, files: &[&str]) {
+    use std::collections::BTreeMap;
+
+    let mut folders: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for file in files {
+        let (folder, name) = file.rsplit_once('/').unwrap();
+        folders
+            .entry(folder.replace('/', "\\"))
+            .or_default()
+            .push(name.to_string());
+    }
+
+    let mut records = Vec::new();
+    let mut blocks = Vec::new();
+    let mut names = Vec::new();
+    for (folder, files) in &folders {
+        records.extend(0u64.to_le_bytes());
+        records.extend(u32::try_from(files.len()).unwrap().to_le_bytes());
+        if version == 105 {
+            records.extend([0; 12]);
+        } else {
+            records.extend([0; 4]);
+        }
+
+        blocks.push(u8::try_from(folder.len().checked_add(1).unwrap()).unwrap());
+        blocks.extend(folder.as_bytes());
+        blocks.push(0);
+        for name in files {
+            blocks.extend([0; 16]);
+            names.extend(name.as_bytes());
+            names.push(0);
+        }
+    }
+
+    let mut archive = MAGIC.to_vec();
+    archive.extend(version.to_le_bytes());
+    archive.extend(36u32.to_le_bytes());
+    archive.extend((FOLDER_NAMES_FLAG | FILE_NAMES_FLAG).to_le_bytes());
+    archive.extend(u32::try_from(folders.len()).unwrap().to_le_bytes());
+    archive.extend(u32::try_from(files.len()).unwrap().to_le_bytes());
+    archive.extend(0u32.to_le_bytes());
+    archive.extend(u32::try_from(names.len()).unwrap().to_le_bytes());
+    archive.extend([0; 4]);
+    archive.extend(records);
+    archive.extend(blocks);
+    archive.extend(names);
+
+    std::fs::write(path, archive).unwrap();
+}
+
+#[cfg(test)]
+mod test {
+    use tempfile::tempdir;
+
+    use super::*;
+
+    #[test]
+    fn test_list() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("Test.bsa");
+
+        for version in [103, 104, 105] {
+            build(
+                &path,
+                version,
+                &[
+                    "meshes/rock.nif",
+                    "textures/rock.dds",
+                    "textures/rock_n.dds",
+                ],
+            );
+
+            assert_eq!(
+                list(&path).unwrap(),
+                [
+                    PathBuf::from("meshes/rock.nif"),
+                    PathBuf::from("textures/rock.dds"),
+                    PathBuf::from("textures/rock_n.dds"),
+                ]
+            );
+        }
+
+        build(&path, 0x100, &[]);
+        assert!(matches!(
+            list(&path),
+            Err(Error::UnsupportedVersion("BSA", 0x100))
+        ));
+    }
+}
@@ -0,0 +1,425 @@
+//! Larian's LSPK package format, the `.pak` files of Baldur's Gate 3.
+//!
+//! Only version 18 is supported, which is what the game and its modding tools have used since
+//! release. A package starts with a header pointing to a compressed table of files, each of which
+//! is stored on its own, possibly compressed, and possibly in another part of a multi-part
+//! package.
+
+use std::{
+    fs::{self, File},
+    io::{self, Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+};
+
+use flate2::read::ZlibDecoder;
+
+use crate::formats::{Error, Result};
+
+const MAGIC: &[u8; 4] = b"LSPK";
+const VERSION: u32 = 18;
+/// Size of the header following the magic
+const HEADER_SIZE: usize = 36;
+/// Size of a file table entry
+const ENTRY_SIZE: usize = 272;
+/// Size of the fixed, zero padded file name at the start of an entry
+const NAME_SIZE: usize = 256;
+/// LZ4 can't compress data to less than 1/255th of its size
+const LZ4_MAX_RATIO: usize = 255;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Compression {
+    None,
+    Zlib,
+    Lz4,
+}
+
+/// A file stored in a [`Package`].
+#[derive(Debug, Clone)]
+pub struct Entry {
+    /// Path of the file inside the package, with forward slashes
+    pub name: String,
+    offset: u64,
+    archive_part: u8,
+    compression: Compression,
+    size_on_disk: u32,
+    uncompressed_size: u32,
+}
+
+impl Entry {
+    /// Size of the file once extracted
+    pub fn size(&self) -> u32 {
+        match self.compression {
+            Compression::None => self.size_on_disk,
+            _ => self.uncompressed_size,
+        }
+    }
+}
+
+/// An opened LSPK package, with its file table read into memory.
+#[derive(Debug, Clone)]
+pub struct Package {
+    path: PathBuf,
+    entries: Vec<Entry>,
+}
+
+impl Package {
+    pub fn open(path: &Path) -> Result<Self> {
+        let mut file = File::open(path)?;
+
+        let mut magic = [0; 4];
+        file.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(Error::Invalid("LSPK"));
+        }
+
+        let mut header = [0; HEADER_SIZE];
+        file.read_exact(&mut header)?;
+        let version = read_u32(&header, 0)?;
+        if version != VERSION {
+            return Err(Error::UnsupportedVersion("LSPK", version));
+        }
+        let file_list_offset = read_u64(&header, 4)?;
+
+        file.seek(SeekFrom::Start(file_list_offset))?;
+        let mut counts = [0; 8];
+        file.read_exact(&mut counts)?;
+        let num_files = usize::try_from(read_u32(&counts, 0)?).map_err(|_| invalid())?;
+        let compressed_size = read_u32(&counts, 4)?;
+
+        let compressed = read_bytes(&mut file, compressed_size)?;
+        let table_size = num_files.checked_mul(ENTRY_SIZE).ok_or_else(invalid)?;
+        // The table is LZ4 compressed, except by some older tools which used zlib. Sizes that
+        // LZ4 couldn't have produced aren't allocated.
+        let table = if table_size <= compressed.len().saturating_mul(LZ4_MAX_RATIO) {
+            lz4_flex::block::decompress(&compressed, table_size).map_err(|e| e.to_string())
+        } else {
+            Err(format!("{num_files} files don't fit in the table"))
+        };
+        let table = table.or_else(|e| {
+            let mut table = Vec::new();
+            ZlibDecoder::new(compressed.as_slice())
+                .take(u64::try_from(table_size).map_err(|_| invalid())?)
+                .read_to_end(&mut table)
+                .map_err(|_| Error::Decompress(format!("LSPK file table: {e}")))?;
+            Ok::<_, Error>(table)
+        })?;
+
+        let entries = table
+            .chunks_exact(ENTRY_SIZE)
+            .map(parse_entry)
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            entries,
+        })
+    }
+
+    pub fn entries(&self) -> &[Entry] {
+        &self.entries
+    }
+
+    /// Returns the entry with the given name, compared case-insensitively.
+    pub fn entry(&self, name: &str) -> Option<&Entry> {
+        self.entries
+            .iter()
+            .find(|e| e.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Read and decompress the contents of a file in the package.
+    pub fn read(&self, entry: &Entry) -> Result<Vec<u8>> {
+        let mut file = File::open(self.part_path(entry.archive_part))?;
+        file.seek(SeekFrom::Start(entry.offset))?;
+
+        let data = read_bytes(&mut file, entry.size_on_disk)?;
+
+        // The sizes come from the package, so what's decompressed is limited to them rather than
+        // allocated up front
+        let size = usize::try_from(entry.uncompressed_size).map_err(|_| invalid())?;
+        let decompress = |reader: &mut dyn Read| {
+            let mut decompressed = Vec::new();
+            reader
+                .take(u64::from(entry.uncompressed_size))
+                .read_to_end(&mut decompressed)
+                .map_err(|e| Error::Decompress(format!("{}: {e}", entry.name)))?;
+            Ok(decompressed)
+        };
+
+        match entry.compression {
+            Compression::None => Ok(data),
+            Compression::Zlib => decompress(&mut ZlibDecoder::new(data.as_slice())),
+            // Files are usually stored as a single block, but bigger ones may be framed
+            Compression::Lz4 if size <= data.len().saturating_mul(LZ4_MAX_RATIO) => {
+                lz4_flex::block::decompress(&data, size).or_else(|_| {
+                    decompress(&mut lz4_flex::frame::FrameDecoder::new(data.as_slice()))
+                })
+            }
+            Compression::Lz4 => {
+                decompress(&mut lz4_flex::frame::FrameDecoder::new(data.as_slice()))
+            }
+        }
+    }
+
+    /// Big packages are split into `Name.pak`, `Name_1.pak`, `Name_2.pak` and so on.
+    fn part_path(&self, part: u8) -> PathBuf {
+        if part == 0 {
+            return self.path.clone();
+        }
+
+        let stem = self
+            .path
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        self.path.with_file_name(format!("{stem}_{part}.pak"))
+    }
+}
+
+/// Returns the packages at the top of a directory, sorted by name. Additional parts of
+/// multi-part packages are included.
+pub fn packages_in(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut packages = Vec::new();
+
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path
+            .extension()
+            .is_some_and(|e| e.eq_ignore_ascii_case("pak"))
+        {
+            packages.push(path);
+        }
+    }
+    packages.sort();
+
+    Ok(packages)
+}
+
+/// Whether `path` is an additional part of a multi-part package, like `Name_1.pak` next to
+/// `Name.pak`. Parts can't be opened on their own.
+pub fn is_part(path: &Path) -> bool {
+    let Some(stem) = path.file_stem().and_then(|s| s.to_str()) else {
+        return false;
+    };
+    let Some((base, part)) = stem.rsplit_once('_') else {
+        return false;
+    };
+
+    !part.is_empty()
+        && part.bytes().all(|b| b.is_ascii_digit())
+        && path.with_file_name(format!("{base}.pak")).exists()
+}
+
+fn parse_entry(bytes: &[u8]) -> Result<Entry> {
+    let name = bytes.get(..NAME_SIZE).ok_or_else(invalid)?;
+    let name_len = name.iter().position(|b| *b == 0).unwrap_or(NAME_SIZE);
+    let name = String::from_utf8_lossy(name.get(..name_len).ok_or_else(invalid)?).into_owned();
+
+    let offset_low = u64::from(read_u32(bytes, NAME_SIZE)?);
+    let offset_high = u64::from(read_u16(bytes, 260)?);
+    let archive_part = *bytes.get(262).ok_or_else(invalid)?;
+    let flags = *bytes.get(263).ok_or_else(invalid)?;
+
+    let compression = match flags & 0x0F {
+        0 => Compression::None,
+        1 => Compression::Zlib,
+        2 => Compression::Lz4,
+        _ => {
+            return Err(Error::Decompress(format!(
+                "{name}: unsupported compression"
+            )));
+        }
+    };
+
+    Ok(Entry {
+        name,
+        offset: offset_low | offset_high.checked_shl(32).ok_or_else(invalid)?,
+        archive_part,
+        compression,
+        size_on_disk: read_u32(bytes, 264)?,
+        uncompressed_size: read_u32(bytes, 268)?,
+    })
+}
+
+fn invalid() -> Error {
+    Error::Invalid("LSPK")
+}
+
+/// Read `len` bytes, as given by the package. Reading through [`Read::take`] means a made up
+/// length runs into the end of the file instead of being allocated.
+fn read_bytes(file: &mut File, len: u32) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    file.take(u64::from(len)).read_to_end(&mut bytes)?;
+
+    if u64::try_from(bytes.len()).ok() != Some(u64::from(len)) {
+        return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+    }
+
+    Ok(bytes)
+}
+
+fn read_u16(bytes: &[u8], at: usize) -> Result<u16> {
+    let end = at.checked_add(2).ok_or_else(invalid)?;
+    let bytes = bytes.get(at..end).ok_or_else(invalid)?;
+
+    Ok(u16::from_le_bytes(bytes.try_into().map_err(|_| invalid())?))
+}
+
+fn read_u32(bytes: &[u8], at: usize) -> Result<u32> {
+    let end = at.checked_add(4).ok_or_else(invalid)?;
+    let bytes = bytes.get(at..end).ok_or_else(invalid)?;
+
+    Ok(u32::from_le_bytes(bytes.try_into().map_err(|_| invalid())?))
+}
+
+fn read_u64(bytes: &[u8], at: usize) -> Result<u64> {
+    let end = at.checked_add(8).ok_or_else(invalid)?;
+    let bytes = bytes.get(at..end).ok_or_else(invalid)?;
+
+    Ok(u64::from_le_bytes(bytes.try_into().map_err(|_| invalid())?))
+}
+
+/// Build a version 18 package from the given files, stored with the given compression methods.
+#[cfg(test)]
+pub(crate) fn build(path: &Path, files: &[(&str, &[u8], u8)]) {
+    use std::io::Write;
+
+    use flate2::{Compression as Level, write::ZlibEncoder};
+
+    let mut data = Vec::new();
+    let mut table = Vec::new();
+    let data_start = u64::try_from(MAGIC.len().checked_add(HEADER_SIZE).unwrap()).unwrap();
+
+    for (name, contents, method) in files {
+        let stored = match method {
+            0 => contents.to_vec(),
+            1 => {
+                let mut encoder = ZlibEncoder::new(Vec::new(), Level::default());
+                encoder.write_all(contents).unwrap();
+                encoder.finish().unwrap()
+            }
+            _ => lz4_flex::block::compress(contents),
+        };
+        let offset = data_start
+            .checked_add(u64::try_from(data.len()).unwrap())
+            .unwrap();
+
+        let mut entry = name.as_bytes().to_vec();
+        entry.resize(NAME_SIZE, 0);
+        entry.extend(u32::try_from(offset & 0xFFFF_FFFF).unwrap().to_le_bytes());
+        entry.extend(u16::try_from(offset >> 32).unwrap().to_le_bytes());
+        entry.extend([0, *method]);
+        entry.extend(u32::try_from(stored.len()).unwrap().to_le_bytes());
+        let uncompressed_size = if *method == 0 { 0 } else { contents.len() };
+        entry.extend(u32::try_from(uncompressed_size).unwrap().to_le_bytes());
+
+        table.extend(entry);
+        data.extend(stored);
+    }
+
+    let compressed_table = lz4_flex::block::compress(&table);
+    let file_list_offset = data_start
+        .checked_add(u64::try_from(data.len()).unwrap())
+        .unwrap();
+
+    let mut package = MAGIC.to_vec();
+    package.extend(VERSION.to_le_bytes());
+    package.extend(file_list_offset.to_le_bytes());
+    package.extend(
+        u32::try_from(compressed_table.len().checked_add(8).unwrap())
+            .unwrap()
+            .to_le_bytes(),
+    );
+    package.extend([0, 0]);
+    package.extend([0; 16]);
+    package.extend(1u16.to_le_bytes());
+    package.extend(data);
+    package.extend(u32::try_from(files.len()).unwrap().to_le_bytes());
+    package.extend(u32::try_from(compressed_table.len()).unwrap().to_le_bytes());
+    package.extend(compressed_table);
+
+    fs::write(path, package).unwrap();
+}
+
+#[cfg(test)]
+mod test {
+    use tempfile::tempdir;
+
+    use super::*;
+
+    #[test]
+    fn test_read() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("Test.pak");
+        build(
+            &path,
+            &[
+                ("Mods/Test/meta.lsx", b"<save/>", 2),
+                ("Public/Test/readme.txt", b"zlib compressed", 1),
+                ("Public/Test/raw.txt", b"stored", 0),
+            ],
+        );
+
+        let package = Package::open(&path).unwrap();
+
+        assert_eq!(package.entries().len(), 3);
+        for (name, contents) in [
+            ("mods/test/META.lsx", "<save/>"),
+            ("Public/Test/readme.txt", "zlib compressed"),
+            ("Public/Test/raw.txt", "stored"),
+        ] {
+            let entry = package.entry(name).unwrap();
+            assert_eq!(package.read(entry).unwrap(), contents.as_bytes());
+            assert_eq!(entry.size(), u32::try_from(contents.len()).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_invalid() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("Test.pak");
+        fs::write(&path, b"PK\x03\x04 not a package at all, but a zip file").unwrap();
+
+        assert!(matches!(Package::open(&path), Err(Error::Invalid(_))));
+    }
+
+    #[test]
+    fn test_is_part() {
+        let dir = tempdir().unwrap();
+        for name in ["Big.pak", "Big_1.pak", "Lonely_2.pak", "Big_Patch.pak"] {
+            fs::write(dir.path().join(name), "").unwrap();
+        }
+
+        assert!(!is_part(&dir.path().join("Big.pak")));
+        assert!(is_part(&dir.path().join("Big_1.pak")));
+        assert!(!is_part(&dir.path().join("Lonely_2.pak")));
+        assert!(!is_part(&dir.path().join("Big_Patch.pak")));
+    }
+    #[test]
+    fn test_made_up_sizes() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("Test.pak");
+        build(&path, &[("Mods/Test/meta.lsx", b"<save/>", 2)]);
+        let package = fs::read(&path).unwrap();
+        let counts_at = usize::try_from(read_u64(&package, 8).unwrap()).unwrap();
+
+        let with_count = |at: usize, count: u32| {
+            let mut package = package.clone();
+            package.splice(at..at.checked_add(4).unwrap(), count.to_le_bytes());
+            fs::write(&path, package).unwrap();
+        };
+
+        // Neither the file count nor the table size is taken at its word
+        with_count(counts_at, u32::MAX);
+        assert!(matches!(Package::open(&path), Err(Error::Decompress(_))));
+        with_count(counts_at.checked_add(4).unwrap(), u32::MAX);
+        assert!(matches!(Package::open(&path), Err(Error::Io(_))));
+
+        with_count(counts_at, 1);
+        let mut package = Package::open(&path).unwrap();
+        for entry in &mut package.entries {
+            entry.size_on_disk = u32::MAX;
+        }
+        let entry = package.entries().first().unwrap();
+        assert!(matches!(package.read(entry), Err(Error::Io(_))));
+    }
+}
@@ -0,0 +1,438 @@
+//! Larian's XML based LSX format, used by Baldur's Gate 3 for `meta.lsx` inside mod packages
+//! and for the `modsettings.lsx` listing the mods to load.
+
+use roxmltree::{Document, Node};
+
+use crate::formats::{Error, Result, lspk::Package};
+
+/// Folder of the base game's module, which `modsettings.lsx` always has to list
+const BASE_MODULE_FOLDERS: [&str; 2] = ["GustavX", "GustavDev"];
+/// Folders of the modules that are part of the game itself, which mods commonly depend on
+const GAME_MODULE_FOLDERS: [&str; 10] = [
+    "Gustav",
+    "GustavDev",
+    "GustavX",
+    "Shared",
+    "SharedDev",
+    "Honour",
+    "HonourX",
+    "MainUI",
+    "ModBrowser",
+    "Engine",
+];
+/// Version 1.0.0.0, for modules that don't state their version
+const DEFAULT_VERSION64: i64 = 36028797018963968;
+
+/// The identity of a module, as described by its `meta.lsx`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModuleInfo {
+    pub uuid: String,
+    /// Name of the module's directory under `Mods` and `Public` inside its package
+    pub folder: String,
+    pub name: String,
+    pub author: String,
+    pub description: String,
+    pub version64: i64,
+    pub md5: String,
+    /// Modules that have to be loaded before this one
+    pub dependencies: Vec<Dependency>,
+}
+
+/// A module another module depends on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dependency {
+    pub uuid: String,
+    pub folder: String,
+    pub name: String,
+}
+
+impl Dependency {
+    /// Whether the dependency is a module of the game itself, which is always loaded
+    pub fn is_game_module(&self) -> bool {
+        GAME_MODULE_FOLDERS.contains(&self.folder.as_str()) || self.folder.starts_with("DiceSet_")
+    }
+}
+
+impl ModuleInfo {
+    /// Parse the `ModuleInfo` node of a `meta.lsx`.
+    pub fn from_meta(xml: &str) -> Result<Self> {
+        let document = Document::parse(xml)?;
+        let info = find_node(document.root(), "ModuleInfo")
+            .ok_or(Error::Missing("ModuleInfo", "meta.lsx"))?;
+
+        let required = |id: &'static str| {
+            attribute(info, id)
+                .map(str::to_string)
+                .ok_or(Error::Missing(id, "meta.lsx"))
+        };
+
+        let optional = |id: &str| attribute(info, id).unwrap_or_default().to_string();
+
+        let dependencies = find_node(document.root(), "Dependencies")
+            .map(|node| {
+                node.descendants()
+                    .filter(|n| n.attribute("id") == Some("ModuleShortDesc"))
+                    .filter_map(|n| {
+                        Some(Dependency {
+                            uuid: attribute(n, "UUID")?.to_string(),
+                            folder: attribute(n, "Folder").unwrap_or_default().to_string(),
+                            name: attribute(n, "Name").unwrap_or_default().to_string(),
+                        })
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Ok(Self {
+            uuid: required("UUID")?,
+            folder: required("Folder")?,
+            name: required("Name")?,
+            author: optional("Author"),
+            description: optional("Description"),
+            version64: attribute(info, "Version64")
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(DEFAULT_VERSION64),
+            md5: optional("MD5"),
+            dependencies,
+        })
+    }
+
+    /// The version as `major.minor.revision.build`
+    pub fn version(&self) -> String {
+        let v = self.version64;
+
+        format!(
+            "{}.{}.{}.{}",
+            v >> 55,
+            (v >> 47) & 0xFF,
+            (v >> 31) & 0xFFFF,
+            v & 0x7FFF_FFFF
+        )
+    }
+
+    /// Read the module info from the `meta.lsx` of a package. Returns `None` for packages without
+    /// one, like those that only override files of other modules.
+    pub fn from_package(package: &Package) -> Result<Option<Self>> {
+        let Some(entry) = package.entries().iter().find(|e| is_meta(&e.name)) else {
+            return Ok(None);
+        };
+
+        let meta = package.read(entry)?;
+        Ok(Some(Self::from_meta(&String::from_utf8_lossy(&meta))?))
+    }
+}
+
+/// Whether a path inside a package is the `meta.lsx` of a module, at `Mods/<Folder>/meta.lsx`
+fn is_meta(name: &str) -> bool {
+    let parts: Vec<&str> = name.split('/').collect();
+
+    matches!(
+        parts.as_slice(),
+        [mods, _, meta] if mods.eq_ignore_ascii_case("Mods") && meta.eq_ignore_ascii_case("meta.lsx")
+    )
+}
+
+/// A `<node>` of a `ModuleShortDesc`, kept as its attributes so that it's written back the way
+/// it was read.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ShortDesc {
+    /// `(id, type, value)` of every attribute
+    attributes: Vec<(String, String, String)>,
+}
+
+impl ShortDesc {
+    fn from_module(module: &ModuleInfo, md5: &str) -> Self {
+        let attribute = |id: &str, type_: &str, value: &str| {
+            (id.to_string(), type_.to_string(), value.to_string())
+        };
+
+        Self {
+            attributes: vec![
+                attribute("Folder", "LSString", &module.folder),
+                attribute("MD5", "LSString", md5),
+                attribute("Name", "LSString", &module.name),
+                attribute("PublishHandle", "uint64", "0"),
+                attribute("UUID", "guid", &module.uuid),
+                attribute("Version64", "int64", &module.version64.to_string()),
+            ],
+        }
+    }
+
+    fn get(&self, id: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(i, _, _)| i == id)
+            .map(|(_, _, value)| value.as_str())
+    }
+}
+
+/// Build the contents of `modsettings.lsx` loading the given modules in order, each paired with
+/// the MD5 hash of its package.
+///
+/// The version and the base game's module are taken over from the `existing` file, if there is
+/// one. Game versions before patch 7 also need a separate `ModOrder` node, which is only written
+/// if the existing file had one as well.
+pub fn mod_settings(existing: Option<&str>, modules: &[(ModuleInfo, String)]) -> Result<String> {
+    let mut version = [
+        ("major", "4"),
+        ("minor", "7"),
+        ("revision", "1"),
+        ("build", "3"),
+    ]
+    .map(|(k, v)| (k.to_string(), v.to_string()));
+    let mut base_modules = Vec::new();
+    let mut has_mod_order = false;
+
+    let document = existing.map(Document::parse).transpose()?;
+    if let Some(document) = &document {
+        if let Some(node) = document.descendants().find(|n| n.has_tag_name("version")) {
+            for (key, value) in version.iter_mut() {
+                if let Some(v) = node.attribute(key.as_str()) {
+                    *value = v.to_string();
+                }
+            }
+        }
+
+        has_mod_order = find_node(document.root(), "ModOrder").is_some();
+
+        if let Some(mods) = find_node(document.root(), "Mods") {
+            base_modules = mods
+                .descendants()
+                .filter(|n| n.attribute("id") == Some("ModuleShortDesc"))
+                .map(|n| ShortDesc {
+                    attributes: n
+                        .children()
+                        .filter(|a| a.has_tag_name("attribute"))
+                        .map(|a| {
+                            (
+                                a.attribute("id").unwrap_or_default().to_string(),
+                                a.attribute("type").unwrap_or_default().to_string(),
+                                a.attribute("value").unwrap_or_default().to_string(),
+                            )
+                        })
+                        .collect(),
+                })
+                .filter(|d| {
+                    d.get("Folder")
+                        .is_some_and(|f| BASE_MODULE_FOLDERS.contains(&f))
+                })
+                .collect();
+        }
+    }
+
+    let descs: Vec<ShortDesc> = base_modules
+        .into_iter()
+        .chain(
+            modules
+                .iter()
+                .map(|(module, md5)| ShortDesc::from_module(module, md5)),
+        )
+        .collect();
+
+    let [major, minor, revision, build] = version.map(|(_, value)| value);
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    let mut line = |depth: usize, content: &str| {
+        xml.push_str(&"    ".repeat(depth));
+        xml.push_str(content);
+        xml.push('\n');
+    };
+
+    line(0, "<save>");
+    line(
+        1,
+        &format!(
+            "<version major=\"{major}\" minor=\"{minor}\" revision=\"{revision}\" build=\"{build}\"/>"
+        ),
+    );
+    line(1, "<region id=\"ModuleSettings\">");
+    line(2, "<node id=\"root\">");
+    line(3, "<children>");
+
+    if has_mod_order {
+        line(4, "<node id=\"ModOrder\">");
+        line(5, "<children>");
+        for desc in &descs {
+            line(6, "<node id=\"Module\">");
+            line(
+                7,
+                &attribute_xml("UUID", "FixedString", desc.get("UUID").unwrap_or_default()),
+            );
+            line(6, "</node>");
+        }
+        line(5, "</children>");
+        line(4, "</node>");
+    }
+
+    line(4, "<node id=\"Mods\">");
+    line(5, "<children>");
+    for desc in &descs {
+        line(6, "<node id=\"ModuleShortDesc\">");
+        for (id, type_, value) in &desc.attributes {
+            line(7, &attribute_xml(id, type_, value));
+        }
+        line(6, "</node>");
+    }
+    line(5, "</children>");
+    line(4, "</node>");
+
+    line(3, "</children>");
+    line(2, "</node>");
+    line(1, "</region>");
+    line(0, "</save>");
+
+    Ok(xml)
+}
+
+fn attribute_xml(id: &str, type_: &str, value: &str) -> String {
+    format!(
+        "<attribute id=\"{}\" type=\"{}\" value=\"{}\"/>",
+        escape(id),
+        escape(type_),
+        escape(value)
+    )
+}
+
+/// Returns the first `<node>` with the given `id`.
+fn find_node<'a, 'input>(root: Node<'a, 'input>, id: &str) -> Option<Node<'a, 'input>> {
+    root.descendants()
+        .find(|n| n.has_tag_name("node") && n.attribute("id") == Some(id))
+}
+
+/// Returns the value of the `<attribute>` with the given `id` directly inside `node`.
+fn attribute<'a>(node: Node<'a, '_>, id: &str) -> Option<&'a str> {
+    node.children()
+        .find(|a| a.has_tag_name("attribute") && a.attribute("id") == Some(id))
+        .and_then(|a| a.attribute("value"))
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const META: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<save>
+    <version major="4" minor="0" revision="9" build="331"/>
+    <region id="Config">
+        <node id="root">
+            <children>
+                <node id="Dependencies">
+                    <children>
+                        <node id="ModuleShortDesc">
+                            <attribute id="Folder" type="LSString" value="GustavDev"/>
+                            <attribute id="Name" type="LSString" value="GustavDev"/>
+                            <attribute id="UUID" type="FixedString" value="28ac9ce2-2aba-8cda-b3b5-6e922f71b6b8"/>
+                        </node>
+                        <node id="ModuleShortDesc">
+                            <attribute id="Folder" type="LSString" value="ImpUI"/>
+                            <attribute id="Name" type="LSString" value="ImprovedUI"/>
+                            <attribute id="UUID" type="FixedString" value="9b8e7f6a-0000-4000-8000-000000000002"/>
+                        </node>
+                    </children>
+                </node>
+                <node id="ModuleInfo">
+                    <attribute id="Author" type="LSString" value="Someone"/>
+                    <attribute id="Description" type="LSString" value="More camp"/>
+                    <attribute id="Folder" type="LSString" value="BetterCamp"/>
+                    <attribute id="Name" type="LSString" value="Better Camp &amp; Co"/>
+                    <attribute id="UUID" type="FixedString" value="f3c2a1b0-0000-4000-8000-000000000001"/>
+                    <attribute id="Version64" type="int64" value="36028797018963970"/>
+                </node>
+            </children>
+        </node>
+    </region>
+</save>"#;
+
+    const MOD_SETTINGS: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<save>
+    <version major="4" minor="8" revision="0" build="10"/>
+    <region id="ModuleSettings">
+        <node id="root">
+            <children>
+                <node id="Mods">
+                    <children>
+                        <node id="ModuleShortDesc">
+                            <attribute id="Folder" type="LSString" value="GustavX"/>
+                            <attribute id="MD5" type="LSString" value=""/>
+                            <attribute id="Name" type="LSString" value="GustavX"/>
+                            <attribute id="PublishHandle" type="uint64" value="0"/>
+                            <attribute id="UUID" type="guid" value="cb555efe-2d9e-131f-8195-a89329d218ea"/>
+                            <attribute id="Version64" type="int64" value="145241946983681427"/>
+                        </node>
+                        <node id="ModuleShortDesc">
+                            <attribute id="Folder" type="LSString" value="OldMod"/>
+                            <attribute id="UUID" type="guid" value="00000000-0000-0000-0000-000000000000"/>
+                        </node>
+                    </children>
+                </node>
+            </children>
+        </node>
+    </region>
+</save>"#;
+
+    #[test]
+    fn test_from_meta() {
+        let info = ModuleInfo::from_meta(META).unwrap();
+
+        assert_eq!(
+            info,
+            ModuleInfo {
+                uuid: "f3c2a1b0-0000-4000-8000-000000000001".to_string(),
+                folder: "BetterCamp".to_string(),
+                name: "Better Camp & Co".to_string(),
+                author: "Someone".to_string(),
+                description: "More camp".to_string(),
+                version64: 36028797018963970,
+                md5: String::new(),
+                dependencies: vec![
+                    Dependency {
+                        uuid: "28ac9ce2-2aba-8cda-b3b5-6e922f71b6b8".to_string(),
+                        folder: "GustavDev".to_string(),
+                        name: "GustavDev".to_string(),
+                    },
+                    Dependency {
+                        uuid: "9b8e7f6a-0000-4000-8000-000000000002".to_string(),
+                        folder: "ImpUI".to_string(),
+                        name: "ImprovedUI".to_string(),
+                    },
+                ],
+            }
+        );
+        assert_eq!(info.version(), "1.0.0.2");
+        assert!(info.dependencies.first().unwrap().is_game_module());
+        assert!(!info.dependencies.last().unwrap().is_game_module());
+    }
+
+    #[test]
+    fn test_mod_settings() {
+        let info = ModuleInfo::from_meta(META).unwrap();
+        let xml = mod_settings(Some(MOD_SETTINGS), &[(info, "abc".to_string())]).unwrap();
+
+        let document = Document::parse(&xml).unwrap();
+        let folders: Vec<&str> = document
+            .descendants()
+            .filter(|n| n.attribute("id") == Some("ModuleShortDesc"))
+            .filter_map(|n| attribute(n, "Folder"))
+            .collect();
+
+        // The base game stays first, mods that are no longer deployed are dropped
+        assert_eq!(folders, ["GustavX", "BetterCamp"]);
+        assert!(xml.contains("<version major=\"4\" minor=\"8\" revision=\"0\" build=\"10\"/>"));
+        assert!(xml.contains("value=\"Better Camp &amp; Co\""));
+        assert!(find_node(document.root(), "ModOrder").is_none());
+    }
+
+    #[test]
+    fn test_is_meta() {
+        assert!(is_meta("Mods/BetterCamp/meta.lsx"));
+        assert!(!is_meta("Public/BetterCamp/meta.lsx"));
+        assert!(!is_meta("Mods/BetterCamp/Story/meta.lsx"));
+    }
+}
@@ -0,0 +1,30 @@
+//! Readers and writers for the file formats of the games Barnacle manages.
+
+use std::io;
+
+use thiserror::Error;
+
+pub mod ba2;
+pub mod bsa;
+pub mod lspk;
+pub mod lsx;
+pub mod plugin;
+pub mod vdf;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Not a valid {0} file")]
+    Invalid(&'static str),
+    #[error("Unsupported {0} version: {1}")]
+    UnsupportedVersion(&'static str, u32),
+    #[error("Failed to decompress {0}")]
+    Decompress(String),
+    #[error("Missing {0} in {1}")]
+    Missing(&'static str, &'static str),
+    #[error("Failed to parse XML: {0}")]
+    Xml(#[from] roxmltree::Error),
+    #[error("I/O error: {0}")]
+    Io(#[from] io::Error),
+}
@@ -0,0 +1,533 @@
+//! Headers of the plugins of Bethesda's games, the `.esm`, `.esp` and `.esl` files.
+//!
+//! Every plugin starts with a header record, `TES3` for Morrowind and `TES4` for Oblivion and
+//! every game since. It lists the plugin's masters and its flags, which is all that's needed to
+//! check and order plugins. The rest of the file is only read to find out whether a plugin could
+//! be made light.
+
+use std::{
+    fs::File,
+    io::{BufReader, Read},
+    ops::RangeInclusive,
+    path::Path,
+};
+
+use crate::formats::{Error, Result};
+
+/// Set on master files, which load before all other plugins
+const MASTER_FLAG: u32 = 0x1;
+/// Set on plugins whose strings live in separate `.strings` files
+const LOCALIZED_FLAG: u32 = 0x80;
+/// `HEDR` file type of Morrowind masters
+const TES3_MASTER_TYPE: u32 = 1;
+/// The lower three bytes of a form ID, which identify the record within its plugin
+const OBJECT_ID_MASK: u32 = 0x00FF_FFFF;
+
+/// The game a plugin was made for, which decides the layout of its header and the meaning of
+/// its flags.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameId {
+    Morrowind,
+    Oblivion,
+    Fallout3,
+    FalloutNV,
+    Skyrim,
+    SkyrimSE,
+    /// Skyrim VR, which shares the plugin format of Skyrim Special Edition but has no light
+    /// plugins
+    SkyrimVR,
+    /// Fallout 4, and Fallout 4 VR
+    Fallout4,
+    Starfield,
+}
+
+impl GameId {
+    /// Whether the game has light plugins, which don't take up a full load order slot
+    pub fn supports_light_plugins(self) -> bool {
+        self.light_flag().is_some()
+    }
+
+    /// Extensions of the game's plugins
+    pub fn extensions(self) -> &'static [&'static str] {
+        if self.supports_light_plugins() {
+            &["esm", "esl", "esp"]
+        } else {
+            &["esm", "esp"]
+        }
+    }
+
+    fn light_flag(self) -> Option<u32> {
+        match self {
+            Self::SkyrimSE | Self::Fallout4 => Some(0x200),
+            Self::Starfield => Some(0x100),
+            _ => None,
+        }
+    }
+
+    fn medium_flag(self) -> Option<u32> {
+        match self {
+            Self::Starfield => Some(0x400),
+            _ => None,
+        }
+    }
+
+    /// Size of a record header, which got longer over time
+    fn record_header_size(self) -> usize {
+        match self {
+            Self::Morrowind => 16,
+            Self::Oblivion => 20,
+            _ => 24,
+        }
+    }
+
+    /// Size of a subrecord header. Morrowind stores the size of subrecords in four bytes instead
+    /// of two.
+    fn subrecord_header_size(self) -> usize {
+        match self {
+            Self::Morrowind => 8,
+            _ => 6,
+        }
+    }
+}
+
+/// The header of a plugin.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Plugin {
+    /// File name of the plugin
+    pub name: String,
+    game: GameId,
+    flags: u32,
+    /// File names of the plugins this one depends on, in the order they have to be loaded
+    pub masters: Vec<String>,
+    pub author: String,
+    pub description: String,
+    /// Version of the header record, such as 1.7 for Skyrim Special Edition
+    pub version: f32,
+    /// Version of the record format, not stored by Morrowind and Oblivion
+    pub form_version: u16,
+    /// Number of records in the plugin
+    pub record_count: u32,
+}
+
+impl Plugin {
+    /// Read the header of the plugin at the given path.
+    pub fn read(game: GameId, path: &Path) -> Result<Self> {
+        let mut file = File::open(path)?;
+
+        let mut header = vec![0; game.record_header_size()];
+        file.read_exact(&mut header)?;
+        let data_size = read_u32(&header, 4)?;
+
+        let mut data = Vec::new();
+        file.take(u64::from(data_size)).read_to_end(&mut data)?;
+        if u32::try_from(data.len()).ok() != Some(data_size) {
+            return Err(invalid());
+        }
+
+        let name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+
+        Self::parse(game, &name, &header, &data)
+    }
+
+    fn parse(game: GameId, name: &str, header: &[u8], data: &[u8]) -> Result<Self> {
+        let record_type: &[u8] = if game == GameId::Morrowind {
+            b"TES3"
+        } else {
+            b"TES4"
+        };
+        if header.get(..4) != Some(record_type) {
+            return Err(invalid());
+        }
+
+        let mut plugin = Self {
+            name: name.to_string(),
+            game,
+            flags: read_u32(header, if game == GameId::Morrowind { 12 } else { 8 })?,
+            masters: Vec::new(),
+            author: String::new(),
+            description: String::new(),
+            version: 0.0,
+            form_version: if header.len() >= 24 {
+                read_u16(header, 20)?
+            } else {
+                0
+            },
+            record_count: 0,
+        };
+
+        for (kind, field) in subrecords(game, data)? {
+            match kind {
+                b"HEDR" if game == GameId::Morrowind => {
+                    plugin.version = read_f32(field, 0)?;
+                    if read_u32(field, 4)? == TES3_MASTER_TYPE {
+                        plugin.flags |= MASTER_FLAG;
+                    }
+                    plugin.author = string(field.get(8..40).ok_or_else(invalid)?);
+                    plugin.description = string(field.get(40..296).ok_or_else(invalid)?);
+                    plugin.record_count = read_u32(field, 296)?;
+                }
+                b"HEDR" => {
+                    plugin.version = read_f32(field, 0)?;
+                    plugin.record_count = read_u32(field, 4)?;
+                }
+                b"CNAM" => plugin.author = string(field),
+                b"SNAM" => plugin.description = string(field),
+                b"MAST" => plugin.masters.push(string(field)),
+                _ => {}
+            }
+        }
+
+        Ok(plugin)
+    }
+
+    pub fn game(&self) -> GameId {
+        self.game
+    }
+
+    /// Whether the plugin is flagged as a master. Masters load before all other plugins.
+    pub fn is_master(&self) -> bool {
+        self.flags & MASTER_FLAG != 0
+    }
+
+    /// Whether the plugin is light, either by flag or by its `.esl` extension, in games that
+    /// support light plugins.
+    pub fn is_light(&self) -> bool {
+        let Some(flag) = self.game.light_flag() else {
+            return false;
+        };
+
+        self.flags & flag != 0
+            || Path::new(&self.name)
+                .extension()
+                .is_some_and(|e| e.eq_ignore_ascii_case("esl"))
+    }
+
+    /// Whether the plugin is a Starfield medium plugin
+    pub fn is_medium(&self) -> bool {
+        self.game
+            .medium_flag()
+            .is_some_and(|flag| self.flags & flag != 0)
+    }
+
+    /// Whether the plugin's strings are stored in separate `.strings` files
+    pub fn is_localized(&self) -> bool {
+        self.game != GameId::Morrowind && self.flags & LOCALIZED_FLAG != 0
+    }
+
+    /// Whether the plugin at the given path could be flagged as light without breaking it.
+    /// That's the case when every record it adds, rather than overrides, has an object ID in the
+    /// range light plugins can use. Masters and plugins that are already light are never
+    /// candidates.
+    pub fn can_be_light(&self, path: &Path) -> Result<bool> {
+        let Some(range) = self.light_object_ids() else {
+            return Ok(false);
+        };
+        if self.is_master() || self.is_light() {
+            return Ok(false);
+        }
+
+        // Records whose form ID starts with the index of a master override that master's
+        let masters = u32::try_from(self.masters.len()).map_err(|_| invalid())?;
+
+        Ok(form_ids(self.game, path)?
+            .into_iter()
+            .filter(|id| id >> 24 >= masters)
+            .all(|id| range.contains(&(id & OBJECT_ID_MASK))))
+    }
+
+    /// Object IDs new records of a light plugin can have. Skyrim Special Edition extended the
+    /// range with header version 1.71.
+    fn light_object_ids(&self) -> Option<RangeInclusive<u32>> {
+        match self.game {
+            GameId::SkyrimSE if self.version >= 1.71 => Some(0..=0xFFF),
+            GameId::SkyrimSE | GameId::Fallout4 => Some(0x800..=0xFFF),
+            GameId::Starfield => Some(0..=0xFFF),
+            _ => None,
+        }
+    }
+}
+
+/// Read the form IDs of every record in a plugin, after its header record. Groups are stepped
+/// into rather than over, so their records are read in turn. Only the record headers are read,
+/// the rest of the file is skipped.
+fn form_ids(game: GameId, path: &Path) -> Result<Vec<u32>> {
+    let mut file = BufReader::new(File::open(path)?);
+    let len = file.get_ref().metadata()?.len();
+    let header_size = u64::try_from(game.record_header_size()).map_err(|_| invalid())?;
+    let mut header = vec![0; game.record_header_size()];
+
+    // Skip the header record
+    file.read_exact(&mut header)?;
+    let header_data = read_u32(&header, 4)?;
+    file.seek_relative(i64::from(header_data))?;
+    let mut at = header_size
+        .checked_add(u64::from(header_data))
+        .ok_or_else(invalid)?;
+    let mut form_ids = Vec::new();
+
+    while at < len {
+        at = at.checked_add(header_size).ok_or_else(invalid)?;
+        if at > len {
+            return Err(invalid());
+        }
+        file.read_exact(&mut header)?;
+
+        if header.get(..4) != Some(b"GRUP".as_slice()) {
+            form_ids.push(read_u32(&header, 12)?);
+            let size = read_u32(&header, 4)?;
+            file.seek_relative(i64::from(size))?;
+            at = at.checked_add(u64::from(size)).ok_or_else(invalid)?;
+        }
+    }
+
+    Ok(form_ids)
+}
+
+/// Split the data of a record into its subrecords' types and contents.
+fn subrecords(game: GameId, data: &[u8]) -> Result<Vec<(&[u8], &[u8])>> {
+    let mut subrecords = Vec::new();
+    // Set by an `XXXX` subrecord, for a following subrecord too big for its own size field
+    let mut large_size = None;
+    let mut at = 0;
+
+    while at < data.len() {
+        let kind = data
+            .get(at..at.checked_add(4).ok_or_else(invalid)?)
+            .ok_or_else(invalid)?;
+        let size = match game {
+            GameId::Morrowind => read_u32(data, at.checked_add(4).ok_or_else(invalid)?)?,
+            _ => u32::from(read_u16(data, at.checked_add(4).ok_or_else(invalid)?)?),
+        };
+        let size = usize::try_from(large_size.take().unwrap_or(size)).map_err(|_| invalid())?;
+
+        let start = at
+            .checked_add(game.subrecord_header_size())
+            .ok_or_else(invalid)?;
+        let end = start.checked_add(size).ok_or_else(invalid)?;
+        let field = data.get(start..end).ok_or_else(invalid)?;
+
+        if kind == b"XXXX" {
+            large_size = Some(read_u32(field, 0)?);
+        } else {
+            subrecords.push((kind, field));
+        }
+        at = end;
+    }
+
+    Ok(subrecords)
+}
+
+/// Decode a zero terminated string. Plugins use the Windows-1252 code page, which matches
+/// Latin-1 for everything but punctuation, so that's the fallback for invalid UTF-8.
+fn string(bytes: &[u8]) -> String {
+    let len = bytes.iter().position(|b| *b == 0).unwrap_or(bytes.len());
+    let bytes = bytes.get(..len).unwrap_or_default();
+
+    match std::str::from_utf8(bytes) {
+        Ok(s) => s.to_string(),
+        Err(_) => bytes.iter().map(|b| char::from(*b)).collect(),
+    }
+}
+
+fn invalid() -> Error {
+    Error::Invalid("plugin")
+}
+
+fn read_u16(bytes: &[u8], at: usize) -> Result<u16> {
+    let end = at.checked_add(2).ok_or_else(invalid)?;
+    let bytes = bytes.get(at..end).ok_or_else(invalid)?;
+
+    Ok(u16::from_le_bytes(bytes.try_into().map_err(|_| invalid())?))
+}
+
+fn read_u32(bytes: &[u8], at: usize) -> Result<u32> {
+    let end = at.checked_add(4).ok_or_else(invalid)?;
+    let bytes = bytes.get(at..end).ok_or_else(invalid)?;
+
+    Ok(u32::from_le_bytes(bytes.try_into().map_err(|_| invalid())?))
+}
+
+fn read_f32(bytes: &[u8], at: usize) -> Result<f32> {
+    read_u32(bytes, at).map(f32::from_bits)
+}
+
+/// Build a plugin with the given masters and header flags.
+#[cfg(test)]
+pub(crate) fn build(path: &Path, game: GameId, flags: u32, masters: &[&str]) {
+    let mut data = Vec::new();
+    let mut subrecord = |kind: &[u8], contents: &[u8]| {
+        data.extend(kind);
+        match game {
+            GameId::Morrowind => data.extend(u32::try_from(contents.len()).unwrap().to_le_bytes()),
+            _ => data.extend(u16::try_from(contents.len()).unwrap().to_le_bytes()),
+        }
+        data.extend(contents);
+    };
+
+    if game == GameId::Morrowind {
+        let mut hedr = 1.3f32.to_le_bytes().to_vec();
+        hedr.extend(u32::from(flags & MASTER_FLAG != 0).to_le_bytes());
+        let mut author = b"Someone".to_vec();
+        author.resize(32, 0);
+        hedr.extend(author);
+        let mut description = b"A test plugin".to_vec();
+        description.resize(256, 0);
+        hedr.extend(description);
+        hedr.extend(42u32.to_le_bytes());
+        subrecord(b"HEDR", &hedr);
+    } else {
+        let mut hedr = 1.71f32.to_le_bytes().to_vec();
+        hedr.extend(42u32.to_le_bytes());
+        hedr.extend(0x800u32.to_le_bytes());
+        subrecord(b"HEDR", &hedr);
+        subrecord(b"CNAM", b"Someone\0");
+        subrecord(b"SNAM", b"A test plugin\0");
+    }
+    for master in masters {
+        let mut name = master.as_bytes().to_vec();
+        name.push(0);
+        subrecord(b"MAST", &name);
+        subrecord(b"DATA", &0u64.to_le_bytes());
+    }
+
+    let mut plugin = Vec::new();
+    match game {
+        GameId::Morrowind => {
+            plugin.extend(b"TES3");
+            plugin.extend(u32::try_from(data.len()).unwrap().to_le_bytes());
+            plugin.extend(0u32.to_le_bytes());
+            plugin.extend(flags.to_le_bytes());
+        }
+        _ => {
+            plugin.extend(b"TES4");
+            plugin.extend(u32::try_from(data.len()).unwrap().to_le_bytes());
+            plugin.extend(flags.to_le_bytes());
+            plugin.extend([0; 8]);
+            if game != GameId::Oblivion {
+                plugin.extend(44u16.to_le_bytes());
+                plugin.extend([0; 2]);
+            }
+        }
+    }
+    plugin.extend(data);
+    // An empty group, which mustn't be read as part of the header
+    let end = plugin.len().checked_add(game.record_header_size()).unwrap();
+    let size = u32::try_from(game.record_header_size()).unwrap();
+    plugin.extend(b"GRUP");
+    plugin.extend(size.to_le_bytes());
+    plugin.resize(end, 0);
+
+    std::fs::write(path, plugin).unwrap();
+}
+
+/// Add empty records with the given form IDs to the end of a plugin made by [`build`].
+#[cfg(test)]
+pub(crate) fn add_records(path: &Path, game: GameId, form_ids: &[u32]) {
+    let mut plugin = std::fs::read(path).unwrap();
+    for form_id in form_ids {
+        let mut record = b"WEAP".to_vec();
+        record.extend([0; 8]);
+        record.extend(form_id.to_le_bytes());
+        record.resize(game.record_header_size(), 0);
+        plugin.extend(record);
+    }
+
+    std::fs::write(path, plugin).unwrap();
+}
+
+#[cfg(test)]
+mod test {
+    use tempfile::tempdir;
+
+    use super::*;
+
+    #[test]
+    fn test_read() {
+        let dir = tempdir().unwrap();
+
+        for game in [GameId::Morrowind, GameId::Oblivion, GameId::SkyrimSE] {
+            let path = dir.path().join("Test.esm");
+            build(&path, game, MASTER_FLAG, &["Base.esm", "Patch.esp"]);
+
+            let plugin = Plugin::read(game, &path).unwrap();
+
+            assert_eq!(plugin.name, "Test.esm");
+            assert_eq!(plugin.masters, ["Base.esm", "Patch.esp"]);
+            assert_eq!(plugin.author, "Someone");
+            assert_eq!(plugin.description, "A test plugin");
+            assert_eq!(plugin.record_count, 42);
+            assert!(plugin.is_master());
+            assert!(!plugin.is_light());
+            assert_eq!(
+                plugin.form_version,
+                if game == GameId::SkyrimSE { 44 } else { 0 }
+            );
+        }
+    }
+
+    #[test]
+    fn test_flags() {
+        let dir = tempdir().unwrap();
+        let read = |game, name: &str, flags| {
+            let path = dir.path().join(name);
+            build(&path, game, flags, &[]);
+            Plugin::read(game, &path).unwrap()
+        };
+
+        assert!(read(GameId::SkyrimSE, "Light.esp", 0x200).is_light());
+        assert!(read(GameId::SkyrimSE, "Light.esl", 0).is_light());
+        assert!(!read(GameId::Skyrim, "Light.esp", 0x200).is_light());
+        assert!(read(GameId::SkyrimSE, "Localized.esm", 0x81).is_localized());
+
+        assert!(read(GameId::Starfield, "Light.esm", 0x101).is_light());
+        let medium = read(GameId::Starfield, "Medium.esm", 0x401);
+        assert!(medium.is_medium() && !medium.is_light());
+    }
+
+    #[test]
+    fn test_can_be_light() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("Test.esp");
+
+        for (game, flags, form_ids, expected) in [
+            // Overrides of the master's records don't count, whatever their IDs
+            (
+                GameId::SkyrimSE,
+                0,
+                &[0x0100_0800, 0x0100_0FFF, 0x0012_3456][..],
+                true,
+            ),
+            (GameId::SkyrimSE, 0, &[0x0100_0001], true),
+            (GameId::Fallout4, 0, &[0x0100_0001], false),
+            (GameId::SkyrimSE, 0, &[0x0100_1000], false),
+            (GameId::SkyrimSE, MASTER_FLAG, &[], false),
+            (GameId::SkyrimSE, 0x200, &[], false),
+            (GameId::Skyrim, 0, &[], false),
+        ] {
+            build(&path, game, flags, &["Base.esm"]);
+            add_records(&path, game, form_ids);
+            let plugin = Plugin::read(game, &path).unwrap();
+
+            assert_eq!(
+                plugin.can_be_light(&path).unwrap(),
+                expected,
+                "{form_ids:x?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_invalid() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("Test.esp");
+        build(&path, GameId::Morrowind, 0, &[]);
+
+        assert!(matches!(
+            Plugin::read(GameId::Skyrim, &path),
+            Err(Error::Invalid(_))
+        ));
+    }
+}
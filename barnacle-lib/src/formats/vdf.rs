@@ -0,0 +1,168 @@
+//! Valve's text KeyValues format, used by Steam's `.vdf` and `.acf` files.
+//!
+//! A file is a tree of quoted keys, each followed by either a quoted value or a block of
+//! nested keys in braces. Keys are compared case-insensitively, like Steam does.
+
+use crate::formats::{Error, Result};
+
+/// A value in a KeyValues tree.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Value {
+    String(String),
+    Block(Vec<(String, Value)>),
+}
+
+impl Value {
+    /// Returns the value of the first key with the given name, if this is a block.
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        match self {
+            Self::String(_) => None,
+            Self::Block(entries) => entries
+                .iter()
+                .find(|(k, _)| k.eq_ignore_ascii_case(key))
+                .map(|(_, v)| v),
+        }
+    }
+
+    /// Returns the string of the first key with the given name, if this is a block.
+    pub fn get_str(&self, key: &str) -> Option<&str> {
+        match self.get(key)? {
+            Self::String(value) => Some(value),
+            Self::Block(_) => None,
+        }
+    }
+
+    /// The keys and values of a block. Empty for strings.
+    pub fn entries(&self) -> &[(String, Value)] {
+        match self {
+            Self::String(_) => &[],
+            Self::Block(entries) => entries,
+        }
+    }
+}
+
+/// Parse a KeyValues document, returning its top-level keys as a block.
+pub fn parse(text: &str) -> Result<Value> {
+    let mut tokens = tokenize(text)?.into_iter().peekable();
+    let mut stack: Vec<(String, Vec<(String, Value)>)> = vec![(String::new(), Vec::new())];
+
+    while let Some(token) = tokens.next() {
+        match token {
+            Token::String(key) => match tokens.next() {
+                Some(Token::String(value)) => stack
+                    .last_mut()
+                    .ok_or_else(invalid)?
+                    .1
+                    .push((key, Value::String(value))),
+                Some(Token::Open) => stack.push((key, Vec::new())),
+                _ => return Err(invalid()),
+            },
+            Token::Close => {
+                let (key, entries) = stack.pop().ok_or_else(invalid)?;
+                stack
+                    .last_mut()
+                    .ok_or_else(invalid)?
+                    .1
+                    .push((key, Value::Block(entries)));
+            }
+            Token::Open => return Err(invalid()),
+        }
+    }
+
+    match <[_; 1]>::try_from(stack) {
+        Ok([(_, entries)]) => Ok(Value::Block(entries)),
+        Err(_) => Err(invalid()),
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Token {
+    String(String),
+    Open,
+    Close,
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '{' => tokens.push(Token::Open),
+            '}' => tokens.push(Token::Close),
+            '"' => {
+                let mut string = String::new();
+                loop {
+                    match chars.next().ok_or_else(invalid)? {
+                        '"' => break,
+                        '\\' => match chars.next().ok_or_else(invalid)? {
+                            'n' => string.push('\n'),
+                            't' => string.push('\t'),
+                            c => string.push(c),
+                        },
+                        c => string.push(c),
+                    }
+                }
+                tokens.push(Token::String(string));
+            }
+            '/' if chars.peek() == Some(&'/') => while chars.next_if(|c| *c != '\n').is_some() {},
+            c if c.is_whitespace() => {}
+            // Conditionals like `[$WIN32]` and unquoted words, which Steam's files don't use
+            _ => {
+                let mut word = c.to_string();
+                while let Some(c) = chars.next_if(|c| !c.is_whitespace() && !"{}\"".contains(*c)) {
+                    word.push(c);
+                }
+                if !word.starts_with('[') {
+                    tokens.push(Token::String(word));
+                }
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+fn invalid() -> Error {
+    Error::Invalid("VDF")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let text = r#"
+// Written by Steam
+"libraryfolders"
+{
+	"0"
+	{
+		"path"		"/home/user/.local/share/Steam"
+		"label"		"Say \"hi\""
+		"apps"
+		{
+			"489830"		"12345"
+		}
+	}
+}
+"#;
+
+        let value = parse(text).unwrap();
+        let library = value.get("LibraryFolders").unwrap().get("0").unwrap();
+
+        assert_eq!(
+            library.get_str("path"),
+            Some("/home/user/.local/share/Steam")
+        );
+        assert_eq!(library.get_str("label"), Some(r#"Say "hi""#));
+        assert_eq!(
+            library.get("apps").unwrap().entries(),
+            [("489830".to_string(), Value::String("12345".to_string()))]
+        );
+
+        assert!(parse(r#""key" { "a" "b" "#).is_err());
+        assert!(parse(r#""key" "a" }"#).is_err());
+    }
+}
@@ -1,5 +1,6 @@
 use std::{
-    fs::{create_dir_all, set_permissions},
+    fs::{self, create_dir_all, set_permissions},
+    io,
     path::{Path, PathBuf},
 };
 
@@ -8,7 +9,7 @@ use walkdir::WalkDir;
 #[derive(PartialEq)]
 pub enum Permissions {
     ReadOnly,
-    // ReadWrite,
+    ReadWrite,
 }
 
 pub fn change_dir_permissions(path: &Path, permissions: Permissions) {
@@ -21,6 +22,31 @@ pub fn change_dir_permissions(path: &Path, permissions: Permissions) {
     }
 }
 
+/// Move a file, falling back to copying if it has to cross filesystems.
+pub fn move_file(from: &Path, to: &Path) -> io::Result<()> {
+    match fs::rename(from, to) {
+        Err(e) if e.kind() == io::ErrorKind::CrossesDevices => {
+            fs::copy(from, to)?;
+            fs::remove_file(from)
+        }
+        result => result,
+    }
+}
+
+/// Remove every empty directory inside `path`, leaving `path` itself in place.
+pub fn remove_empty_dirs(path: &Path) {
+    for entry in WalkDir::new(path)
+        .min_depth(1)
+        .contents_first(true)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_dir())
+    {
+        // Fails for directories that aren't empty, which is exactly what should be kept
+        fs::remove_dir(entry.path()).ok();
+    }
+}
+
 /// Returns the path to the Barnacle configuration directory. If it doesn't exist when this
 /// function is called, it will be created.
 pub fn config_dir() -> PathBuf {
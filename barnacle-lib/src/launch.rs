@@ -0,0 +1,503 @@
+//! Running games and tools with a profile deployed.
+//!
+//! Only one launch can run at a time, so that a second launch can't deploy another profile
+//! underneath a running game. This holds across Barnacle processes, through a lock on the file
+//! at [`CoreConfig::launch_lock_path`](crate::repository::config::CoreConfig::launch_lock_path).
+
+use std::{
+    collections::HashMap,
+    fs::{File, TryLockError},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    process::{Child, ExitStatus, Stdio},
+    thread,
+    time::SystemTime,
+};
+
+use thiserror::Error;
+use tracing::{debug, warn};
+
+use crate::repository::{
+    Deployment, Mod, Profile, Repository, Run, Tool, config::CoreConfigHandle,
+};
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Another game or tool is already running")]
+    AlreadyRunning,
+    #[error("The profile belongs to a different game than the tool")]
+    WrongGame,
+    #[error("Failed to start {0}: {1}")]
+    Spawn(PathBuf, io::Error),
+}
+
+/// The right to run a launch, given back when it's dropped, or when the process holding it
+/// exits.
+#[derive(Debug)]
+struct Slot {
+    _lock: File,
+}
+
+impl Slot {
+    fn take(cfg: &CoreConfigHandle) -> crate::Result<Self> {
+        let path = cfg.read().launch_lock_path().to_path_buf();
+        let lock = File::options()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(path)?;
+
+        match lock.try_lock() {
+            Ok(()) => Ok(Self { _lock: lock }),
+            Err(TryLockError::WouldBlock) => Err(Error::AlreadyRunning.into()),
+            Err(TryLockError::Error(e)) => Err(e.into()),
+        }
+    }
+}
+
+/// A running game or tool, run through its game's [`Runtime`](crate::runtime::Runtime).
+///
+/// The profile is deployed for the launch unless it already is. A profile deployed for the
+/// launch is undeployed once the process exits, if
+/// [`Repository::undeploy_after_launch`] is set. Dropping a launch without waiting for it
+/// leaves the process running, and finishes the launch in the background once it exits. Other
+/// launches are refused until then.
+///
+/// The process's output is logged to a [`Run`], which is completed once it exits. If the tool
+/// has an [output mod](Tool::output_mod), the files it created in the profile's overwrite
+/// directory are moved into that mod once the profile is undeployed. A profile that stays
+/// deployed keeps its overwrite directory in use, so the files are left there instead.
+#[derive(Debug)]
+pub struct Launch {
+    id: u32,
+    run: Run,
+    /// The running process, along with what's left to do once it exits
+    running: Option<Running>,
+    /// How the process exited, once the launch is finished
+    status: Option<ExitStatus>,
+}
+
+#[derive(Debug)]
+struct Running {
+    child: Child,
+    repo: Repository,
+    run: Run,
+    /// The deployment to undo once the process exits
+    deployment: Option<Deployment>,
+    output: Option<Output>,
+    _slot: Slot,
+}
+
+impl Running {
+    /// Record how the process exited, undo the deployment and collect the output. The slot is
+    /// given back afterwards.
+    fn finish(mut self, status: ExitStatus) -> crate::Result<()> {
+        self.run.finish(Some(status))?;
+
+        let Some(deployment) = self.deployment.take() else {
+            if self.output.is_some() {
+                warn!(
+                    "The profile stays deployed, so the files created by the launch are left in \
+                     its overwrite directory"
+                );
+            }
+            return Ok(());
+        };
+        self.repo.undeploy(deployment)?;
+
+        if let Some(output) = self.output.take()
+            && let Err(e) = output.collect()
+        {
+            warn!("Failed to move the files created by the launch into its output mod: {e}");
+        }
+
+        Ok(())
+    }
+}
+
+/// Where the files a tool creates end up once it exits.
+#[derive(Debug)]
+struct Output {
+    mod_: Mod,
+    profile: Profile,
+    /// The files in the overwrite directory before the launch, with their modification times
+    existing: HashMap<PathBuf, Option<SystemTime>>,
+}
+
+impl Output {
+    fn new(mod_: Mod, profile: &Profile) -> crate::Result<Self> {
+        Ok(Self {
+            existing: overwrite_files(profile)?,
+            mod_,
+            profile: profile.clone(),
+        })
+    }
+
+    /// Move the files that were created or changed since the launch into the mod.
+    fn collect(self) -> crate::Result<()> {
+        let files: Vec<PathBuf> = overwrite_files(&self.profile)?
+            .into_iter()
+            .filter(|(path, modified)| self.existing.get(path) != Some(modified))
+            .map(|(path, _)| path)
+            .collect();
+        if !files.is_empty() {
+            self.profile.move_overwrite_files(&files, &self.mod_)?;
+            debug!("Moved {} files into {}", files.len(), self.mod_.name()?);
+        }
+
+        Ok(())
+    }
+}
+
+fn overwrite_files(profile: &Profile) -> crate::Result<HashMap<PathBuf, Option<SystemTime>>> {
+    let dir = profile.overwrite_dir()?;
+
+    Ok(profile
+        .overwrite_files()?
+        .into_iter()
+        .map(|path| {
+            let modified = dir
+                .join(&path)
+                .symlink_metadata()
+                .and_then(|m| m.modified())
+                .ok();
+            (path, modified)
+        })
+        .collect())
+}
+
+impl Launch {
+    pub(crate) fn start(repo: &Repository, tool: &Tool, profile: &Profile) -> crate::Result<Self> {
+        let slot = Slot::take(&tool.cfg)?;
+
+        let game = tool.parent()?;
+        if game != profile.parent()? {
+            return Err(Error::WrongGame.into());
+        }
+
+        let deployed = repo
+            .deployments()?
+            .iter()
+            .any(|d| d.profile().is_ok_and(|p| p.id == profile.id));
+        let deployment = if deployed {
+            None
+        } else {
+            Some(repo.deploy(profile)?)
+        };
+
+        let spawn = || -> crate::Result<(Child, Run, Option<Output>)> {
+            let path = tool.resolved_path()?;
+            let mut command = game.runtime()?.command(&path, &tool.args()?);
+            command.envs(tool.env()?);
+            // Windows programs tend to expect to be started from their own directory
+            let working_dir = match (
+                tool.working_dir()?,
+                path.parent().filter(|dir| dir.is_dir()),
+            ) {
+                (Some(dir), Some(exe_dir)) => Some(exe_dir.join(dir)),
+                (dir, exe_dir) => dir.or(exe_dir.map(Path::to_path_buf)),
+            };
+            if let Some(dir) = working_dir {
+                command.current_dir(dir);
+            }
+
+            let output = match tool.output_mod()? {
+                Some(mod_) => Some(Output::new(mod_, profile)?),
+                None => None,
+            };
+
+            let (mut run, mut log) =
+                Run::add(tool.db.clone(), tool.cfg.clone(), tool, &profile.name()?)?;
+            command
+                .stdout(Stdio::from(log.try_clone()?))
+                .stderr(Stdio::from(log.try_clone()?));
+
+            let child = match command.spawn() {
+                Ok(child) => child,
+                Err(e) => {
+                    if let Err(e) = writeln!(log, "Failed to start {}: {e}", path.display()) {
+                        warn!("Failed to log a failed launch: {e}");
+                    }
+                    if let Err(e) = run.finish(None) {
+                        warn!("Failed to record a failed launch: {e}");
+                    }
+                    return Err(Error::Spawn(path, e).into());
+                }
+            };
+            debug!("Launched {} as process {}", tool.name()?, child.id());
+
+            Ok((child, run, output))
+        };
+
+        // A profile deployed for the launch never outlives a launch that didn't start
+        let (child, run, output) = match spawn() {
+            Ok(spawned) => spawned,
+            Err(e) => {
+                if let Some(deployment) = deployment
+                    && let Err(e) = repo.undeploy(deployment)
+                {
+                    warn!("Failed to undeploy after a failed launch: {e}");
+                }
+                return Err(e);
+            }
+        };
+
+        Ok(Self {
+            id: child.id(),
+            run: run.clone(),
+            running: Some(Running {
+                child,
+                repo: repo.clone(),
+                run,
+                deployment: deployment.filter(|_| repo.undeploy_after_launch()),
+                output,
+                _slot: slot,
+            }),
+            status: None,
+        })
+    }
+
+    /// The ID of the running process
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    /// The record the process's output is logged to
+    pub fn run(&self) -> &Run {
+        &self.run
+    }
+
+    /// Wait for the process to exit, and undeploy the profile if it was deployed for the
+    /// launch.
+    pub fn wait(mut self) -> crate::Result<ExitStatus> {
+        if let Some(mut running) = self.running.take() {
+            let status = running.child.wait()?;
+            self.status = Some(status);
+            running.finish(status)?;
+        }
+
+        Ok(self
+            .status
+            .expect("A finished launch should have an exit status"))
+    }
+
+    /// Check whether the process has exited without waiting for it, and undeploy the profile
+    /// if it has and was deployed for the launch.
+    pub fn try_wait(&mut self) -> crate::Result<Option<ExitStatus>> {
+        let Some(running) = &mut self.running else {
+            return Ok(self.status);
+        };
+
+        let status = running.child.try_wait()?;
+        if let Some(status) = status
+            && let Some(running) = self.running.take()
+        {
+            self.status = Some(status);
+            running.finish(status)?;
+        }
+
+        Ok(status)
+    }
+}
+
+impl Drop for Launch {
+    fn drop(&mut self) {
+        let Some(mut running) = self.running.take() else {
+            return;
+        };
+
+        // The slot goes along, so that no other launch starts while the process is running
+        thread::spawn(move || {
+            let result = running
+                .child
+                .wait()
+                .map_err(crate::Error::from)
+                .and_then(|status| running.finish(status));
+            if let Err(e) = result {
+                warn!("Failed to finish a launch in the background: {e}");
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{fs, time::Duration};
+
+    use tempfile::tempdir;
+
+    use crate::repository::{DeployKind, DeployMethod};
+
+    use super::*;
+
+    #[test]
+    fn test_launch() {
+        let repo = Repository::mock();
+        let target = tempdir().unwrap();
+
+        let mut game = repo.add_game("Skyrim", DeployKind::Overlay).unwrap();
+        game.set_targets(vec![target.path().to_path_buf()]).unwrap();
+        game.set_deploy_method(DeployMethod::Symlink).unwrap();
+        let profile = game.add_profile("Test").unwrap();
+        let tool = game
+            .add_tool(
+                "Shell",
+                Path::new("/bin/sh"),
+                &["-c", "echo out; echo err >&2; exit 3"],
+            )
+            .unwrap();
+
+        let launch = tool.launch(&profile).unwrap();
+        let run = launch.run().clone();
+        assert_eq!(run.exit_code().unwrap(), None);
+        assert_eq!(repo.deployments().unwrap().len(), 1);
+        assert!(matches!(
+            tool.launch(&profile),
+            Err(crate::Error::Launch(Error::AlreadyRunning))
+        ));
+
+        let status = launch.wait().unwrap();
+        assert_eq!(status.code(), Some(3));
+        assert!(repo.deployments().unwrap().is_empty());
+
+        assert_eq!(run.exit_code().unwrap(), Some(3));
+        assert!(run.ended().unwrap().is_some());
+        assert_eq!(run.tool_name().unwrap(), "Shell");
+        assert_eq!(run.profile_name().unwrap(), "Test");
+        assert_eq!(run.tool().unwrap(), Some(tool.clone()));
+        let log = run.log().unwrap();
+        assert!(log.contains("out") && log.contains("err"), "{log}");
+        assert_eq!(repo.runs().unwrap(), [run.clone()]);
+        assert_eq!(tool.runs().unwrap(), [run]);
+
+        let other = repo
+            .add_game("Morrowind", DeployKind::OpenMW)
+            .unwrap()
+            .add_profile("Other")
+            .unwrap();
+        assert!(matches!(
+            tool.launch(&other),
+            Err(crate::Error::Launch(Error::WrongGame))
+        ));
+
+        // A launch that's dropped keeps others out until its process exits
+        let sleep = game
+            .add_tool("Sleep", Path::new("/bin/sh"), &["-c", "sleep 0.2"])
+            .unwrap();
+        drop(sleep.launch(&profile).unwrap());
+        assert!(matches!(
+            tool.launch(&profile),
+            Err(crate::Error::Launch(Error::AlreadyRunning))
+        ));
+        let launch = (0..100)
+            .find_map(|_| {
+                thread::sleep(Duration::from_millis(50));
+                tool.launch(&profile).ok()
+            })
+            .unwrap();
+        launch.wait().unwrap();
+        let sleep_run = sleep.runs().unwrap().pop().unwrap();
+        assert_eq!(sleep_run.exit_code().unwrap(), Some(0));
+        assert!(repo.deployments().unwrap().is_empty());
+
+        let output = game.add_mod("Generated LOD", None).unwrap();
+
+        let overwrite_dir = profile.overwrite_dir().unwrap();
+        fs::create_dir_all(&overwrite_dir).unwrap();
+        fs::write(overwrite_dir.join("skse.log"), "log").unwrap();
+
+        // Writes into the deployed target, which is moved into the overwrite directory once
+        // it's undeployed
+        let mut lod = game
+            .add_tool(
+                "LOD",
+                Path::new("/bin/sh"),
+                &["-c", "mkdir -p meshes && pwd > meshes/lod.nif"],
+            )
+            .unwrap();
+        lod.set_working_dir(Some(target.path())).unwrap();
+        lod.set_output_mod(Some(&output)).unwrap();
+        assert_eq!(lod.output_mod().unwrap().map(|m| m.id), Some(output.id));
+
+        let status = lod.launch(&profile).unwrap().wait().unwrap();
+        assert!(status.success());
+
+        assert_eq!(
+            fs::read_to_string(output.dir().unwrap().join("meshes/lod.nif")).unwrap(),
+            format!("{}\n", target.path().display())
+        );
+        assert!(!target.path().join("meshes/lod.nif").exists());
+        assert_eq!(
+            profile.overwrite_files().unwrap(),
+            [PathBuf::from("skse.log")]
+        );
+
+        // A profile that was deployed already keeps the files in its overwrite directory
+        let deployment = repo.deploy(&profile).unwrap();
+        let status = lod.launch(&profile).unwrap().wait().unwrap();
+        assert!(status.success());
+        assert_eq!(repo.deployments().unwrap().len(), 1);
+        repo.undeploy(deployment).unwrap();
+
+        let mut files = profile.overwrite_files().unwrap();
+        files.sort();
+        assert_eq!(
+            files,
+            [PathBuf::from("meshes/lod.nif"), PathBuf::from("skse.log")]
+        );
+
+        lod.set_output_mod(None).unwrap();
+        assert!(lod.output_mod().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_failed_launch() {
+        let repo = Repository::mock();
+        let target = tempdir().unwrap();
+
+        let mut game = repo.add_game("Skyrim", DeployKind::Overlay).unwrap();
+        game.set_targets(vec![target.path().to_path_buf()]).unwrap();
+        game.set_deploy_method(DeployMethod::Symlink).unwrap();
+        let profile = game.add_profile("Test").unwrap();
+        let tool = game
+            .add_tool("Missing", Path::new("/nonexistent/tool"), &[])
+            .unwrap();
+
+        assert!(matches!(
+            tool.launch(&profile),
+            Err(crate::Error::Launch(Error::Spawn(..)))
+        ));
+        assert!(repo.deployments().unwrap().is_empty());
+        let run = tool.runs().unwrap().pop().unwrap();
+        assert!(run.ended().unwrap().is_some());
+        assert_eq!(run.exit_code().unwrap(), None);
+
+        // The slot was given back
+        let shell = game
+            .add_tool("Shell", Path::new("/bin/sh"), &["-c", "exit 0"])
+            .unwrap();
+        assert!(shell.launch(&profile).unwrap().wait().unwrap().success());
+    }
+
+    #[test]
+    fn test_resolved_path() {
+        let repo = Repository::mock();
+        let target = tempdir().unwrap();
+
+        let mut game = repo.add_game("Skyrim", DeployKind::Overlay).unwrap();
+        game.set_targets(vec![target.path().to_path_buf()]).unwrap();
+        let mut tool = game
+            .add_tool("xEdit", Path::new("Tools/SSEEdit.exe"), &[])
+            .unwrap();
+        assert_eq!(
+            tool.resolved_path().unwrap(),
+            PathBuf::from("Tools/SSEEdit.exe")
+        );
+
+        tool.set_run_in_target(true).unwrap();
+        assert_eq!(
+            tool.resolved_path().unwrap(),
+            target.path().join("Tools/SSEEdit.exe")
+        );
+    }
+}
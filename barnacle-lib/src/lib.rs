@@ -4,9 +4,17 @@ use thiserror::Error;
 
 use crate::repository::entities;
 
-// mod deployers;
+pub mod conflicts;
+pub mod definitions;
+pub mod deployers;
+pub mod detect;
+pub mod formats;
 pub mod fs;
+pub mod launch;
+pub mod loot;
+pub mod mounts;
 pub mod repository;
+pub mod runtime;
 
 pub use repository::Repository;
 
@@ -20,4 +28,8 @@ pub enum Error {
     Io(#[from] io::Error),
     #[error("Entity error: {0}")]
     Entity(#[from] entities::Error),
+    #[error("Deploy error: {0}")]
+    Deploy(#[from] deployers::Error),
+    #[error("Launch error: {0}")]
+    Launch(#[from] launch::Error),
 }
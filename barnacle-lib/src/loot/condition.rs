@@ -0,0 +1,220 @@
+//! Evaluation of the conditions LOOT attaches to metadata, such as
+//! `file("Update.esm") and not active("Patch.esp")`.
+//!
+//! Only the functions that depend on which plugins and files exist are supported. Any other
+//! function, like a checksum or version comparison, is unknown, and so is anything that depends
+//! on it, even when negated. Unknown conditions are never met, so the metadata they guard is left
+//! out rather than applied by mistake.
+
+use std::{iter::Peekable, str::Chars};
+
+/// What conditions are evaluated against.
+pub(crate) trait Context {
+    /// Whether a file exists, given relative to the data directory
+    fn file(&self, path: &str) -> bool;
+    /// Whether a plugin is active
+    fn active(&self, plugin: &str) -> bool;
+}
+
+/// Whether a condition is met, or `None` if that's unknown
+type Met = Option<bool>;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Word(String),
+    Text(String),
+    Open,
+    Close,
+    Comma,
+}
+
+/// Evaluate a condition. Conditions that can't be parsed are never met.
+pub(crate) fn evaluate(condition: &str, context: &impl Context) -> bool {
+    let Some(tokens) = tokenize(condition) else {
+        return false;
+    };
+
+    let mut parser = Parser {
+        tokens: tokens.into_iter().peekable(),
+        context,
+    };
+    let result = parser.expression();
+
+    // Anything left over means the condition wasn't understood
+    result.is_some_and(|met| met == Some(true) && parser.tokens.peek().is_none())
+}
+
+fn tokenize(condition: &str) -> Option<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = condition.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '(' => tokens.push(Token::Open),
+            ')' => tokens.push(Token::Close),
+            ',' => tokens.push(Token::Comma),
+            '"' => tokens.push(Token::Text(text(&mut chars)?)),
+            c if c.is_whitespace() => {}
+            // Function names, keywords, checksums and comparison operators
+            c => {
+                let mut word = c.to_string();
+                while let Some(c) = chars.next_if(|c| is_word_char(*c)) {
+                    word.push(c);
+                }
+                tokens.push(Token::Word(word));
+            }
+        }
+    }
+
+    Some(tokens)
+}
+
+fn is_word_char(c: char) -> bool {
+    !c.is_whitespace() && !matches!(c, '(' | ')' | ',' | '"')
+}
+
+/// Read a string up to its closing quote. Paths use backslashes as separators, so they aren't
+/// escapes.
+fn text(chars: &mut Peekable<Chars>) -> Option<String> {
+    let mut text = String::new();
+
+    loop {
+        match chars.next()? {
+            '"' => return Some(text),
+            '\\' => text.push('/'),
+            c => text.push(c),
+        }
+    }
+}
+
+struct Parser<'a, C> {
+    tokens: Peekable<std::vec::IntoIter<Token>>,
+    context: &'a C,
+}
+
+impl<C: Context> Parser<'_, C> {
+    /// `expression = conjunction { "or" conjunction }`
+    fn expression(&mut self) -> Option<Met> {
+        let mut met = self.conjunction()?;
+        while self.keyword("or") {
+            // Both sides are parsed, so that errors on either side are found
+            met = match (met, self.conjunction()?) {
+                (Some(true), _) | (_, Some(true)) => Some(true),
+                (Some(false), Some(false)) => Some(false),
+                _ => None,
+            };
+        }
+
+        Some(met)
+    }
+
+    /// `conjunction = term { "and" term }`
+    fn conjunction(&mut self) -> Option<Met> {
+        let mut met = self.term()?;
+        while self.keyword("and") {
+            met = match (met, self.term()?) {
+                (Some(false), _) | (_, Some(false)) => Some(false),
+                (Some(true), Some(true)) => Some(true),
+                _ => None,
+            };
+        }
+
+        Some(met)
+    }
+
+    /// `term = "not" term | "(" expression ")" | function`
+    fn term(&mut self) -> Option<Met> {
+        if self.keyword("not") {
+            return self.term().map(|met| met.map(|met| !met));
+        }
+
+        match self.tokens.next()? {
+            Token::Open => {
+                let met = self.expression()?;
+                (self.tokens.next()? == Token::Close).then_some(met)
+            }
+            Token::Word(function) => {
+                let arguments = self.arguments()?;
+                let first = arguments.first().map(String::as_str);
+
+                Some(match (function.as_str(), first) {
+                    ("file", Some(path)) => Some(self.context.file(path)),
+                    ("active", Some(plugin)) => Some(self.context.active(plugin)),
+                    _ => None,
+                })
+            }
+            _ => None,
+        }
+    }
+
+    /// `arguments = "(" [ argument { "," argument } ] ")"`, where the arguments are strings or
+    /// bare words such as version comparison operators
+    fn arguments(&mut self) -> Option<Vec<String>> {
+        if self.tokens.next()? != Token::Open {
+            return None;
+        }
+
+        let mut arguments = Vec::new();
+        loop {
+            match self.tokens.next()? {
+                Token::Close => return Some(arguments),
+                Token::Comma => {}
+                Token::Text(text) | Token::Word(text) => arguments.push(text),
+                Token::Open => return None,
+            }
+        }
+    }
+
+    fn keyword(&mut self, keyword: &str) -> bool {
+        self.tokens
+            .next_if(|t| matches!(t, Token::Word(w) if w == keyword))
+            .is_some()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    struct Files;
+
+    impl Context for Files {
+        fn file(&self, path: &str) -> bool {
+            ["Update.esm", "Patch.esp", "SKSE/Plugins/Engine.dll"].contains(&path)
+        }
+
+        fn active(&self, plugin: &str) -> bool {
+            plugin == "Update.esm"
+        }
+    }
+
+    #[test]
+    fn test_evaluate() {
+        for (condition, met) in [
+            (r#"file("Update.esm")"#, true),
+            (r#"file("Missing.esp")"#, false),
+            (r#"file("SKSE\Plugins\Engine.dll")"#, true),
+            (r#"active("Update.esm") and not active("Patch.esp")"#, true),
+            (r#"active("Patch.esp") or file("Patch.esp")"#, true),
+            (r#"not (file("Update.esm") and file("Missing.esp"))"#, true),
+            (r#"checksum("Update.esm", DEADBEEF)"#, false),
+            (r#"not checksum("Update.esm", DEADBEEF)"#, false),
+            (
+                r#"not (checksum("Update.esm", DEADBEEF) and file("Missing.esp"))"#,
+                true,
+            ),
+            (
+                r#"not (checksum("Update.esm", DEADBEEF) or file("Missing.esp"))"#,
+                false,
+            ),
+            (
+                r#"version("Patch.esp", "1.0", >=) or file("Patch.esp")"#,
+                true,
+            ),
+            (r#"file("Update.esm") and"#, false),
+            (r#"file("Update.esm"))"#, false),
+        ] {
+            assert_eq!(evaluate(condition, &Files), met, "{condition}");
+        }
+    }
+}
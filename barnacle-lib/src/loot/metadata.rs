@@ -0,0 +1,301 @@
+//! LOOT's metadata files, the community maintained `masterlist.yaml` and the user's own
+//! `userlist.yaml`.
+
+use std::{fs, path::Path};
+
+use serde::Deserialize;
+
+use crate::loot::{MessageKind, Result};
+
+/// Name of the group plugins without one belong to
+pub(crate) const DEFAULT_GROUP: &str = "default";
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct MetadataFile {
+    globals: Vec<MessageEntry>,
+    groups: Vec<Group>,
+    plugins: Vec<PluginMetadata>,
+}
+
+/// A named set of plugins that loads after other groups.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub(crate) struct Group {
+    pub name: String,
+    pub after: Vec<String>,
+}
+
+/// What the metadata files say about a plugin.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub(crate) struct PluginMetadata {
+    pub name: String,
+    pub group: Option<String>,
+    /// Plugins to load after
+    pub after: Vec<FileEntry>,
+    /// Plugins that have to be installed, and loaded before this one
+    #[serde(rename = "req")]
+    pub requirements: Vec<FileEntry>,
+    /// Plugins that mustn't be installed at the same time
+    #[serde(rename = "inc")]
+    pub incompatibilities: Vec<FileEntry>,
+    #[serde(rename = "msg")]
+    pub messages: Vec<MessageEntry>,
+    /// Versions of the plugin that need cleaning, by their CRC
+    pub dirty: Vec<DirtyInfo>,
+}
+
+/// A reference to a file, given either as its name alone or with a condition.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub(crate) enum FileEntry {
+    Name(String),
+    Detailed {
+        name: String,
+        #[serde(default)]
+        condition: Option<String>,
+    },
+}
+
+impl FileEntry {
+    pub fn name(&self) -> &str {
+        match self {
+            Self::Name(name) | Self::Detailed { name, .. } => name,
+        }
+    }
+
+    pub fn condition(&self) -> Option<&str> {
+        match self {
+            Self::Name(_) => None,
+            Self::Detailed { condition, .. } => condition.as_deref(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct MessageEntry {
+    #[serde(rename = "type")]
+    pub kind: MessageKind,
+    pub content: MessageContent,
+    /// Values for the `{0}`, `{1}`, ... placeholders in the content
+    #[serde(default)]
+    pub subs: Vec<String>,
+    #[serde(default)]
+    pub condition: Option<String>,
+}
+
+impl MessageEntry {
+    /// The English text of the message, with its placeholders filled in
+    pub fn text(&self) -> String {
+        let mut text = match &self.content {
+            MessageContent::Text(text) => text.clone(),
+            MessageContent::Localized(texts) => texts
+                .iter()
+                .find(|t| t.lang == "en")
+                .or(texts.first())
+                .map(|t| t.text.clone())
+                .unwrap_or_default(),
+        };
+
+        for (index, sub) in self.subs.iter().enumerate() {
+            text = text.replace(&format!("{{{index}}}"), sub);
+        }
+
+        text
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub(crate) enum MessageContent {
+    Text(String),
+    Localized(Vec<LocalizedText>),
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct LocalizedText {
+    pub text: String,
+    pub lang: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct DirtyInfo {
+    pub crc: u32,
+    /// The utility and version that found the records
+    pub util: String,
+    /// Identical to master records
+    #[serde(default)]
+    pub itm: u32,
+    /// Deleted references
+    #[serde(default)]
+    pub udr: u32,
+    /// Deleted navmeshes
+    #[serde(default)]
+    pub nav: u32,
+}
+
+/// The combined contents of a masterlist and a userlist.
+///
+/// Plugins are matched by their exact name, ignoring case. Entries that match plugins by a
+/// regular expression are never applied.
+#[derive(Debug, Clone, Default)]
+pub struct Metadata {
+    pub(crate) globals: Vec<MessageEntry>,
+    pub(crate) groups: Vec<Group>,
+    plugins: Vec<PluginMetadata>,
+}
+
+impl Metadata {
+    /// Read a masterlist, and optionally a userlist that adds to it.
+    pub fn load(masterlist: &Path, userlist: Option<&Path>) -> Result<Self> {
+        let userlist = userlist
+            .filter(|path| path.exists())
+            .map(fs::read_to_string)
+            .transpose()?;
+
+        Self::parse(&fs::read_to_string(masterlist)?, userlist.as_deref())
+    }
+
+    pub fn parse(masterlist: &str, userlist: Option<&str>) -> Result<Self> {
+        let mut metadata = Self {
+            groups: vec![Group {
+                name: DEFAULT_GROUP.to_string(),
+                after: Vec::new(),
+            }],
+            ..Default::default()
+        };
+
+        for contents in [Some(masterlist), userlist].into_iter().flatten() {
+            // An empty userlist is an empty document, which isn't a mapping
+            if contents.trim().is_empty() {
+                continue;
+            }
+            let file: MetadataFile = serde_yaml::from_str(contents)?;
+
+            metadata.globals.extend(file.globals);
+            for group in file.groups {
+                match metadata.groups.iter_mut().find(|g| g.name == group.name) {
+                    Some(existing) => existing.after.extend(group.after),
+                    None => metadata.groups.push(group),
+                }
+            }
+            metadata.plugins.extend(file.plugins);
+        }
+
+        Ok(metadata)
+    }
+
+    /// Returns the metadata of a plugin, combined from every entry for it. A group set by a
+    /// later entry, such as one in the userlist, replaces the earlier one.
+    pub(crate) fn plugin(&self, name: &str) -> PluginMetadata {
+        let mut combined = PluginMetadata {
+            name: name.to_string(),
+            ..Default::default()
+        };
+
+        for entry in self
+            .plugins
+            .iter()
+            .filter(|p| p.name.eq_ignore_ascii_case(name))
+        {
+            if entry.group.is_some() {
+                combined.group.clone_from(&entry.group);
+            }
+            combined.after.extend(entry.after.iter().cloned());
+            combined
+                .requirements
+                .extend(entry.requirements.iter().cloned());
+            combined
+                .incompatibilities
+                .extend(entry.incompatibilities.iter().cloned());
+            combined.messages.extend(entry.messages.iter().cloned());
+            combined.dirty.extend(entry.dirty.iter().cloned());
+        }
+
+        combined
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const MASTERLIST: &str = r#"
+prelude:
+  - &patchMessage
+    type: say
+    content: 'A patch for {0} is available.'
+    subs: [ 'Some Mod' ]
+
+globals:
+  - type: warn
+    content:
+      - lang: de
+        text: 'Veraltet'
+      - lang: en
+        text: 'Outdated'
+    condition: 'file("Old.esp")'
+
+groups:
+  - name: Early Loaders
+  - name: default
+    after: [ Early Loaders ]
+  - name: Late Loaders
+    after: [ default ]
+
+plugins:
+  - name: 'Unofficial Patch.esp'
+    group: Early Loaders
+    after: [ 'Base.esm' ]
+    req:
+      - name: 'Dependency.esm'
+        condition: 'active("Other.esp")'
+    inc: [ 'Conflicting.esp' ]
+    msg: [ *patchMessage ]
+    dirty:
+      - crc: 0xDEADBEEF
+        util: 'SSEEdit v4.0.4'
+        itm: 3
+        udr: 1
+"#;
+
+    const USERLIST: &str = r#"
+groups:
+  - name: Late Loaders
+    after: [ Early Loaders ]
+
+plugins:
+  - name: 'unofficial patch.esp'
+    group: Late Loaders
+    after: [ 'Other.esp' ]
+"#;
+
+    #[test]
+    fn test_parse() {
+        let metadata = Metadata::parse(MASTERLIST, Some(USERLIST)).unwrap();
+
+        assert_eq!(metadata.globals.first().unwrap().text(), "Outdated");
+        assert_eq!(metadata.groups.len(), 3);
+        assert_eq!(
+            metadata.groups.last().unwrap().after,
+            ["default", "Early Loaders"]
+        );
+
+        let plugin = metadata.plugin("Unofficial Patch.esp");
+        assert_eq!(plugin.group.as_deref(), Some("Late Loaders"));
+        let after: Vec<&str> = plugin.after.iter().map(FileEntry::name).collect();
+        assert_eq!(after, ["Base.esm", "Other.esp"]);
+        assert_eq!(
+            plugin.requirements.first().unwrap().condition(),
+            Some(r#"active("Other.esp")"#)
+        );
+        assert_eq!(
+            plugin.messages.first().unwrap().text(),
+            "A patch for Some Mod is available."
+        );
+        assert_eq!(plugin.dirty.first().unwrap().crc, 0xDEADBEEF);
+
+        assert!(metadata.plugin("Unknown.esp").after.is_empty());
+    }
+}
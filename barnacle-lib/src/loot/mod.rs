@@ -0,0 +1,489 @@
+//! Plugin sorting with LOOT's metadata, without running LOOT itself.
+//!
+//! The sorter builds a graph of the rules that decide which plugins have to load before
+//! others: a plugin's masters, and the `after` and `req` entries of the masterlist and
+//! userlist. Every order that follows these rules is valid, so among them the one closest to
+//! the current order is picked, with plugins of earlier groups moved ahead where the rules
+//! allow it. That keeps sorting stable, sorting an already sorted order changes nothing.
+
+use std::{
+    cmp::Reverse,
+    collections::{BTreeSet, BinaryHeap, HashMap},
+    io,
+    path::{Path, PathBuf},
+};
+
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::formats::{self, plugin::Plugin};
+
+mod condition;
+mod metadata;
+
+pub use metadata::Metadata;
+
+use condition::Context;
+use metadata::{DEFAULT_GROUP, FileEntry};
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("I/O error: {0}")]
+    Io(#[from] io::Error),
+    #[error("Failed to parse the LOOT metadata: {0}")]
+    Yaml(#[from] serde_yaml::Error),
+    #[error("Failed to read a plugin: {0}")]
+    Format(#[from] formats::Error),
+    #[error("These plugins have to load after each other: {}", .0.join(", "))]
+    Cycle(Vec<String>),
+    #[error("These groups have to load after each other: {}", .0.join(", "))]
+    GroupCycle(Vec<String>),
+    #[error("{1} refers to the group {0}, which doesn't exist")]
+    UnknownGroup(String, String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MessageKind {
+    Say,
+    Warn,
+    Error,
+}
+
+/// Something the metadata has to say about the load order, or about a single plugin.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Message {
+    /// The plugin the message is about, `None` for general messages
+    pub plugin: Option<String>,
+    pub kind: MessageKind,
+    pub text: String,
+}
+
+/// A plugin to sort.
+#[derive(Debug, Clone)]
+pub struct SortPlugin {
+    pub header: Plugin,
+    pub active: bool,
+    /// CRC-32 of the plugin file, to tell whether it's a version that needs cleaning
+    pub crc: u32,
+}
+
+/// The result of [`sort`].
+#[derive(Debug, Clone)]
+pub struct Sorted {
+    /// File names of the plugins, in the order they should load in
+    pub order: Vec<String>,
+    pub messages: Vec<Message>,
+}
+
+struct SortContext<'a> {
+    plugins: &'a [SortPlugin],
+    data_dir: Option<&'a Path>,
+}
+
+impl SortContext<'_> {
+    fn find(&self, name: &str) -> Option<&SortPlugin> {
+        self.plugins
+            .iter()
+            .find(|p| p.header.name.eq_ignore_ascii_case(name))
+    }
+
+    fn met(&self, condition: Option<&str>) -> bool {
+        condition.is_none_or(|condition| condition::evaluate(condition, self))
+    }
+}
+
+impl Context for SortContext<'_> {
+    fn file(&self, path: &str) -> bool {
+        self.find(path).is_some()
+            || self
+                .data_dir
+                .is_some_and(|dir| dir.join(PathBuf::from(path)).exists())
+    }
+
+    fn active(&self, plugin: &str) -> bool {
+        self.find(plugin).is_some_and(|p| p.active)
+    }
+}
+
+/// Sort the given plugins, which are in their current load order. Conditions in the metadata
+/// that check for files look in `data_dir`, besides the plugins themselves.
+pub fn sort(
+    metadata: &Metadata,
+    plugins: &[SortPlugin],
+    data_dir: Option<&Path>,
+) -> Result<Sorted> {
+    let context = SortContext { plugins, data_dir };
+    let index: HashMap<String, usize> = plugins
+        .iter()
+        .enumerate()
+        .map(|(i, p)| (p.header.name.to_lowercase(), i))
+        .collect();
+    let group_ranks = group_ranks(metadata)?;
+
+    let mut after: Vec<BTreeSet<usize>> = vec![BTreeSet::new(); plugins.len()];
+    let mut ranks = Vec::with_capacity(plugins.len());
+    let mut messages: Vec<Message> = metadata
+        .globals
+        .iter()
+        .filter(|m| context.met(m.condition.as_deref()))
+        .map(|m| Message {
+            plugin: None,
+            kind: m.kind,
+            text: m.text(),
+        })
+        .collect();
+
+    for (i, plugin) in plugins.iter().enumerate() {
+        let name = &plugin.header.name;
+        let meta = metadata.plugin(name);
+
+        let group = meta.group.as_deref().unwrap_or(DEFAULT_GROUP);
+        let rank = group_ranks
+            .get(group)
+            .ok_or_else(|| Error::UnknownGroup(group.to_string(), name.clone()))?;
+        ranks.push(*rank);
+
+        let rules = meta
+            .after
+            .iter()
+            .chain(&meta.requirements)
+            .filter(|entry| context.met(entry.condition()))
+            .map(FileEntry::name);
+        for earlier in plugin
+            .header
+            .masters
+            .iter()
+            .map(String::as_str)
+            .chain(rules)
+        {
+            if let Some(&j) = index.get(&earlier.to_lowercase())
+                && j != i
+                && let Some(set) = after.get_mut(i)
+            {
+                set.insert(j);
+            }
+        }
+
+        messages.extend(plugin_messages(&context, plugin, &meta));
+    }
+
+    let order = topological_order(plugins, &after, &ranks)?;
+
+    Ok(Sorted {
+        order: order
+            .into_iter()
+            .filter_map(|i| plugins.get(i).map(|p| p.header.name.clone()))
+            .collect(),
+        messages,
+    })
+}
+
+/// Returns the position of every group in an order where each group comes after the groups it
+/// loads after.
+fn group_ranks(metadata: &Metadata) -> Result<HashMap<&str, usize>> {
+    let groups = &metadata.groups;
+    let mut after: Vec<BTreeSet<usize>> = Vec::with_capacity(groups.len());
+
+    for group in groups {
+        let mut earlier = BTreeSet::new();
+        for name in &group.after {
+            let j = groups
+                .iter()
+                .position(|g| &g.name == name)
+                .ok_or_else(|| Error::UnknownGroup(name.clone(), group.name.clone()))?;
+            earlier.insert(j);
+        }
+        after.push(earlier);
+    }
+
+    let priorities = vec![(false, 0); groups.len()];
+    let order = kahn(&after, &priorities).map_err(|cycle| {
+        Error::GroupCycle(
+            cycle
+                .into_iter()
+                .filter_map(|i| groups.get(i).map(|g| g.name.clone()))
+                .collect(),
+        )
+    })?;
+
+    Ok(order
+        .into_iter()
+        .enumerate()
+        .filter_map(|(rank, i)| Some((groups.get(i)?.name.as_str(), rank)))
+        .collect())
+}
+
+/// Order the plugins so that each one comes after those in `after`, and masters come before
+/// all other plugins.
+fn topological_order(
+    plugins: &[SortPlugin],
+    after: &[BTreeSet<usize>],
+    ranks: &[usize],
+) -> Result<Vec<usize>> {
+    let names = |indices: Vec<usize>| -> Vec<String> {
+        indices
+            .into_iter()
+            .filter_map(|i| plugins.get(i).map(|p| p.header.name.clone()))
+            .collect()
+    };
+    let is_master = |i: usize| {
+        plugins.get(i).is_some_and(|p| {
+            p.header.is_master()
+                || Path::new(&p.header.name)
+                    .extension()
+                    .is_some_and(|e| e.eq_ignore_ascii_case("esl"))
+        })
+    };
+
+    // Masters go first, then groups in order, then the current order
+    let priorities: Vec<(bool, usize)> = ranks
+        .iter()
+        .enumerate()
+        .map(|(i, rank)| (!is_master(i), *rank))
+        .collect();
+    let order = kahn(after, &priorities).map_err(|cycle| Error::Cycle(names(cycle)))?;
+
+    // The rules can force a master after a regular plugin, which the game won't do
+    if let Some(first_regular) = order.iter().position(|i| !is_master(*i))
+        && let Some(master) = order.iter().skip(first_regular).find(|i| is_master(**i))
+    {
+        let regular = order.get(first_regular).copied().unwrap_or_default();
+        return Err(Error::Cycle(names(vec![regular, *master])));
+    }
+
+    Ok(order)
+}
+
+/// Kahn's algorithm, always taking the available node with the lowest priority, and then the
+/// lowest index. Returns the nodes of a cycle if there is one.
+fn kahn(
+    after: &[BTreeSet<usize>],
+    priorities: &[(bool, usize)],
+) -> std::result::Result<Vec<usize>, Vec<usize>> {
+    let mut before: Vec<Vec<usize>> = vec![Vec::new(); after.len()];
+    for (i, earlier) in after.iter().enumerate() {
+        for j in earlier {
+            if let Some(list) = before.get_mut(*j) {
+                list.push(i);
+            }
+        }
+    }
+
+    let mut waiting: Vec<usize> = after.iter().map(BTreeSet::len).collect();
+    let priority = |i: usize| Reverse((priorities.get(i).copied().unwrap_or_default(), i));
+    let mut available: BinaryHeap<_> = waiting
+        .iter()
+        .enumerate()
+        .filter(|(_, count)| **count == 0)
+        .map(|(i, _)| priority(i))
+        .collect();
+
+    let mut order = Vec::with_capacity(after.len());
+    while let Some(Reverse((_, i))) = available.pop() {
+        order.push(i);
+        for later in before.get(i).into_iter().flatten() {
+            if let Some(count) = waiting.get_mut(*later) {
+                *count = count.saturating_sub(1);
+                if *count == 0 {
+                    available.push(priority(*later));
+                }
+            }
+        }
+    }
+
+    if order.len() == after.len() {
+        return Ok(order);
+    }
+
+    // Every node left waits on another one that's left, so following those leads into a cycle
+    let left = |i: usize| waiting.get(i).is_some_and(|count| *count > 0);
+    let Some(mut current) = (0..after.len()).find(|i| left(*i)) else {
+        return Ok(order);
+    };
+    let mut path: Vec<usize> = Vec::new();
+    while !path.contains(&current) {
+        path.push(current);
+        let Some(next) = after
+            .get(current)
+            .and_then(|earlier| earlier.iter().copied().find(|j| left(*j)))
+        else {
+            break;
+        };
+        current = next;
+    }
+    let start = path.iter().position(|i| *i == current).unwrap_or_default();
+    let mut cycle = path.split_off(start);
+    cycle.reverse();
+
+    Err(cycle)
+}
+
+/// The messages about a plugin: its own, along with any missing requirements, installed
+/// incompatibilities and known dirty edits.
+fn plugin_messages(
+    context: &SortContext,
+    plugin: &SortPlugin,
+    meta: &metadata::PluginMetadata,
+) -> Vec<Message> {
+    let message = |kind, text| Message {
+        plugin: Some(plugin.header.name.clone()),
+        kind,
+        text,
+    };
+    let mut messages = Vec::new();
+
+    for entry in &meta.messages {
+        if context.met(entry.condition.as_deref()) {
+            messages.push(message(entry.kind, entry.text()));
+        }
+    }
+
+    for entry in &meta.requirements {
+        if context.met(entry.condition()) && !context.file(entry.name()) {
+            messages.push(message(
+                MessageKind::Error,
+                format!("Requires {}, which isn't installed", entry.name()),
+            ));
+        }
+    }
+
+    for entry in &meta.incompatibilities {
+        if context.met(entry.condition()) && context.file(entry.name()) {
+            messages.push(message(
+                MessageKind::Error,
+                format!("Incompatible with {}, which is installed", entry.name()),
+            ));
+        }
+    }
+
+    for dirty in meta.dirty.iter().filter(|d| d.crc == plugin.crc) {
+        messages.push(message(
+            MessageKind::Warn,
+            format!(
+                "Contains {} identical to master records, {} deleted references and {} deleted \
+                 navmeshes. Clean it with {}.",
+                dirty.itm, dirty.udr, dirty.nav, dirty.util
+            ),
+        ));
+    }
+
+    messages
+}
+
+#[cfg(test)]
+mod test {
+    use tempfile::tempdir;
+
+    use crate::formats::plugin::{self, GameId};
+
+    use super::*;
+
+    fn plugins(plugins: &[(&str, u32, &[&str])]) -> Vec<SortPlugin> {
+        let dir = tempdir().unwrap();
+
+        plugins
+            .iter()
+            .map(|(name, flags, masters)| {
+                let path = dir.path().join(name);
+                plugin::build(&path, GameId::SkyrimSE, *flags, masters);
+
+                SortPlugin {
+                    header: Plugin::read(GameId::SkyrimSE, &path).unwrap(),
+                    active: true,
+                    crc: 0x1234,
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_sort() {
+        let metadata = Metadata::parse(
+            r#"
+groups:
+  - name: Early
+  - name: default
+    after: [ Early ]
+plugins:
+  - name: Early.esp
+    group: Early
+  - name: Tweaks.esp
+    after: [ Patch.esp ]
+    req: [ Missing.esm ]
+    dirty:
+      - crc: 0x1234
+        util: SSEEdit
+        itm: 2
+"#,
+            None,
+        )
+        .unwrap();
+
+        let plugins = plugins(&[
+            ("Tweaks.esp", 0, &[]),
+            ("Patch.esp", 0, &["Base.esm"]),
+            ("Early.esp", 0, &[]),
+            ("Base.esm", 1, &[]),
+        ]);
+
+        let sorted = sort(&metadata, &plugins, None).unwrap();
+
+        assert_eq!(
+            sorted.order,
+            ["Base.esm", "Early.esp", "Patch.esp", "Tweaks.esp"]
+        );
+        let kinds: Vec<MessageKind> = sorted.messages.iter().map(|m| m.kind).collect();
+        assert_eq!(kinds, [MessageKind::Error, MessageKind::Warn]);
+
+        // Sorting again changes nothing
+        let resorted: Vec<SortPlugin> = sorted
+            .order
+            .iter()
+            .filter_map(|name| plugins.iter().find(|p| &p.header.name == name).cloned())
+            .collect();
+        assert_eq!(
+            sort(&metadata, &resorted, None).unwrap().order,
+            sorted.order
+        );
+    }
+
+    #[test]
+    fn test_cycle() {
+        let metadata = Metadata::parse(
+            r#"
+plugins:
+  - name: A.esp
+    after: [ B.esp ]
+"#,
+            None,
+        )
+        .unwrap();
+        let plugins = plugins(&[
+            ("A.esp", 0, &[]),
+            ("B.esp", 0, &["A.esp"]),
+            ("C.esp", 0, &[]),
+        ]);
+
+        match sort(&metadata, &plugins, None) {
+            Err(Error::Cycle(cycle)) => assert_eq!(cycle.len(), 2),
+            other => panic!("Expected a cycle, got {other:?}"),
+        }
+
+        let metadata = Metadata::parse(
+            r#"
+plugins:
+  - name: A.esp
+    group: Missing
+"#,
+            None,
+        )
+        .unwrap();
+        assert!(matches!(
+            sort(&metadata, plugins.get(..1).unwrap(), None),
+            Err(Error::UnknownGroup(..))
+        ));
+    }
+}
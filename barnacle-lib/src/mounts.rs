@@ -0,0 +1,164 @@
+//! Inspection of the mounts that are currently active on the system.
+//!
+//! Barnacle can't rely on its own bookkeeping alone to know what's mounted, since a crash can
+//! leave an overlay behind without any record of it, or a record behind without any overlay.
+//! This module reads `/proc/self/mountinfo` so the two can be compared.
+
+use std::{
+    ffi::OsString,
+    fs, io,
+    os::unix::ffi::OsStringExt,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+const MOUNTINFO_PATH: &str = "/proc/self/mountinfo";
+
+/// A single entry of `/proc/self/mountinfo`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mount {
+    pub mount_point: PathBuf,
+    pub fs_type: String,
+    pub source: String,
+    /// Filesystem specific options, like `lowerdir=...` for OverlayFS
+    pub options: Vec<String>,
+}
+
+impl Mount {
+    pub fn is_overlay(&self) -> bool {
+        matches!(self.fs_type.as_str(), "overlay" | "fuse.fuse-overlayfs")
+    }
+
+    /// Returns the lower directories of an overlay mount, topmost first.
+    pub fn lower_dirs(&self) -> Vec<PathBuf> {
+        self.option("lowerdir")
+            .map(|dirs| dirs.split(':').map(PathBuf::from).collect())
+            .unwrap_or_default()
+    }
+
+    /// Returns the value of a `key=value` filesystem option.
+    pub fn option(&self, key: &str) -> Option<&str> {
+        self.options
+            .iter()
+            .find_map(|o| o.strip_prefix(key)?.strip_prefix('='))
+    }
+}
+
+/// Returns every mount visible to this process.
+pub fn mounts() -> io::Result<Vec<Mount>> {
+    Ok(parse(&fs::read_to_string(MOUNTINFO_PATH)?))
+}
+
+/// Returns the mounts whose mount point is `path` or lies inside of it.
+pub fn mounts_under(path: &Path) -> io::Result<Vec<Mount>> {
+    Ok(mounts()?
+        .into_iter()
+        .filter(|m| m.mount_point.starts_with(path))
+        .collect())
+}
+
+/// Unmount the filesystem mounted at `path`.
+pub fn unmount(path: &Path) -> io::Result<()> {
+    let status = Command::new("umount").arg(path).status()?;
+
+    if status.success() {
+        Ok(())
+    } else {
+        Err(io::Error::other(format!(
+            "umount {} exited with {status}",
+            path.display()
+        )))
+    }
+}
+
+/// Parse the contents of a `mountinfo` file. Malformed lines are skipped.
+pub fn parse(contents: &str) -> Vec<Mount> {
+    contents.lines().filter_map(parse_line).collect()
+}
+
+// Lines look like this, where the optional fields before the `-` separator vary in number:
+// 36 35 98:0 /mnt1 /mnt2 rw,noatime master:1 - ext3 /dev/root rw,errors=continue
+fn parse_line(line: &str) -> Option<Mount> {
+    let (mount_fields, fs_fields) = line.split_once(" - ")?;
+
+    let mount_point = mount_fields.split(' ').nth(4)?;
+
+    let mut fs_fields = fs_fields.split(' ');
+    let fs_type = fs_fields.next()?;
+    let source = fs_fields.next()?;
+    let options = fs_fields.next().unwrap_or_default();
+
+    Some(Mount {
+        mount_point: PathBuf::from(OsString::from_vec(unescape(mount_point))),
+        fs_type: unescape_lossy(fs_type),
+        source: unescape_lossy(source),
+        options: options.split(',').map(unescape_lossy).collect(),
+    })
+}
+
+/// The kernel escapes spaces, tabs, newlines and backslashes as three digit octal sequences.
+fn unescape(field: &str) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(field.len());
+    let mut rest = field.as_bytes();
+
+    while let Some((&byte, tail)) = rest.split_first() {
+        if byte == b'\\'
+            && let Some(code) = tail
+                .get(..3)
+                .and_then(|digits| std::str::from_utf8(digits).ok())
+                .and_then(|digits| u8::from_str_radix(digits, 8).ok())
+        {
+            bytes.push(code);
+            rest = tail.get(3..).unwrap_or_default();
+            continue;
+        }
+
+        bytes.push(byte);
+        rest = tail;
+    }
+
+    bytes
+}
+
+fn unescape_lossy(field: &str) -> String {
+    String::from_utf8_lossy(&unescape(field)).into_owned()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const MOUNTINFO: &str = "\
+22 1 8:2 / / rw,relatime shared:1 - ext4 /dev/sda2 rw
+36 22 0:32 / /home/user/games/Skyrim\\040Special\\040Edition rw,relatime shared:2 - overlay overlay rw,lowerdir=/mods/b:/mods/a:/games/skyrim,upperdir=/profile/override,workdir=/profile/work,userxattr
+garbage line
+";
+
+    #[test]
+    fn test_parse() {
+        let mounts = parse(MOUNTINFO);
+
+        assert_eq!(mounts.len(), 2);
+
+        let root = mounts.first().unwrap();
+        assert_eq!(root.mount_point, PathBuf::from("/"));
+        assert_eq!(root.fs_type, "ext4");
+        assert!(!root.is_overlay());
+
+        let overlay = mounts.last().unwrap();
+        assert_eq!(
+            overlay.mount_point,
+            PathBuf::from("/home/user/games/Skyrim Special Edition")
+        );
+        assert!(overlay.is_overlay());
+        assert_eq!(overlay.option("upperdir"), Some("/profile/override"));
+        assert_eq!(
+            overlay.lower_dirs(),
+            vec![
+                PathBuf::from("/mods/b"),
+                PathBuf::from("/mods/a"),
+                PathBuf::from("/games/skyrim")
+            ]
+        );
+    }
+}
@@ -19,6 +19,15 @@ pub(crate) type CoreConfigHandle = Arc<RwLock<CoreConfig>>;
 pub struct CoreConfig {
     version: u16,
     library_dir: PathBuf,
+    /// Where the logs of launched tools are kept
+    #[serde(default = "default_log_dir")]
+    log_dir: PathBuf,
+    /// The file that's locked while a game or tool is running, shared by every Barnacle process
+    #[serde(default = "default_launch_lock_path")]
+    launch_lock_path: PathBuf,
+    /// Whether a profile deployed to launch a game or tool is undeployed once it exits
+    #[serde(default = "default_undeploy_after_launch")]
+    undeploy_after_launch: bool,
 }
 
 impl CoreConfig {
@@ -52,6 +61,32 @@ impl CoreConfig {
         &self.library_dir
     }
 
+    /// Returns the path to the directory the output of launched tools is
+    /// logged to. If it doesn't exist when this function is called, it will
+    /// be created.
+    pub fn log_dir(&self) -> &Path {
+        fs::create_dir_all(&self.log_dir).unwrap();
+        &self.log_dir
+    }
+
+    /// Returns the path to the file that's locked while a game or tool is
+    /// running. Its directory is created if it doesn't exist.
+    pub fn launch_lock_path(&self) -> &Path {
+        if let Some(dir) = self.launch_lock_path.parent() {
+            fs::create_dir_all(dir).unwrap();
+        }
+        &self.launch_lock_path
+    }
+
+    pub fn undeploy_after_launch(&self) -> bool {
+        self.undeploy_after_launch
+    }
+
+    pub fn set_undeploy_after_launch(&mut self, undeploy: bool) {
+        self.undeploy_after_launch = undeploy;
+        self.save();
+    }
+
     /// Create a new mock [`CoreConfig`] instance for testing
     #[cfg(test)]
     pub(crate) fn mock() -> Self {
@@ -63,6 +98,15 @@ impl CoreConfig {
                 .expect("Temporary directory should exist")
                 .path()
                 .to_path_buf(),
+            log_dir: tempdir()
+                .expect("Temporary directory should exist")
+                .path()
+                .to_path_buf(),
+            launch_lock_path: tempdir()
+                .expect("Temporary directory should exist")
+                .path()
+                .join("launch.lock"),
+            undeploy_after_launch: true,
         }
     }
 }
@@ -75,6 +119,27 @@ impl Default for CoreConfig {
                 .get_data_home()
                 .unwrap()
                 .join("library"),
+            log_dir: default_log_dir(),
+            launch_lock_path: default_launch_lock_path(),
+            undeploy_after_launch: default_undeploy_after_launch(),
         }
     }
 }
+
+fn default_log_dir() -> PathBuf {
+    xdg::BaseDirectories::with_prefix("barnacle")
+        .get_data_home()
+        .unwrap()
+        .join("logs")
+}
+
+fn default_launch_lock_path() -> PathBuf {
+    xdg::BaseDirectories::with_prefix("barnacle")
+        .get_data_home()
+        .unwrap()
+        .join("launch.lock")
+}
+
+fn default_undeploy_after_launch() -> bool {
+    true
+}
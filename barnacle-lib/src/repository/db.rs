@@ -6,9 +6,22 @@ use parking_lot::RwLock;
 
 use crate::{
     fs::data_dir,
-    repository::models::{CURRENT_MODEL_VERSION, ModelVersion},
+    repository::models::{CURRENT_MODEL_VERSION, ModelVersion, migrate},
 };
 
+/// Root nodes that entities are linked to, along with nodes holding global state.
+const ALIASES: [&str; 8] = [
+    "games",
+    "profiles",
+    "mods",
+    "tools",
+    "deployments",
+    "runs",
+    // State
+    "current_profile",
+    "model_version",
+];
+
 #[derive(Debug, Clone, Deref)]
 pub(crate) struct DbHandle {
     #[deref]
@@ -23,28 +36,13 @@ impl DbHandle {
     }
 
     fn init(mut db: DbAny) -> Self {
-        // Insert aliases if they don't exist
-        if db
-            .exec(QueryBuilder::select().aliases().query())
-            .unwrap()
-            .result
-            == 0
-        {
-            db.exec_mut(
-                QueryBuilder::insert()
-                    .nodes()
-                    .aliases([
-                        "games",
-                        "profiles",
-                        "mods",
-                        "tools",
-                        // State
-                        "current_profile",
-                        "model_version",
-                    ])
-                    .query(),
-            )
-            .unwrap();
+        // Insert aliases if they don't exist. Checking each one individually means aliases
+        // added in newer versions also get created in existing databases.
+        for alias in ALIASES {
+            if db.exec(QueryBuilder::select().ids(alias).query()).is_err() {
+                db.exec_mut(QueryBuilder::insert().nodes().aliases([alias]).query())
+                    .unwrap();
+            }
         }
 
         // Fetch the current model version (if any)
@@ -64,8 +62,21 @@ impl DbHandle {
 
         if let Some(mv) = model_version {
             if mv.version() < CURRENT_MODEL_VERSION {
-                // TODO: perform migrations
-                dbg!(mv);
+                migrate(&mut db, mv.version()).unwrap();
+
+                db.exec_mut(
+                    QueryBuilder::insert()
+                        .values_uniform([("version", CURRENT_MODEL_VERSION).into()])
+                        .ids(
+                            QueryBuilder::search()
+                                .from("model_version")
+                                .where_()
+                                .neighbor()
+                                .query(),
+                        )
+                        .query(),
+                )
+                .unwrap();
             }
         } else {
             // Insert default ModelVersion if missing
@@ -105,3 +116,126 @@ impl DbHandle {
         Self::init(DbAny::new_memory("data.db").unwrap())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use crate::repository::{
+        DeployKind, DeployMethod,
+        models::{GameModel, RuntimeKind, ToolModel, v1},
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_migrate_v1() {
+        let mut db = Arc::into_inner(DbHandle::in_memory().db)
+            .unwrap()
+            .into_inner();
+
+        // Store a game and a tool the way version 1 did
+        let id = db
+            .exec_mut(
+                QueryBuilder::insert()
+                    .element(v1::games::GameModel::new(
+                        "Skyrim",
+                        v1::games::DeployKind::CreationEngine,
+                    ))
+                    .query(),
+            )
+            .unwrap()
+            .elements
+            .first()
+            .unwrap()
+            .id;
+        db.exec_mut(QueryBuilder::insert().edges().from("games").to(id).query())
+            .unwrap();
+        let tool_id = db
+            .exec_mut(
+                QueryBuilder::insert()
+                    .element(v1::tools::ToolModel::new(
+                        "SKSE",
+                        PathBuf::from("skse64_loader.exe"),
+                        Some("-forcesteamloader  -log"),
+                    ))
+                    .query(),
+            )
+            .unwrap()
+            .elements
+            .first()
+            .unwrap()
+            .id;
+        db.exec_mut(
+            QueryBuilder::insert()
+                .edges()
+                .from("tools")
+                .to(tool_id)
+                .query(),
+        )
+        .unwrap();
+        db.exec_mut(
+            QueryBuilder::insert()
+                .values_uniform([("version", 1_u64).into()])
+                .ids(
+                    QueryBuilder::search()
+                        .from("model_version")
+                        .where_()
+                        .neighbor()
+                        .query(),
+                )
+                .query(),
+        )
+        .unwrap();
+
+        let db = DbHandle::init(db);
+
+        let game: GameModel = db
+            .read()
+            .exec(
+                QueryBuilder::select()
+                    .elements::<GameModel>()
+                    .ids(id)
+                    .query(),
+            )
+            .unwrap()
+            .try_into()
+            .unwrap();
+        assert_eq!(game.name, "Skyrim");
+        assert_eq!(game.deploy_kind, DeployKind::CreationEngine);
+        assert_eq!(game.deploy_method, DeployMethod::Overlay);
+        assert_eq!(game.runtime, RuntimeKind::Native);
+        assert_eq!(game.steam_app_id, 0);
+
+        let tool: ToolModel = db
+            .read()
+            .exec(
+                QueryBuilder::select()
+                    .elements::<ToolModel>()
+                    .ids(tool_id)
+                    .query(),
+            )
+            .unwrap()
+            .try_into()
+            .unwrap();
+        assert_eq!(tool.args, ["-forcesteamloader", "-log"]);
+        assert!(tool.env_names.is_empty());
+        assert!(!tool.run_in_target);
+
+        let model_version: ModelVersion = db
+            .read()
+            .exec(
+                QueryBuilder::select()
+                    .elements::<ModelVersion>()
+                    .search()
+                    .from("model_version")
+                    .where_()
+                    .neighbor()
+                    .query(),
+            )
+            .unwrap()
+            .try_into()
+            .unwrap();
+        assert_eq!(model_version.version(), CURRENT_MODEL_VERSION);
+    }
+}
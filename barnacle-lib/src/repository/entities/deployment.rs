@@ -0,0 +1,349 @@
+use std::{
+    path::PathBuf,
+    process,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use agdb::{DbId, QueryBuilder, QueryId};
+use tracing::debug;
+
+use crate::{
+    deployers::Setup,
+    repository::{
+        CoreConfigHandle,
+        db::DbHandle,
+        entities::{Result, get_field, profile::Profile},
+        models::{DeploymentModel, ProfileModel},
+    },
+};
+
+/// Represents a record of a deployed [`Profile`] in the Barnacle system.
+///
+/// A record is created whenever a profile is deployed and removed when it's undeployed, so
+/// any record that still exists at startup describes a deployment that outlived Barnacle.
+#[derive(Debug, Clone)]
+pub struct Deployment {
+    pub(crate) id: DbId,
+    pub(crate) db: DbHandle,
+    pub(crate) cfg: CoreConfigHandle,
+}
+
+impl Deployment {
+    pub(crate) fn from_id(id: DbId, db: DbHandle, cfg: CoreConfigHandle) -> Self {
+        Self { id, db, cfg }
+    }
+
+    pub fn targets(&self) -> Result<Vec<PathBuf>> {
+        get_field(&self.db, self.id, "targets")
+    }
+
+    pub fn mount_points(&self) -> Result<Vec<PathBuf>> {
+        get_field(&self.db, self.id, "mount_points")
+    }
+
+    pub fn lower_dirs(&self) -> Result<Vec<PathBuf>> {
+        get_field(&self.db, self.id, "lower_dirs")
+    }
+
+    /// When the deployment happened, in seconds since the Unix epoch
+    pub fn timestamp(&self) -> Result<u64> {
+        get_field(&self.db, self.id, "timestamp")
+    }
+
+    /// Whether the deployment was made by an earlier Barnacle session, which means it was left
+    /// behind by a crash or by exiting without undeploying.
+    pub fn is_stale(&self) -> Result<bool> {
+        let pid: u64 = get_field(&self.db, self.id, "pid")?;
+
+        Ok(pid != u64::from(process::id()))
+    }
+
+    /// Returns the [`Setup`] the profile was deployed with, so that it can be undone the same
+    /// way. Mod directories that no longer exist are left out, since undoing doesn't need them.
+    pub(crate) fn setup(&self) -> Result<Setup> {
+        Ok(Setup {
+            kind: get_field(&self.db, self.id, "deploy_kind")?,
+            method: get_field(&self.db, self.id, "deploy_method")?,
+            targets: self.targets()?,
+            appdata_dir: get_field(&self.db, self.id, "appdata_dir")?,
+            mod_dirs: self
+                .lower_dirs()?
+                .into_iter()
+                .filter(|dir| dir.exists())
+                .collect(),
+            root_files: get_field(&self.db, self.id, "root_files")?,
+        })
+    }
+
+    /// Returns the [`Profile`] that was deployed
+    pub fn profile(&self) -> Result<Profile> {
+        let profile_id = self
+            .db
+            .read()
+            .exec(
+                QueryBuilder::select()
+                    .elements::<ProfileModel>()
+                    .search()
+                    .from(self.id)
+                    .where_()
+                    .neighbor()
+                    .query(),
+            )?
+            .elements
+            .pop()
+            .expect("A successful query should not be empty")
+            .id;
+
+        Ok(Profile::from_id(
+            profile_id,
+            self.db.clone(),
+            self.cfg.clone(),
+        ))
+    }
+
+    /// Record a new deployment of the given [`Profile`].
+    pub(crate) fn add(
+        db: DbHandle,
+        cfg: CoreConfigHandle,
+        profile: &Profile,
+        setup: Setup,
+        mount_points: Vec<PathBuf>,
+    ) -> Result<Self> {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let model = DeploymentModel::new(setup, mount_points, timestamp, u64::from(process::id()));
+
+        let deployment = db.write().transaction_mut(|t| -> Result<Deployment> {
+            let deployment_id = t
+                .exec_mut(QueryBuilder::insert().element(model).query())?
+                .elements
+                .first()
+                .expect("A successful query should not be empty")
+                .id;
+
+            // Link the record to the root "deployments" node
+            t.exec_mut(
+                QueryBuilder::insert()
+                    .edges()
+                    .from(QueryId::from("deployments"))
+                    .to(deployment_id)
+                    .query(),
+            )?;
+
+            // Link the record to the deployed profile
+            t.exec_mut(
+                QueryBuilder::insert()
+                    .edges()
+                    .from(deployment_id)
+                    .to(profile.id)
+                    .query(),
+            )?;
+
+            Ok(Deployment::from_id(deployment_id, db.clone(), cfg.clone()))
+        })?;
+
+        debug!("Recorded deployment of profile: {}", profile.name()?);
+
+        Ok(deployment)
+    }
+
+    pub(crate) fn list(db: DbHandle, cfg: CoreConfigHandle) -> Result<Vec<Deployment>> {
+        Ok(db
+            .read()
+            .exec(
+                QueryBuilder::select()
+                    .elements::<DeploymentModel>()
+                    .search()
+                    .from("deployments")
+                    .where_()
+                    .node()
+                    .and()
+                    .neighbor()
+                    .query(),
+            )?
+            .elements
+            .iter()
+            .map(|e| Deployment::from_id(e.id, db.clone(), cfg.clone()))
+            .collect())
+    }
+
+    /// Delete the record. This doesn't touch anything on disk.
+    pub(crate) fn remove(self) -> Result<()> {
+        self.db
+            .write()
+            .exec_mut(QueryBuilder::remove().ids(self.id).query())?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use tempfile::tempdir;
+
+    use crate::{
+        Repository, deployers,
+        repository::{DeployKind, DeployMethod},
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_add() {
+        let repo = Repository::mock();
+
+        let mut game = repo.add_game("Skyrim", DeployKind::CreationEngine).unwrap();
+        let profile = game.add_profile("Test").unwrap();
+
+        let target_dir = tempdir().unwrap();
+        let target = target_dir.path().to_path_buf();
+        game.set_targets(vec![target.clone()]).unwrap();
+
+        let deployment = Deployment::add(
+            repo.db.clone(),
+            repo.cfg.clone(),
+            &profile,
+            Setup::new(&profile).unwrap(),
+            vec![target.clone()],
+        )
+        .unwrap();
+
+        assert_eq!(repo.deployments().unwrap().len(), 1);
+        assert_eq!(deployment.profile().unwrap().name().unwrap(), "Test");
+        assert!(!deployment.is_stale().unwrap());
+
+        // The target is taken, so deploying on top of it must be refused
+        assert!(matches!(
+            repo.deploy(&profile),
+            Err(crate::Error::Deploy(deployers::Error::TargetInUse(_)))
+        ));
+
+        deployment.remove().unwrap();
+
+        assert!(repo.deployments().unwrap().is_empty());
+    }
+    #[test]
+    fn test_undeploy_recorded() {
+        let repo = Repository::mock();
+        let target = tempdir().unwrap();
+        let other_target = tempdir().unwrap();
+
+        let mut game = repo.add_game("Skyrim", DeployKind::Overlay).unwrap();
+        game.set_targets(vec![target.path().to_path_buf()]).unwrap();
+        game.set_deploy_method(DeployMethod::Symlink).unwrap();
+        let mut profile = game.add_profile("Test").unwrap();
+        let mod_ = game.add_mod("SkyUI", None).unwrap();
+        fs::write(mod_.dir().unwrap().join("skyui.esp"), "plugin").unwrap();
+        profile.add_mod_entry(mod_).unwrap();
+
+        let deployment = repo.deploy(&profile).unwrap();
+        assert_eq!(deployment.setup().unwrap(), Setup::new(&profile).unwrap());
+        assert!(target.path().join("skyui.esp").is_symlink());
+
+        // Undeploying must undo what was deployed, not what the game is set up to deploy now
+        game.set_deploy_method(DeployMethod::Copy).unwrap();
+        game.set_targets(vec![other_target.path().to_path_buf()])
+            .unwrap();
+        repo.undeploy(deployment).unwrap();
+
+        assert!(!target.path().join("skyui.esp").exists());
+        assert!(repo.deployments().unwrap().is_empty());
+    }
+    #[test]
+    fn test_redeploy() {
+        let repo = Repository::mock();
+        let target = tempdir().unwrap();
+
+        let mut game = repo.add_game("Skyrim", DeployKind::Overlay).unwrap();
+        game.set_targets(vec![target.path().to_path_buf()]).unwrap();
+        game.set_deploy_method(DeployMethod::Copy).unwrap();
+        let mut profile = game.add_profile("Test").unwrap();
+        let mod_ = game.add_mod("SkyUI", None).unwrap();
+        let mod_file = mod_.dir().unwrap().join("skyui.esp");
+        fs::write(&mod_file, "plugin").unwrap();
+        profile.add_mod_entry(mod_).unwrap();
+
+        let deployment = repo.deploy(&profile).unwrap();
+
+        // Copies are updated in place
+        fs::write(&mod_file, "updated plugin").unwrap();
+        let deployment = repo.redeploy(deployment).unwrap();
+        assert_eq!(
+            fs::read_to_string(target.path().join("skyui.esp")).unwrap(),
+            "updated plugin"
+        );
+        assert_eq!(repo.deployments().unwrap().len(), 1);
+
+        // A different method means starting over
+        game.set_deploy_method(DeployMethod::Symlink).unwrap();
+        let deployment = repo.redeploy(deployment).unwrap();
+        assert!(target.path().join("skyui.esp").is_symlink());
+        assert_eq!(repo.deployments().unwrap().len(), 1);
+
+        repo.undeploy(deployment).unwrap();
+        assert!(!target.path().join("skyui.esp").exists());
+    }
+    #[test]
+    fn test_root_files() {
+        let repo = Repository::mock();
+        let install_dir = tempdir().unwrap();
+        let data_dir = install_dir.path().join("Data");
+        fs::create_dir_all(&data_dir).unwrap();
+
+        let mut game = repo.add_game("Skyrim", DeployKind::Overlay).unwrap();
+        game.set_targets(vec![data_dir.clone()]).unwrap();
+        game.set_root_files(vec!["skse64_*".to_string()]).unwrap();
+        let mut profile = game.add_profile("Test").unwrap();
+        let mod_ = game.add_mod("SKSE", None).unwrap();
+        fs::write(mod_.dir().unwrap().join("skse64_loader.exe"), "loader").unwrap();
+        fs::write(mod_.dir().unwrap().join("skse64.esp"), "plugin").unwrap();
+        profile.add_mod_entry(mod_).unwrap();
+
+        for method in [DeployMethod::Symlink, DeployMethod::Copy] {
+            game.set_deploy_method(method).unwrap();
+
+            let deployment = repo.deploy(&profile).unwrap();
+            assert_eq!(
+                fs::read_to_string(install_dir.path().join("skse64_loader.exe")).unwrap(),
+                "loader"
+            );
+            assert!(!data_dir.join("skse64_loader.exe").exists());
+            assert!(data_dir.join("skse64.esp").exists());
+
+            // Root files are undone along with the rest, even once the game no longer has them
+            game.set_root_files(Vec::new()).unwrap();
+            repo.undeploy(deployment).unwrap();
+            game.set_root_files(vec!["skse64_*".to_string()]).unwrap();
+
+            assert!(!install_dir.path().join("skse64_loader.exe").exists());
+            assert!(!data_dir.join("skse64.esp").exists());
+        }
+    }
+    #[test]
+    fn test_shared_openmw_config() {
+        let repo = Repository::mock();
+        let config_dir = tempdir().unwrap();
+
+        let [first, second] = ["Morrowind", "Morrowind Rebirth"].map(|name| {
+            let mut game = repo.add_game(name, DeployKind::OpenMW).unwrap();
+            game.set_appdata_dir(config_dir.path()).unwrap();
+            game.add_profile("Test").unwrap()
+        });
+
+        // Both games would write to the same openmw.cfg, which is the one target they have
+        let config_path = config_dir.path().join("openmw.cfg");
+        let deployment = repo.deploy(&first).unwrap();
+        assert_eq!(deployment.targets().unwrap(), [config_path.clone()]);
+        assert!(matches!(
+            repo.deploy(&second),
+            Err(crate::Error::Deploy(deployers::Error::TargetInUse(path))) if path == config_path
+        ));
+
+        repo.undeploy(deployment).unwrap();
+        repo.undeploy(repo.deploy(&second).unwrap()).unwrap();
+    }
+}
@@ -13,13 +13,22 @@ use heck::ToSnakeCase;
 use tracing::debug;
 
 use crate::{
+    deployers::{self, CreationGame},
+    formats::plugin::GameId,
     fs::{Permissions, change_dir_permissions},
     repository::{
         CoreConfigHandle,
         db::DbHandle,
-        entities::{Error, Result, get_field, mod_::Mod, profile::Profile, set_field},
-        models::{DeployKind, GameModel, ModModel, ProfileModel},
+        entities::{
+            Deployment, Error, Result, get_field, mod_::Mod, profile::Profile, set_field,
+            tool::Tool,
+        },
+        models::{
+            DeployKind, DeployMethod, GameModel, ModModel, ProfileModel, RuntimeKind, ToolModel,
+        },
+        snapshot::GameSnapshot,
     },
+    runtime::Runtime,
 };
 
 /// Represents a game entity in the Barnacle system.
@@ -28,7 +37,7 @@ use crate::{
 /// managing profiles and mods. Always reflects the current database state.
 #[derive(Debug, Clone)]
 pub struct Game {
-    id: DbId,
+    pub(crate) id: DbId,
     valid: Arc<AtomicBool>,
     db: DbHandle,
     cfg: CoreConfigHandle,
@@ -74,6 +83,12 @@ impl Game {
         get_field(&self.db, self.id, "targets")
     }
 
+    pub fn set_targets(&mut self, new_targets: Vec<PathBuf>) -> Result<()> {
+        self.is_valid()?;
+
+        set_field(&mut self.db, self.id, "targets", new_targets)
+    }
+
     pub fn deploy_kind(&self) -> Result<DeployKind> {
         self.is_valid()?;
 
@@ -90,6 +105,198 @@ impl Game {
         set_field(&mut self.db, self.id, "deploy_kind", new_deploy_kind)
     }
 
+    pub fn deploy_method(&self) -> Result<DeployMethod> {
+        self.is_valid()?;
+
+        get_field(&self.db, self.id, "deploy_method")
+    }
+
+    pub fn set_deploy_method(&mut self, new_deploy_method: DeployMethod) -> Result<()> {
+        self.is_valid()?;
+
+        if new_deploy_method == self.deploy_method()? {
+            return Ok(());
+        }
+
+        set_field(&mut self.db, self.id, "deploy_method", new_deploy_method)
+    }
+
+    /// The game's folder in `AppData/Local`. A relative path, like `Skyrim Special Edition`,
+    /// is resolved inside the prefix of the game's [`Runtime`]. Empty if it hasn't been
+    /// configured.
+    ///
+    /// For OpenMW, this is the directory containing `openmw.cfg` instead, which defaults to
+    /// `~/.config/openmw` when empty.
+    pub fn appdata_dir(&self) -> Result<PathBuf> {
+        self.is_valid()?;
+
+        let dir: PathBuf = get_field(&self.db, self.id, "appdata_dir")?;
+        if dir.is_relative()
+            && !dir.as_os_str().is_empty()
+            && let Some(local_appdata_dir) = self.runtime()?.local_appdata_dir()
+        {
+            return Ok(local_appdata_dir.join(dir));
+        }
+
+        Ok(dir)
+    }
+
+    pub fn set_appdata_dir(&mut self, new_appdata_dir: &Path) -> Result<()> {
+        self.is_valid()?;
+
+        set_field(
+            &mut self.db,
+            self.id,
+            "appdata_dir",
+            new_appdata_dir.to_path_buf(),
+        )
+    }
+
+    /// The masters the game loads on its own, in order, which are left out of its plugin list.
+    /// Empty if they're to be found from the base master in the game's primary target.
+    pub fn implicit_masters(&self) -> Result<Vec<String>> {
+        self.is_valid()?;
+
+        get_field(&self.db, self.id, "implicit_masters")
+    }
+
+    pub fn set_implicit_masters(&mut self, new_implicit_masters: Vec<String>) -> Result<()> {
+        self.is_valid()?;
+
+        set_field(
+            &mut self.db,
+            self.id,
+            "implicit_masters",
+            new_implicit_masters,
+        )
+    }
+
+    /// Patterns of the files at the top of a mod that are deployed to the install directory, the
+    /// one above the primary target, like script extender loaders. See
+    /// [`Definition::root_files`](crate::definitions::Definition::root_files).
+    pub fn root_files(&self) -> Result<Vec<String>> {
+        self.is_valid()?;
+
+        get_field(&self.db, self.id, "root_files")
+    }
+
+    pub fn set_root_files(&mut self, new_root_files: Vec<String>) -> Result<()> {
+        self.is_valid()?;
+
+        set_field(&mut self.db, self.id, "root_files", new_root_files)
+    }
+
+    pub fn runtime(&self) -> Result<Runtime> {
+        self.is_valid()?;
+
+        let kind: RuntimeKind = get_field(&self.db, self.id, "runtime")?;
+        let path: PathBuf = get_field(&self.db, self.id, "runtime_path")?;
+        let prefix: PathBuf = get_field(&self.db, self.id, "prefix")?;
+
+        Ok(match kind {
+            RuntimeKind::Native => Runtime::Native,
+            RuntimeKind::Wine => Runtime::Wine {
+                binary: path,
+                prefix,
+            },
+            RuntimeKind::Proton => {
+                let app_id: u64 = get_field(&self.db, self.id, "steam_app_id")?;
+                Runtime::Proton {
+                    proton_dir: path,
+                    compat_data: prefix,
+                    app_id: u32::try_from(app_id).unwrap_or_default(),
+                    steam_dir: get_field(&self.db, self.id, "steam_dir")?,
+                }
+            }
+        })
+    }
+
+    pub fn set_runtime(&mut self, new_runtime: &Runtime) -> Result<()> {
+        self.is_valid()?;
+
+        let (kind, path, prefix, app_id, steam_dir) = match new_runtime.clone() {
+            Runtime::Native => (
+                RuntimeKind::Native,
+                PathBuf::new(),
+                PathBuf::new(),
+                0,
+                PathBuf::new(),
+            ),
+            Runtime::Wine { binary, prefix } => {
+                (RuntimeKind::Wine, binary, prefix, 0, PathBuf::new())
+            }
+            Runtime::Proton {
+                proton_dir,
+                compat_data,
+                app_id,
+                steam_dir,
+            } => (
+                RuntimeKind::Proton,
+                proton_dir,
+                compat_data,
+                app_id,
+                steam_dir,
+            ),
+        };
+
+        set_field(&mut self.db, self.id, "runtime", kind)?;
+        set_field(&mut self.db, self.id, "runtime_path", path)?;
+        set_field(&mut self.db, self.id, "prefix", prefix)?;
+        set_field(&mut self.db, self.id, "steam_app_id", u64::from(app_id))?;
+        set_field(&mut self.db, self.id, "steam_dir", steam_dir)
+    }
+
+    /// The game the plugins of this game's mods are made for, told apart by the implicit masters
+    /// of its definition, or by the base master in its primary target for games set up without
+    /// them. `None` for games without plugins, or when no known master is found.
+    pub fn plugin_game(&self) -> Result<Option<GameId>> {
+        let from_masters: fn(&[String]) -> Option<GameId> = match self.deploy_kind()? {
+            DeployKind::OpenMW => return Ok(Some(GameId::Morrowind)),
+            DeployKind::Gamebryo => deployers::gamebryo_game,
+            DeployKind::CreationEngine => {
+                |masters| CreationGame::from_masters(masters).map(CreationGame::plugin_game)
+            }
+            DeployKind::Overlay | DeployKind::BaldursGate3 => return Ok(None),
+        };
+
+        if let Some(game) = from_masters(&self.implicit_masters()?) {
+            return Ok(Some(game));
+        }
+
+        Ok(self
+            .targets()?
+            .first()
+            .and_then(|data_dir| from_masters(&deployers::masters_in(data_dir))))
+    }
+
+    /// Read all of the game's fields at once.
+    pub fn snapshot(&self) -> Result<GameSnapshot> {
+        self.is_valid()?;
+
+        let model: GameModel = self
+            .db
+            .read()
+            .exec(
+                QueryBuilder::select()
+                    .elements::<GameModel>()
+                    .ids(self.id)
+                    .query(),
+            )?
+            .try_into()?;
+
+        Ok(GameSnapshot {
+            id: self.id.0,
+            name: model.name,
+            deploy_kind: model.deploy_kind,
+            deploy_method: model.deploy_method,
+            targets: model.targets,
+            appdata_dir: model.appdata_dir,
+            implicit_masters: model.implicit_masters,
+            root_files: model.root_files,
+            runtime: self.runtime()?,
+        })
+    }
+
     pub fn dir(&self) -> Result<PathBuf> {
         self.is_valid()?;
 
@@ -123,13 +330,10 @@ impl Game {
 
         let model = ProfileModel::new(name);
 
-        if self
-            .profiles()?
-            .iter()
-            .any(|p: &Profile| p.name().unwrap() == model.name)
-        {
-            // return Err(Error::UniqueViolation(UniqueConstraint::ProfileName));
-            panic!("Unique violation")
+        for profile in self.profiles()? {
+            if profile.name()? == model.name {
+                return Err(Error::DuplicateProfile(model.name));
+            }
         }
 
         let profile = self.db.write().transaction_mut(|t| -> Result<Profile> {
@@ -179,6 +383,28 @@ impl Game {
         Ok(())
     }
 
+    /// Add a new [`Profile`] with the same mod entries and plugin order as an existing one.
+    pub fn duplicate_profile(&mut self, profile: &Profile, name: &str) -> Result<Profile> {
+        self.is_valid()?;
+
+        let mut copy = self.add_profile(name)?;
+        for entry in profile.mod_entries()? {
+            let mut copied_entry = copy.add_mod_entry(entry.mod_())?;
+            copied_entry.set_enabled(entry.enabled()?)?;
+            copied_entry.set_notes(&entry.notes()?)?;
+        }
+
+        let mut plugin_order = Vec::new();
+        for entry in profile.plugin_entries()? {
+            plugin_order.push((entry.name()?, entry.enabled()?));
+        }
+        copy.write_plugin_entries(&plugin_order)?;
+
+        debug!("Duplicated profile {} as {name}", profile.name()?);
+
+        Ok(copy)
+    }
+
     pub fn profiles(&self) -> Result<Vec<Profile>> {
         self.is_valid()?;
 
@@ -206,15 +432,17 @@ impl Game {
         self.is_valid()?;
 
         let new_mod = ModModel::new(name);
+        let mod_dir = self.dir()?.join(name.to_snake_case());
+        fs::create_dir_all(&mod_dir).unwrap();
 
         // TODO: Only attempt to open the archive if the input_path is an archive
         if let Some(path) = path {
-            let archive = File::open(path).unwrap();
-            uncompress_archive(archive, &self.dir()?, Ownership::Preserve).unwrap();
-            change_dir_permissions(&self.dir()?, Permissions::ReadOnly);
+            let archive = File::open(path)?;
+            uncompress_archive(archive, &mod_dir, Ownership::Preserve)?;
+            change_dir_permissions(&mod_dir, Permissions::ReadOnly);
         }
 
-        self.db.write().transaction_mut(|t| -> Result<Mod> {
+        let mod_ = self.db.write().transaction_mut(|t| -> Result<Mod> {
             let mod_id = t
                 .exec_mut(QueryBuilder::insert().element(new_mod).query())?
                 .elements
@@ -232,7 +460,141 @@ impl Game {
             )?;
 
             Ok(Mod::from_id(mod_id, self.db.clone(), self.cfg.clone()))
-        })
+        })?;
+
+        if self.deploy_kind()? == DeployKind::BaldursGate3 {
+            mod_.index_modules()?;
+        }
+
+        Ok(mod_)
+    }
+
+    /// Remove a [`Mod`] along with its files and the entries of every profile that point to it.
+    /// Refuses while one of those profiles is deployed.
+    pub fn remove_mod(&mut self, mod_: Mod) -> Result<()> {
+        self.is_valid()?;
+
+        // Deployments are undone from the profile's mod entries, so they can't change until then
+        for deployment in Deployment::list(self.db.clone(), self.cfg.clone())? {
+            let profile = deployment.profile()?;
+            if profile
+                .mod_entries()?
+                .iter()
+                .any(|entry| entry.mod_id == mod_.id)
+            {
+                return Err(Error::ModDeployed(profile.name()?));
+            }
+        }
+
+        let name = mod_.name()?;
+        let dir = mod_.dir()?;
+
+        for mut profile in self.profiles()? {
+            for entry in profile.mod_entries()? {
+                if entry.mod_id == mod_.id {
+                    profile.remove_mod_entry(entry)?;
+                }
+            }
+        }
+
+        self.db
+            .write()
+            .exec_mut(QueryBuilder::remove().ids(mod_.id).query())?;
+
+        if dir.exists() {
+            // Mod directories are kept read-only
+            change_dir_permissions(&dir, Permissions::ReadWrite);
+            fs::remove_dir_all(dir)?;
+        }
+
+        debug!("Removed mod: {name}");
+
+        Ok(())
+    }
+
+    pub fn mods(&self) -> Result<Vec<Mod>> {
+        self.is_valid()?;
+
+        Ok(self
+            .db
+            .read()
+            .exec(
+                QueryBuilder::select()
+                    .elements::<ModModel>()
+                    .search()
+                    .from(self.id)
+                    .where_()
+                    .node()
+                    .and()
+                    .neighbor()
+                    .query(),
+            )?
+            .elements
+            .iter()
+            .map(|e| Mod::from_id(e.id, self.db.clone(), self.cfg.clone()))
+            .collect())
+    }
+
+    /// Add a [`Tool`] that runs the executable at the given path with the given arguments.
+    pub fn add_tool(&mut self, name: &str, path: &Path, args: &[&str]) -> Result<Tool> {
+        self.is_valid()?;
+
+        let model = ToolModel::new(name, path.to_path_buf(), args);
+
+        let tool = self.db.write().transaction_mut(|t| -> Result<Tool> {
+            let tool_id = t
+                .exec_mut(QueryBuilder::insert().element(model).query())?
+                .elements
+                .first()
+                .expect("A successful query should not be empty")
+                .id;
+
+            // Link Tool to the specified Game node and root "tools" node
+            t.exec_mut(
+                QueryBuilder::insert()
+                    .edges()
+                    .from([QueryId::from("tools"), QueryId::from(self.id)])
+                    .to(tool_id)
+                    .query(),
+            )?;
+
+            Ok(Tool::from_id(tool_id, self.db.clone(), self.cfg.clone()))
+        })?;
+
+        debug!("Added tool: {name}");
+
+        Ok(tool)
+    }
+
+    pub fn remove_tool(&mut self, tool: Tool) -> Result<()> {
+        self.is_valid()?;
+
+        self.db
+            .write()
+            .exec_mut(QueryBuilder::remove().ids(tool.id).query())?;
+
+        Ok(())
+    }
+
+    pub fn tools(&self) -> Result<Vec<Tool>> {
+        self.is_valid()?;
+
+        Ok(self
+            .db
+            .read()
+            .exec(
+                QueryBuilder::select()
+                    .elements::<ToolModel>()
+                    .search()
+                    .from(self.id)
+                    .where_()
+                    .neighbor()
+                    .query(),
+            )?
+            .elements
+            .iter()
+            .map(|e| Tool::from_id(e.id, self.db.clone(), self.cfg.clone()))
+            .collect())
     }
 
     /// Ensure that the entity is pointing to an existent model in the database
@@ -306,8 +668,16 @@ impl Game {
     }
 }
 
+impl PartialEq for Game {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
 #[cfg(test)]
 mod test {
+    use tempfile::tempdir;
+
     use crate::Repository;
 
     use super::*;
@@ -355,6 +725,170 @@ mod test {
         assert_eq!(game.name().unwrap(), "Skyrim 2: Electric Boogaloo");
     }
 
+    #[test]
+    fn test_tools() {
+        let repo = Repository::mock();
+
+        let mut game = repo.add_game("Skyrim", DeployKind::CreationEngine).unwrap();
+        let tool = game
+            .add_tool(
+                "SKSE",
+                Path::new("/games/skyrim/skse64_loader.exe"),
+                &["-forcesteamloader"],
+            )
+            .unwrap();
+
+        assert_eq!(game.tools().unwrap(), [tool.clone()]);
+        assert_eq!(tool.name().unwrap(), "SKSE");
+        assert_eq!(tool.args().unwrap(), ["-forcesteamloader"]);
+        assert_eq!(tool.parent().unwrap().name().unwrap(), "Skyrim");
+
+        game.remove_tool(tool).unwrap();
+
+        assert!(game.tools().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_mods() {
+        let repo = Repository::mock();
+
+        let mut game = repo.add_game("Morrowind", DeployKind::OpenMW).unwrap();
+        let mut profile = game.add_profile("Test").unwrap();
+        for name in ["Base", "Patch"] {
+            let mod_ = game.add_mod(name, None).unwrap();
+            let mut entry = profile.add_mod_entry(mod_).unwrap();
+            entry.set_enabled(name == "Patch").unwrap();
+        }
+        assert_eq!(game.mods().unwrap().len(), 2);
+
+        let copy = game.duplicate_profile(&profile, "Copy").unwrap();
+        let entries = copy.mod_entries().unwrap();
+        assert_eq!(entries.len(), 2);
+        assert!(!entries.first().unwrap().enabled().unwrap());
+        assert!(entries.last().unwrap().enabled().unwrap());
+
+        let base = entries.first().unwrap().mod_();
+        let dir = base.dir().unwrap();
+        game.remove_mod(base).unwrap();
+
+        assert!(!dir.exists());
+        assert_eq!(game.mods().unwrap().len(), 1);
+        for profile in [profile, copy] {
+            let entries = profile.mod_entries().unwrap();
+            assert_eq!(entries.len(), 1);
+            assert_eq!(entries.first().unwrap().name().unwrap(), "Patch");
+        }
+    }
+
+    #[test]
+    fn test_add_errors() {
+        let repo = Repository::mock();
+
+        let mut game = repo.add_game("Skyrim", DeployKind::Overlay).unwrap();
+        game.add_profile("Test").unwrap();
+        assert!(matches!(
+            game.add_profile("Test"),
+            Err(Error::DuplicateProfile(name)) if name == "Test"
+        ));
+        assert!(matches!(
+            game.add_mod("Missing", Some(Path::new("/nonexistent/mod.zip"))),
+            Err(Error::Io(_))
+        ));
+    }
+
+    #[test]
+    fn test_remove_deployed_mod() {
+        let repo = Repository::mock();
+        let target = tempdir().unwrap();
+
+        let mut game = repo.add_game("Skyrim", DeployKind::Overlay).unwrap();
+        game.set_targets(vec![target.path().to_path_buf()]).unwrap();
+        game.set_deploy_method(DeployMethod::Symlink).unwrap();
+        let mut profile = game.add_profile("Test").unwrap();
+        let mod_ = game.add_mod("SkyUI", None).unwrap();
+        profile.add_mod_entry(mod_.clone()).unwrap();
+
+        let deployment = repo.deploy(&profile).unwrap();
+        assert!(matches!(
+            game.remove_mod(mod_.clone()),
+            Err(Error::ModDeployed(name)) if name == "Test"
+        ));
+        assert_eq!(game.mods().unwrap().len(), 1);
+
+        repo.undeploy(deployment).unwrap();
+        game.remove_mod(mod_).unwrap();
+        assert!(game.mods().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_plugin_game() {
+        let repo = Repository::mock();
+        let target = tempdir().unwrap();
+
+        let mut game = repo.add_game("Skyrim", DeployKind::CreationEngine).unwrap();
+        game.set_targets(vec![target.path().to_path_buf()]).unwrap();
+        assert_eq!(game.plugin_game().unwrap(), None);
+
+        // Masters are found regardless of their case
+        File::create(target.path().join("skyrim.esm")).unwrap();
+        assert_eq!(game.plugin_game().unwrap(), Some(GameId::SkyrimSE));
+        File::create(target.path().join("SkyrimVR.esm")).unwrap();
+        assert_eq!(game.plugin_game().unwrap(), Some(GameId::SkyrimVR));
+
+        // The definition's masters take precedence over what's in the target
+        game.set_implicit_masters(
+            CreationGame::Fallout4
+                .base_masters()
+                .iter()
+                .map(|master| master.to_string())
+                .collect(),
+        )
+        .unwrap();
+        assert_eq!(game.plugin_game().unwrap(), Some(GameId::Fallout4));
+
+        let mut oblivion = repo.add_game("Oblivion", DeployKind::Gamebryo).unwrap();
+        oblivion
+            .set_implicit_masters(vec!["Oblivion.esm".to_string()])
+            .unwrap();
+        assert_eq!(oblivion.plugin_game().unwrap(), Some(GameId::Oblivion));
+    }
+
+    #[test]
+    fn test_runtime() {
+        let repo = Repository::mock();
+
+        let mut game = repo.add_game("Skyrim", DeployKind::CreationEngine).unwrap();
+        game.set_appdata_dir(Path::new("Skyrim Special Edition"))
+            .unwrap();
+
+        assert_eq!(game.runtime().unwrap(), Runtime::Native);
+        assert_eq!(
+            game.appdata_dir().unwrap(),
+            Path::new("Skyrim Special Edition")
+        );
+
+        let runtime = Runtime::Proton {
+            proton_dir: PathBuf::from("/steam/steamapps/common/Proton 9.0"),
+            compat_data: PathBuf::from("/steam/steamapps/compatdata/489830"),
+            app_id: 489830,
+            steam_dir: PathBuf::from("/steam"),
+        };
+        game.set_runtime(&runtime).unwrap();
+
+        assert_eq!(game.runtime().unwrap(), runtime);
+        assert_eq!(
+            game.appdata_dir().unwrap(),
+            Path::new(
+                "/steam/steamapps/compatdata/489830/pfx/drive_c/users/steamuser/AppData/Local/\
+                 Skyrim Special Edition"
+            )
+        );
+
+        // Absolute paths are taken as they are
+        game.set_appdata_dir(Path::new("/appdata")).unwrap();
+        assert_eq!(game.appdata_dir().unwrap(), Path::new("/appdata"));
+    }
+
     #[test]
     fn test_dir() {
         let repo = Repository::mock();
@@ -4,23 +4,29 @@
 //! the system. They provide a unified interface for inspecting and mutating
 //! these elements, handling all necessary operations behind the scenes.
 
-use std::fmt::Debug;
+use std::{fmt::Debug, io};
 
 use agdb::{DbId, DbValue, QueryBuilder};
 use thiserror::Error;
 
 use crate::repository::db::DbHandle;
 
+mod deployment;
 mod game;
 mod mod_;
 mod mod_entry;
+mod plugin_entry;
 mod profile;
+mod run;
 mod tool;
 
+pub use deployment::Deployment;
 pub use game::Game;
 pub use mod_::Mod;
 pub use mod_entry::ModEntry;
-pub use profile::Profile;
+pub use plugin_entry::{PluginCounts, PluginEntry, PluginIssue};
+pub use profile::{MissingDependency, Profile};
+pub use run::Run;
 pub use tool::Tool;
 
 pub type Result<T> = std::result::Result<T, Error>;
@@ -31,6 +37,20 @@ pub enum Error {
     Internal(#[from] agdb::DbError),
     #[error("This entity refers to a model that has been deleted")]
     StaleEntity,
+    #[error("I/O error: {0}")]
+    Io(#[from] io::Error),
+    #[error("The overwrite directory is in use by a mounted overlay")]
+    OverwriteInUse,
+    #[error("No profile is selected")]
+    NoCurrentProfile,
+    #[error("A profile named {0} already exists")]
+    DuplicateProfile(String),
+    #[error("The mod is part of the deployed profile {0}")]
+    ModDeployed(String),
+    #[error("Failed to extract the mod archive: {0}")]
+    Archive(#[from] compress_tools::Error),
+    #[error("Failed to sort plugins: {0}")]
+    Sort(#[from] crate::loot::Error),
 }
 
 pub(crate) fn get_field<T>(db: &DbHandle, id: DbId, field: &str) -> Result<T>
@@ -2,12 +2,20 @@ use std::path::PathBuf;
 
 use agdb::{DbId, QueryBuilder};
 use heck::ToSnakeCase;
+use tracing::warn;
 
-use crate::repository::{
-    CoreConfigHandle,
-    db::DbHandle,
-    entities::{Result, game::Game, get_field},
-    models::GameModel,
+use crate::{
+    formats::{
+        lspk::{self, Package},
+        lsx::ModuleInfo,
+    },
+    repository::{
+        CoreConfigHandle,
+        db::DbHandle,
+        entities::{Result, game::Game, get_field},
+        models::{GameModel, ModModel, ModuleModel},
+        snapshot::ModSnapshot,
+    },
 };
 
 /// Represents a mod entity in the Barnacle system.
@@ -34,6 +42,103 @@ impl Mod {
         Ok(self.parent()?.dir()?.join(self.name()?.to_snake_case()))
     }
 
+    /// Returns the modules found in this mod's packages when it was installed, for games like
+    /// Baldur's Gate 3 whose mods are packaged modules.
+    pub fn modules(&self) -> Result<Vec<ModuleInfo>> {
+        let models: Vec<ModuleModel> = self
+            .db
+            .read()
+            .exec(
+                QueryBuilder::select()
+                    .elements::<ModuleModel>()
+                    .search()
+                    .from(self.id)
+                    .where_()
+                    .neighbor()
+                    .query(),
+            )?
+            .try_into()?;
+
+        Ok(models.into_iter().map(ModuleInfo::from).collect())
+    }
+
+    /// Read the metadata of the packages at the top of the mod directory, replacing what was
+    /// stored before. Packages that can't be read are skipped with a warning.
+    pub(crate) fn index_modules(&self) -> Result<()> {
+        let mut modules = Vec::new();
+        for path in lspk::packages_in(&self.dir()?)? {
+            if lspk::is_part(&path) {
+                continue;
+            }
+
+            match Package::open(&path).and_then(|p| ModuleInfo::from_package(&p)) {
+                Ok(Some(module)) => modules.push(ModuleModel::from(&module)),
+                Ok(None) => {}
+                Err(e) => warn!("Failed to read the metadata of {}: {e}", path.display()),
+            }
+        }
+
+        self.db.write().transaction_mut(|t| -> Result<()> {
+            let old_ids: Vec<DbId> = t
+                .exec(
+                    QueryBuilder::select()
+                        .elements::<ModuleModel>()
+                        .search()
+                        .from(self.id)
+                        .where_()
+                        .neighbor()
+                        .query(),
+                )?
+                .elements
+                .iter()
+                .map(|e| e.id)
+                .collect();
+            t.exec_mut(QueryBuilder::remove().ids(old_ids).query())?;
+
+            if modules.is_empty() {
+                return Ok(());
+            }
+
+            let module_ids: Vec<DbId> = t
+                .exec_mut(QueryBuilder::insert().elements(&modules).query())?
+                .elements
+                .iter()
+                .map(|e| e.id)
+                .collect();
+            t.exec_mut(
+                QueryBuilder::insert()
+                    .edges()
+                    .from(self.id)
+                    .to(module_ids)
+                    .each()
+                    .query(),
+            )?;
+
+            Ok(())
+        })
+    }
+
+    /// Read all of the mod's fields at once.
+    pub fn snapshot(&self) -> Result<ModSnapshot> {
+        let model: ModModel = self
+            .db
+            .read()
+            .exec(
+                QueryBuilder::select()
+                    .elements::<ModModel>()
+                    .ids(self.id)
+                    .query(),
+            )?
+            .try_into()?;
+
+        Ok(ModSnapshot {
+            id: self.id.0,
+            game_id: self.parent()?.id.0,
+            name: model.name,
+            dir: self.dir()?,
+        })
+    }
+
     /// Returns the parent [`Game`] of this [`Mod`]
     pub fn parent(&self) -> Result<Game> {
         let parent_game_id = self
@@ -1,9 +1,11 @@
-use agdb::DbId;
+use agdb::{DbId, QueryBuilder};
 
 use crate::repository::{
     CoreConfigHandle,
     db::DbHandle,
-    entities::{Result, get_field},
+    entities::{Result, get_field, mod_::Mod, set_field},
+    models::ModEntryModel,
+    snapshot::ModEntrySnapshot,
 };
 
 /// Represents a mod entry in the Barnacle system.
@@ -17,14 +19,21 @@ pub struct ModEntry {
     /// The ID of the ModModel the entry points to
     pub(crate) mod_id: DbId,
     pub(crate) db: DbHandle,
+    pub(crate) cfg: CoreConfigHandle,
 }
 
 impl ModEntry {
-    pub(crate) fn from_id(entry_id: DbId, mod_id: DbId, db: DbHandle) -> Self {
+    pub(crate) fn from_id(
+        entry_id: DbId,
+        mod_id: DbId,
+        db: DbHandle,
+        cfg: CoreConfigHandle,
+    ) -> Self {
         Self {
             entry_id,
             mod_id,
             db,
+            cfg,
         }
     }
 
@@ -36,7 +45,48 @@ impl ModEntry {
         get_field(&self.db, self.entry_id, "enabled")
     }
 
+    pub fn set_enabled(&mut self, enabled: bool) -> Result<()> {
+        set_field(&mut self.db, self.entry_id, "enabled", enabled)
+    }
+
     pub fn notes(&self) -> Result<String> {
         get_field(&self.db, self.entry_id, "notes")
     }
+
+    pub fn set_notes(&mut self, notes: &str) -> Result<()> {
+        set_field(&mut self.db, self.entry_id, "notes", notes)
+    }
+
+    /// Read all of the entry's fields at once.
+    pub fn snapshot(&self) -> Result<ModEntrySnapshot> {
+        let model: ModEntryModel = self
+            .db
+            .read()
+            .exec(
+                QueryBuilder::select()
+                    .elements::<ModEntryModel>()
+                    .ids(self.entry_id)
+                    .query(),
+            )?
+            .try_into()?;
+
+        Ok(ModEntrySnapshot {
+            id: self.entry_id.0,
+            mod_id: self.mod_id.0,
+            name: self.name()?,
+            enabled: model.enabled,
+            notes: model.notes,
+        })
+    }
+
+    /// Returns the [`Mod`] this entry points to
+    pub fn mod_(&self) -> Mod {
+        Mod::from_id(self.mod_id, self.db.clone(), self.cfg.clone())
+    }
+}
+
+impl PartialEq for ModEntry {
+    fn eq(&self, other: &Self) -> bool {
+        self.entry_id == other.entry_id
+    }
 }
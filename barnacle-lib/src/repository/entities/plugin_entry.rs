@@ -0,0 +1,86 @@
+use agdb::DbId;
+
+use crate::repository::{
+    CoreConfigHandle,
+    db::DbHandle,
+    entities::{Result, get_field, mod_entry::ModEntry, set_field},
+    models::PluginLimit,
+};
+
+/// Represents a plugin in a profile's plugin order.
+///
+/// The plugin order is kept apart from the order of the mod entries, since the order in which
+/// mods override each other's files has nothing to do with the order the game loads plugins
+/// in. Always reflects the current database state.
+#[derive(Debug, Clone)]
+pub struct PluginEntry {
+    pub(crate) id: DbId,
+    pub(crate) db: DbHandle,
+    pub(crate) cfg: CoreConfigHandle,
+}
+
+impl PluginEntry {
+    pub(crate) fn from_id(id: DbId, db: DbHandle, cfg: CoreConfigHandle) -> Self {
+        Self { id, db, cfg }
+    }
+
+    /// File name of the plugin
+    pub fn name(&self) -> Result<String> {
+        get_field(&self.db, self.id, "name")
+    }
+
+    pub fn enabled(&self) -> Result<bool> {
+        get_field(&self.db, self.id, "enabled")
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) -> Result<()> {
+        set_field(&mut self.db, self.id, "enabled", enabled)
+    }
+}
+
+impl PartialEq for PluginEntry {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+/// A problem with a profile's plugin order, found by
+/// [`Profile::validate_plugins`](super::Profile::validate_plugins).
+#[derive(Debug, Clone)]
+pub enum PluginIssue {
+    /// An enabled plugin needs a master that neither the profile nor the game provides
+    MissingMaster { plugin: String, master: String },
+    /// An enabled plugin needs a master that is disabled
+    DisabledMaster { plugin: String, master: String },
+    /// A master loads after a plugin that needs it
+    MasterAfterDependent { plugin: String, master: String },
+    /// An enabled plugin is only provided by mods that are disabled
+    ProviderDisabled { plugin: String, entry: ModEntry },
+    /// The header of an enabled plugin couldn't be read
+    Unreadable { plugin: String, error: String },
+    /// More plugins are enabled than the game can load. `light` tells whether it's the limit
+    /// for light plugins that's exceeded.
+    TooManyPlugins {
+        light: bool,
+        count: usize,
+        limit: usize,
+    },
+}
+
+/// How many plugins a profile loads, returned by
+/// [`Profile::plugin_counts`](super::Profile::plugin_counts).
+///
+/// The game's own plugins are included. Starfield's medium plugins are counted as full ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PluginCounts {
+    pub full: usize,
+    pub light: usize,
+    pub limit: PluginLimit,
+}
+
+impl PluginCounts {
+    /// Whether the game can't load all of the plugins
+    pub fn exceeded(&self) -> bool {
+        self.full > self.limit.full || self.light > self.limit.light
+    }
+}
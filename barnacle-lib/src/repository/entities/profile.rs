@@ -1,15 +1,56 @@
-use std::{fs, path::PathBuf};
+use std::{
+    collections::HashSet,
+    fs, io,
+    path::{Component, Path, PathBuf},
+};
 
 use agdb::{CountComparison, DbId, QueryBuilder};
 use heck::ToSnakeCase;
 
-use crate::repository::{
-    CoreConfigHandle,
-    db::DbHandle,
-    entities::{Result, game::Game, get_field, mod_::Mod, mod_entry::ModEntry, set_field},
-    models::{GameModel, ModEntryModel, ModModel, ProfileModel},
+use crate::{
+    conflicts::{Conflicts, list_files},
+    formats::{
+        lsx::Dependency,
+        plugin::{GameId, Plugin},
+    },
+    fs::{Permissions, change_dir_permissions, move_file, remove_empty_dirs},
+    loot::{self, Metadata, SortPlugin},
+    mounts,
+    repository::{
+        CoreConfigHandle,
+        db::DbHandle,
+        entities::{
+            Error, Result,
+            game::Game,
+            get_field,
+            mod_::Mod,
+            mod_entry::ModEntry,
+            plugin_entry::{PluginCounts, PluginEntry, PluginIssue},
+            set_field,
+        },
+        models::{GameModel, ModEntryModel, ModModel, PluginEntryModel, ProfileModel},
+        snapshot::ProfileSnapshot,
+    },
 };
 
+/// A dependency of a module in an enabled mod that no enabled mod provides.
+#[derive(Debug, Clone)]
+pub struct MissingDependency {
+    /// The entry of the mod the module belongs to
+    pub entry: ModEntry,
+    /// Name of the module that has the dependency
+    pub module: String,
+    pub dependency: Dependency,
+}
+
+/// A plugin at the top of a mod entry's directory.
+struct PluginSource {
+    name: String,
+    entry: ModEntry,
+    enabled: bool,
+    path: PathBuf,
+}
+
 /// Represents a profile entity in the Barnacle system.
 ///
 /// Provides methods to inspect and modify this profile's data, including
@@ -53,6 +94,13 @@ impl Profile {
         Ok(self.parent()?.dir()?.join(self.name()?.to_snake_case()))
     }
 
+    /// Returns the directory that collects the files created in the game while this profile is
+    /// deployed, like generated LODs, co-saves or config files. OverlayFS writes them here
+    /// directly, while the other deploy methods move them here when undeploying.
+    pub fn overwrite_dir(&self) -> Result<PathBuf> {
+        Ok(self.dir()?.join("overlay").join("override"))
+    }
+
     pub(crate) fn set_current(db: DbHandle, profile: &Profile) -> Result<()> {
         db.write().transaction_mut(|t| {
             // Delete existing current_profile, if it exists
@@ -91,12 +139,37 @@ impl Profile {
             )?
             .elements
             .first()
-            .expect("A successful query should not be empty")
+            .ok_or(Error::NoCurrentProfile)?
             .id;
 
         Ok(Profile::from_id(id, db.clone(), cfg.clone()))
     }
 
+    /// Read all of the profile's fields at once, along with its mod entries.
+    pub fn snapshot(&self) -> Result<ProfileSnapshot> {
+        let model: ProfileModel = self
+            .db
+            .read()
+            .exec(
+                QueryBuilder::select()
+                    .elements::<ProfileModel>()
+                    .ids(self.id)
+                    .query(),
+            )?
+            .try_into()?;
+
+        Ok(ProfileSnapshot {
+            id: self.id.0,
+            game_id: self.parent()?.id.0,
+            name: model.name,
+            mod_entries: self
+                .mod_entries()?
+                .iter()
+                .map(ModEntry::snapshot)
+                .collect::<Result<_>>()?,
+        })
+    }
+
     /// Returns the parent [`Game`] of this [`Profile`]
     pub fn parent(&self) -> Result<Game> {
         let parent_game_id = self
@@ -125,10 +198,10 @@ impl Profile {
     // Operations
 
     /// Add a new [`ModEntry`] to a [`Profile`] that points to the [`Mod`] given by ID.
-    pub fn add_mod_entry(&mut self, mod_: Mod) -> Result<()> {
+    pub fn add_mod_entry(&mut self, mod_: Mod) -> Result<ModEntry> {
         let maybe_last_entry_id = self.mod_entries()?.last().map(|e| e.entry_id);
 
-        self.db.write().transaction_mut(|t| -> Result<()> {
+        let mod_entry_id = self.db.write().transaction_mut(|t| -> Result<DbId> {
             let mod_entry = ModEntryModel::default();
             let mod_entry_id = t
                 .exec_mut(QueryBuilder::insert().element(&mod_entry).query())?
@@ -169,11 +242,15 @@ impl Profile {
                     .query(),
             )?;
 
-            // TODO: Return ModEntry
-            Ok(())
+            Ok(mod_entry_id)
         })?;
 
-        Ok(())
+        Ok(ModEntry::from_id(
+            mod_entry_id,
+            mod_.id,
+            self.db.clone(),
+            self.cfg.clone(),
+        ))
     }
 
     pub fn mod_entries(&self) -> Result<Vec<ModEntry>> {
@@ -219,14 +296,652 @@ impl Profile {
         Ok(mod_entry_ids
             .into_iter()
             .zip(mod_ids)
-            .map(|(entry_id, mod_id)| ModEntry::from_id(entry_id, mod_id, self.db.clone()))
+            .map(|(entry_id, mod_id)| {
+                ModEntry::from_id(entry_id, mod_id, self.db.clone(), self.cfg.clone())
+            })
+            .collect())
+    }
+
+    /// Remove a [`ModEntry`] from the profile. The [`Mod`] it points to is kept.
+    pub fn remove_mod_entry(&mut self, entry: ModEntry) -> Result<()> {
+        let old_order = self.mod_entry_ids()?;
+        let new_order: Vec<DbId> = old_order
+            .iter()
+            .copied()
+            .filter(|id| *id != entry.entry_id)
+            .collect();
+
+        self.link_mod_entries(&old_order, &new_order)?;
+        self.db
+            .write()
+            .exec_mut(QueryBuilder::remove().ids(entry.entry_id).query())?;
+
+        Ok(())
+    }
+
+    /// Move a [`ModEntry`] to the given position in the list, or to the end if the position is
+    /// past it. Later entries win file conflicts.
+    pub fn move_mod_entry(&mut self, entry: &ModEntry, index: usize) -> Result<()> {
+        let old_order = self.mod_entry_ids()?;
+        let mut new_order: Vec<DbId> = old_order
+            .iter()
+            .copied()
+            .filter(|id| *id != entry.entry_id)
+            .collect();
+        new_order.insert(index.min(new_order.len()), entry.entry_id);
+
+        self.link_mod_entries(&old_order, &new_order)
+    }
+
+    fn mod_entry_ids(&self) -> Result<Vec<DbId>> {
+        Ok(self.mod_entries()?.iter().map(|e| e.entry_id).collect())
+    }
+
+    /// Replace the links between the mod entries, chaining them into a list in the new order.
+    fn link_mod_entries(&mut self, old_order: &[DbId], new_order: &[DbId]) -> Result<()> {
+        self.db.write().transaction_mut(|t| -> Result<()> {
+            let mut previous = self.id;
+            for id in old_order {
+                t.exec_mut(
+                    QueryBuilder::remove()
+                        .search()
+                        .from(previous)
+                        .to(*id)
+                        .where_()
+                        .edge()
+                        .query(),
+                )?;
+                previous = *id;
+            }
+
+            if new_order.is_empty() {
+                return Ok(());
+            }
+
+            let from: Vec<DbId> = std::iter::once(self.id)
+                .chain(new_order.iter().copied())
+                .take(new_order.len())
+                .collect();
+            t.exec_mut(
+                QueryBuilder::insert()
+                    .edges()
+                    .from(from)
+                    .to(new_order.to_vec())
+                    .query(),
+            )?;
+
+            Ok(())
+        })
+    }
+
+    /// Analyze which files of the enabled mods override each other. When several mods provide
+    /// the same file, the one that comes last in the list of mod entries wins. Files in
+    /// archives override each other in plugin order instead, and loose files override them.
+    pub fn conflicts(&self) -> Result<Conflicts> {
+        let mut mods = Vec::new();
+
+        for entry in self.mod_entries()? {
+            if entry.enabled()? {
+                let dir = entry.mod_().dir()?;
+                mods.push((entry, dir));
+            }
+        }
+
+        let mut plugins = Vec::new();
+        for entry in self.plugin_entries()? {
+            if entry.enabled()? {
+                plugins.push(entry.name()?);
+            }
+        }
+
+        Ok(Conflicts::analyze(mods, &plugins))
+    }
+
+    /// Returns the dependencies of the enabled mods' modules that none of them provide. Modules
+    /// of the game itself are always available.
+    pub fn missing_dependencies(&self) -> Result<Vec<MissingDependency>> {
+        let mut modules = Vec::new();
+        for entry in self.mod_entries()? {
+            if entry.enabled()? {
+                for module in entry.mod_().modules()? {
+                    modules.push((entry.clone(), module));
+                }
+            }
+        }
+
+        let available: HashSet<&str> = modules.iter().map(|(_, m)| m.uuid.as_str()).collect();
+
+        Ok(modules
+            .iter()
+            .flat_map(|(entry, module)| {
+                module
+                    .dependencies
+                    .iter()
+                    .filter(|d| !d.is_game_module() && !available.contains(d.uuid.as_str()))
+                    .map(|d| MissingDependency {
+                        entry: entry.clone(),
+                        module: module.name.clone(),
+                        dependency: d.clone(),
+                    })
+            })
+            .collect())
+    }
+
+    // Plugins
+
+    /// Returns the profile's plugin order, the plugin loaded first coming first.
+    pub fn plugin_entries(&self) -> Result<Vec<PluginEntry>> {
+        Ok(self
+            .db
+            .read()
+            .exec(
+                QueryBuilder::select()
+                    .elements::<PluginEntryModel>()
+                    .search()
+                    .from(self.id)
+                    .query(),
+            )?
+            .elements
+            .iter()
+            .map(|e| PluginEntry::from_id(e.id, self.db.clone(), self.cfg.clone()))
             .collect())
     }
+
+    /// Bring the plugin order up to date with the plugins the profile's mods provide. Known
+    /// plugins keep their place and enabled state, new ones are enabled and added at the end in
+    /// mod order, and plugins that no mod provides anymore are removed.
+    pub fn sync_plugin_entries(&mut self) -> Result<Vec<PluginEntry>> {
+        let sources = self.plugin_sources()?;
+
+        let mut order = Vec::new();
+        for entry in self.plugin_entries()? {
+            let name = entry.name()?;
+            if sources.iter().any(|s| s.name.eq_ignore_ascii_case(&name)) {
+                order.push((name, entry.enabled()?));
+            }
+        }
+        for source in &sources {
+            if !order
+                .iter()
+                .any(|(name, _)| name.eq_ignore_ascii_case(&source.name))
+            {
+                order.push((source.name.clone(), true));
+            }
+        }
+
+        self.write_plugin_entries(&order)
+    }
+
+    /// Put the named plugins first, in the given order. The other plugins keep their order
+    /// after them.
+    pub fn set_plugin_order(&mut self, names: &[&str]) -> Result<Vec<PluginEntry>> {
+        let mut order = Vec::new();
+        for entry in self.plugin_entries()? {
+            order.push((entry.name()?, entry.enabled()?));
+        }
+
+        order.sort_by_key(|(name, _)| {
+            names
+                .iter()
+                .position(|n| n.eq_ignore_ascii_case(name))
+                .unwrap_or(usize::MAX)
+        });
+
+        self.write_plugin_entries(&order)
+    }
+
+    /// Check the plugin order for enabled plugins that won't load properly: those with missing
+    /// or disabled masters, those loading before their masters, and those whose mods are all
+    /// disabled.
+    pub fn validate_plugins(&self) -> Result<Vec<PluginIssue>> {
+        let game = self.parent()?;
+        let Some(plugin_game) = game.plugin_game()? else {
+            return Ok(Vec::new());
+        };
+
+        let sources = self.plugin_sources()?;
+        let mut order = Vec::new();
+        for entry in self.plugin_entries()? {
+            order.push((entry.name()?, entry.enabled()?));
+        }
+
+        // The game's own plugins aren't part of the plugin order
+        let base_plugins: Vec<String> = base_plugins(&game, plugin_game)?
+            .iter()
+            .map(|path| file_name(path))
+            .collect();
+
+        let mut issues = Vec::new();
+        for (index, (plugin, enabled)) in order.iter().enumerate() {
+            if !enabled {
+                continue;
+            }
+
+            let Some(source) = provider(&sources, plugin) else {
+                continue;
+            };
+            if !source.enabled {
+                issues.push(PluginIssue::ProviderDisabled {
+                    plugin: plugin.clone(),
+                    entry: source.entry.clone(),
+                });
+            }
+
+            let header = match Plugin::read(plugin_game, &source.path) {
+                Ok(header) => header,
+                Err(e) => {
+                    issues.push(PluginIssue::Unreadable {
+                        plugin: plugin.clone(),
+                        error: e.to_string(),
+                    });
+                    continue;
+                }
+            };
+
+            for master in header.masters {
+                let position = order
+                    .iter()
+                    .position(|(name, _)| name.eq_ignore_ascii_case(&master));
+                let master_enabled = position.and_then(|i| order.get(i)).map(|(_, e)| *e);
+
+                let issue = match (position, master_enabled) {
+                    (Some(_), Some(false)) => PluginIssue::DisabledMaster {
+                        plugin: plugin.clone(),
+                        master,
+                    },
+                    (Some(i), _) if i > index => PluginIssue::MasterAfterDependent {
+                        plugin: plugin.clone(),
+                        master,
+                    },
+                    (Some(_), _) => continue,
+                    (None, _) if base_plugins.iter().any(|p| p.eq_ignore_ascii_case(&master)) => {
+                        continue;
+                    }
+                    (None, _) => PluginIssue::MissingMaster {
+                        plugin: plugin.clone(),
+                        master,
+                    },
+                };
+                issues.push(issue);
+            }
+        }
+
+        if let Some(counts) = self.plugin_counts()? {
+            for (light, count, limit) in [
+                (false, counts.full, counts.limit.full),
+                (true, counts.light, counts.limit.light),
+            ] {
+                if count > limit {
+                    issues.push(PluginIssue::TooManyPlugins {
+                        light,
+                        count,
+                        limit,
+                    });
+                }
+            }
+        }
+
+        Ok(issues)
+    }
+
+    /// Count the plugins the game loads: its own, and the enabled ones of enabled mods. Returns
+    /// `None` if the game's [`DeployKind`](crate::repository::DeployKind) doesn't limit the
+    /// number of plugins.
+    pub fn plugin_counts(&self) -> Result<Option<PluginCounts>> {
+        let game = self.parent()?;
+        let (Some(limit), Some(plugin_game)) =
+            (game.deploy_kind()?.plugin_limit(), game.plugin_game()?)
+        else {
+            return Ok(None);
+        };
+
+        let sources = self.plugin_sources()?;
+        // Game plugins replaced by a mod are counted with the mod's plugins
+        let mut paths: Vec<PathBuf> = base_plugins(&game, plugin_game)?
+            .into_iter()
+            .filter(|path| provider(&sources, &file_name(path)).is_none())
+            .collect();
+        for entry in self.plugin_entries()? {
+            if !entry.enabled()? {
+                continue;
+            }
+            if let Some(source) = provider(&sources, &entry.name()?)
+                && source.enabled
+            {
+                paths.push(source.path.clone());
+            }
+        }
+
+        // Plugins that can't be read are counted as full ones
+        let light = paths
+            .iter()
+            .filter(|path| Plugin::read(plugin_game, path).is_ok_and(|p| p.is_light()))
+            .count();
+
+        Ok(Some(PluginCounts {
+            full: paths.len().saturating_sub(light),
+            light,
+            limit,
+        }))
+    }
+
+    /// Returns the enabled `.esp` plugins that could be flagged as light without breaking them,
+    /// which would free up their load order slots. Plugins that can't be read are left out.
+    pub fn light_candidates(&self) -> Result<Vec<String>> {
+        let Some(plugin_game) = self.parent()?.plugin_game()? else {
+            return Ok(Vec::new());
+        };
+
+        let sources = self.plugin_sources()?;
+        let mut candidates = Vec::new();
+        for entry in self.plugin_entries()? {
+            let name = entry.name()?;
+            let is_esp = Path::new(&name)
+                .extension()
+                .is_some_and(|e| e.eq_ignore_ascii_case("esp"));
+            let Some(source) = provider(&sources, &name) else {
+                continue;
+            };
+            if !is_esp || !entry.enabled()? {
+                continue;
+            }
+
+            if Plugin::read(plugin_game, &source.path)
+                .and_then(|header| header.can_be_light(&source.path))
+                .unwrap_or(false)
+            {
+                candidates.push(name);
+            }
+        }
+
+        Ok(candidates)
+    }
+
+    /// Sort the plugin order with LOOT's masterlist, and optionally the user's userlist.
+    /// Returns what the metadata has to say about the plugins.
+    pub fn sort_plugins(
+        &mut self,
+        masterlist: &Path,
+        userlist: Option<&Path>,
+    ) -> Result<Vec<loot::Message>> {
+        let game = self.parent()?;
+        let Some(plugin_game) = game.plugin_game()? else {
+            return Ok(Vec::new());
+        };
+
+        let metadata = Metadata::load(masterlist, userlist)?;
+        let sources = self.plugin_sources()?;
+
+        let mut plugins = Vec::new();
+        for entry in self.plugin_entries()? {
+            let Some(source) = provider(&sources, &entry.name()?) else {
+                continue;
+            };
+
+            plugins.push(SortPlugin {
+                header: Plugin::read(plugin_game, &source.path).map_err(loot::Error::from)?,
+                active: entry.enabled()?,
+                crc: crc32fast::hash(&fs::read(&source.path)?),
+            });
+        }
+
+        let data_dir = game.targets()?.into_iter().next();
+        let sorted = loot::sort(&metadata, &plugins, data_dir.as_deref())?;
+
+        let order: Vec<&str> = sorted.order.iter().map(String::as_str).collect();
+        self.set_plugin_order(&order)?;
+
+        Ok(sorted.messages)
+    }
+
+    /// Returns the plugins at the top of every mod entry's directory, in mod order.
+    fn plugin_sources(&self) -> Result<Vec<PluginSource>> {
+        let Some(game) = self.parent()?.plugin_game()? else {
+            return Ok(Vec::new());
+        };
+
+        let mut sources = Vec::new();
+        for entry in self.mod_entries()? {
+            let enabled = entry.enabled()?;
+            for path in plugin_files(&entry.mod_().dir()?, game)? {
+                sources.push(PluginSource {
+                    name: file_name(&path),
+                    entry: entry.clone(),
+                    enabled,
+                    path,
+                });
+            }
+        }
+
+        Ok(sources)
+    }
+
+    /// Replace the plugin order, given as plugin names and whether they're enabled.
+    pub(crate) fn write_plugin_entries(
+        &mut self,
+        order: &[(String, bool)],
+    ) -> Result<Vec<PluginEntry>> {
+        let models: Vec<PluginEntryModel> = order
+            .iter()
+            .map(|(name, enabled)| PluginEntryModel::new(name, *enabled))
+            .collect();
+
+        let ids = self.db.write().transaction_mut(|t| -> Result<Vec<DbId>> {
+            let old_ids: Vec<DbId> = t
+                .exec(
+                    QueryBuilder::select()
+                        .elements::<PluginEntryModel>()
+                        .search()
+                        .from(self.id)
+                        .query(),
+                )?
+                .elements
+                .iter()
+                .map(|e| e.id)
+                .collect();
+            t.exec_mut(QueryBuilder::remove().ids(old_ids).query())?;
+
+            if models.is_empty() {
+                return Ok(Vec::new());
+            }
+
+            let ids: Vec<DbId> = t
+                .exec_mut(QueryBuilder::insert().elements(&models).query())?
+                .elements
+                .iter()
+                .map(|e| e.id)
+                .collect();
+
+            // Chain the entries into a list starting at the profile, like the mod entries
+            let from: Vec<DbId> = std::iter::once(self.id)
+                .chain(ids.iter().copied())
+                .take(ids.len())
+                .collect();
+            t.exec_mut(
+                QueryBuilder::insert()
+                    .edges()
+                    .from(from)
+                    .to(ids.clone())
+                    .query(),
+            )?;
+
+            Ok(ids)
+        })?;
+
+        Ok(ids
+            .into_iter()
+            .map(|id| PluginEntry::from_id(id, self.db.clone(), self.cfg.clone()))
+            .collect())
+    }
+
+    // Overwrite
+
+    /// Returns the files in the overwrite directory, relative to it. Whiteouts, which record
+    /// deleted game files, aren't included.
+    pub fn overwrite_files(&self) -> Result<Vec<PathBuf>> {
+        let dir = self.overwrite_dir()?;
+
+        Ok(list_files(&dir)
+            .into_iter()
+            .filter(|path| {
+                dir.join(path)
+                    .symlink_metadata()
+                    .is_ok_and(|m| m.is_file() || m.is_symlink())
+            })
+            .collect())
+    }
+
+    /// Move the given files out of the overwrite directory into an existing [`Mod`], replacing
+    /// any files the mod already has at the same paths.
+    pub fn move_overwrite_files(&self, files: &[PathBuf], target: &Mod) -> Result<()> {
+        self.ensure_overwrite_unused()?;
+
+        let overwrite_dir = self.overwrite_dir()?;
+        let mod_dir = target.dir()?;
+
+        // Mod directories are kept read-only outside of this
+        change_dir_permissions(&mod_dir, Permissions::ReadWrite);
+        let result = files.iter().try_for_each(|path| -> io::Result<()> {
+            let dest = mod_dir.join(checked_relative(path)?);
+            if let Some(parent) = dest.parent() {
+                fs::create_dir_all(parent)?;
+            }
+
+            move_file(&overwrite_dir.join(path), &dest)
+        });
+        change_dir_permissions(&mod_dir, Permissions::ReadOnly);
+        result?;
+
+        remove_empty_dirs(&overwrite_dir);
+
+        Ok(())
+    }
+
+    /// Create a new [`Mod`] out of the given files in the overwrite directory, and add an
+    /// enabled [`ModEntry`] for it to this profile.
+    pub fn create_mod_from_overwrite(&mut self, name: &str, files: &[PathBuf]) -> Result<ModEntry> {
+        self.ensure_overwrite_unused()?;
+
+        let mod_ = self.parent()?.add_mod(name, None)?;
+        self.move_overwrite_files(files, &mod_)?;
+
+        let mut entry = self.add_mod_entry(mod_)?;
+        entry.set_enabled(true)?;
+
+        Ok(entry)
+    }
+
+    /// Delete the given files from the overwrite directory.
+    pub fn delete_overwrite_files(&self, files: &[PathBuf]) -> Result<()> {
+        self.ensure_overwrite_unused()?;
+
+        let overwrite_dir = self.overwrite_dir()?;
+        for path in files {
+            fs::remove_file(overwrite_dir.join(checked_relative(path)?))?;
+        }
+
+        remove_empty_dirs(&overwrite_dir);
+
+        Ok(())
+    }
+
+    /// Changing the upper directory of a mounted overlay is undefined behaviour, so the
+    /// overwrite directory can only be touched while the profile isn't deployed.
+    fn ensure_overwrite_unused(&self) -> Result<()> {
+        let dir = self.overwrite_dir()?;
+
+        if mounts::mounts()?.iter().any(|m| {
+            m.option("upperdir")
+                .is_some_and(|upper| Path::new(upper) == dir)
+        }) {
+            return Err(Error::OverwriteInUse);
+        }
+
+        Ok(())
+    }
+}
+
+impl PartialEq for Profile {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+/// Returns the copy of a plugin the game loads. Like with any other file, the last mod providing
+/// it wins, or the last disabled one if none of them are enabled.
+fn provider<'a>(sources: &'a [PluginSource], plugin: &str) -> Option<&'a PluginSource> {
+    let providers: Vec<&PluginSource> = sources
+        .iter()
+        .filter(|s| s.name.eq_ignore_ascii_case(plugin))
+        .collect();
+
+    providers
+        .iter()
+        .rev()
+        .find(|s| s.enabled)
+        .or(providers.last())
+        .copied()
+}
+
+/// Returns the plugins of the given game at the top of a directory, sorted by name.
+fn plugin_files(dir: &Path, game: GameId) -> io::Result<Vec<PathBuf>> {
+    let mut plugins = Vec::new();
+
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        let is_plugin = path.extension().is_some_and(|e| {
+            game.extensions()
+                .iter()
+                .any(|extension| e.eq_ignore_ascii_case(extension))
+        });
+
+        if is_plugin && entry.file_type()?.is_file() {
+            plugins.push(path);
+        }
+    }
+    plugins.sort();
+
+    Ok(plugins)
+}
+
+/// Returns the plugins the game itself has in its data directory, the first target.
+fn base_plugins(game: &Game, plugin_game: GameId) -> Result<Vec<PathBuf>> {
+    Ok(match game.targets()?.first() {
+        Some(data_dir) => plugin_files(data_dir, plugin_game).unwrap_or_default(),
+        None => Vec::new(),
+    })
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+/// Make sure a path stays inside the directory it's relative to.
+fn checked_relative(path: &Path) -> io::Result<&Path> {
+    if path.components().all(|c| matches!(c, Component::Normal(_))) {
+        Ok(path)
+    } else {
+        Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{} is not a relative path", path.display()),
+        ))
+    }
 }
 
 #[cfg(test)]
 mod test {
-    use crate::{Repository, repository::DeployKind};
+    use tempfile::tempdir;
+
+    use crate::{
+        Repository,
+        formats::{bsa, lspk, plugin},
+        repository::DeployKind,
+    };
+
+    use super::*;
 
     #[test]
     fn test_add() {
@@ -235,4 +950,414 @@ mod test {
         let mut game = repo.add_game("Morrowind", DeployKind::OpenMW).unwrap();
         game.add_profile("Test").unwrap();
     }
+
+    #[test]
+    fn test_conflicts() {
+        let repo = Repository::mock();
+
+        let mut game = repo.add_game("Morrowind", DeployKind::OpenMW).unwrap();
+        let mut profile = game.add_profile("Test").unwrap();
+
+        for name in ["Base", "Patch"] {
+            let mod_ = game.add_mod(name, None).unwrap();
+            let dir = mod_.dir().unwrap().join("textures");
+            fs::create_dir_all(&dir).unwrap();
+            fs::write(dir.join("rock.dds"), name).unwrap();
+
+            profile.add_mod_entry(mod_).unwrap();
+        }
+
+        for mut entry in profile.mod_entries().unwrap() {
+            entry.set_enabled(true).unwrap();
+        }
+
+        let entries = profile.mod_entries().unwrap();
+        let (base, patch) = (entries.first().unwrap(), entries.last().unwrap());
+        let conflicts = profile.conflicts().unwrap();
+
+        assert_eq!(conflicts.files().len(), 1);
+        assert_eq!(conflicts.overwrites(patch).len(), 1);
+        assert_eq!(conflicts.overwritten_by(base).len(), 1);
+        assert_eq!(conflicts.fully_overridden(), [base.clone()]);
+        assert!(conflicts.archive_files().is_empty());
+    }
+
+    #[test]
+    fn test_mod_entry_order() {
+        let repo = Repository::mock();
+
+        let mut game = repo.add_game("Morrowind", DeployKind::OpenMW).unwrap();
+        let mut profile = game.add_profile("Test").unwrap();
+        for name in ["A", "B", "C"] {
+            let mod_ = game.add_mod(name, None).unwrap();
+            profile.add_mod_entry(mod_).unwrap();
+        }
+        let names = |profile: &Profile| -> Vec<String> {
+            profile
+                .mod_entries()
+                .unwrap()
+                .iter()
+                .map(|e| e.name().unwrap())
+                .collect()
+        };
+
+        let entries = profile.mod_entries().unwrap();
+        let (a, c) = (entries.first().unwrap(), entries.last().unwrap());
+        profile.move_mod_entry(c, 0).unwrap();
+        assert_eq!(names(&profile), ["C", "A", "B"]);
+        profile.move_mod_entry(a, 10).unwrap();
+        assert_eq!(names(&profile), ["C", "B", "A"]);
+
+        let b = profile.mod_entries().unwrap().get(1).unwrap().clone();
+        profile.remove_mod_entry(b).unwrap();
+        assert_eq!(names(&profile), ["C", "A"]);
+
+        let c = profile.mod_entries().unwrap().first().unwrap().clone();
+        profile.remove_mod_entry(c).unwrap();
+        assert_eq!(names(&profile), ["A"]);
+    }
+
+    #[test]
+    fn test_archive_conflicts() {
+        let repo = Repository::mock();
+        let data_dir = tempdir().unwrap();
+        plugin::build(
+            &data_dir.path().join("Skyrim.esm"),
+            GameId::SkyrimSE,
+            1,
+            &[],
+        );
+
+        let mut game = repo.add_game("Skyrim", DeployKind::CreationEngine).unwrap();
+        game.set_targets(vec![data_dir.path().to_path_buf()])
+            .unwrap();
+        let mut profile = game.add_profile("Test").unwrap();
+
+        for name in ["Textures", "Patch", "Loose"] {
+            let mod_ = game.add_mod(name, None).unwrap();
+            let dir = mod_.dir().unwrap();
+            if name == "Loose" {
+                fs::create_dir_all(dir.join("textures")).unwrap();
+                fs::write(dir.join("textures/rock.dds"), name).unwrap();
+            } else {
+                plugin::build(&dir.join(format!("{name}.esp")), GameId::SkyrimSE, 0, &[]);
+                bsa::build(
+                    &dir.join(format!("{name}.bsa")),
+                    105,
+                    &["textures/rock.dds"],
+                );
+            }
+
+            profile
+                .add_mod_entry(mod_)
+                .unwrap()
+                .set_enabled(true)
+                .unwrap();
+        }
+        profile.sync_plugin_entries().unwrap();
+        // The patch's archive loads first, even though its mod comes later
+        profile.set_plugin_order(&["Patch.esp"]).unwrap();
+
+        let entries = profile.mod_entries().unwrap();
+        let conflicts = profile.conflicts().unwrap();
+
+        assert!(conflicts.files().is_empty());
+        let [conflict] = conflicts.archive_files() else {
+            panic!("Expected a single conflict");
+        };
+        assert_eq!(conflict.winner.entry, *entries.last().unwrap());
+        assert_eq!(conflict.winner.archive, None);
+        let losers: Vec<_> = conflict
+            .losers
+            .iter()
+            .map(|l| l.archive.clone().unwrap())
+            .collect();
+        assert_eq!(
+            losers,
+            [PathBuf::from("Textures.bsa"), PathBuf::from("Patch.bsa")]
+        );
+    }
+
+    #[test]
+    fn test_missing_dependencies() {
+        let repo = Repository::mock();
+
+        let mut game = repo
+            .add_game("Baldur's Gate 3", DeployKind::BaldursGate3)
+            .unwrap();
+        let mut profile = game.add_profile("Test").unwrap();
+
+        let meta = |folder: &str, uuid: &str, dependencies: &[(&str, &str)]| {
+            let mut xml = String::from(r#"<save><region id="Config"><node id="root"><children>"#);
+            xml.push_str(r#"<node id="Dependencies"><children>"#);
+            for (folder, uuid) in dependencies {
+                xml.push_str(&format!(
+                    r#"<node id="ModuleShortDesc">
+                        <attribute id="Folder" type="LSString" value="{folder}"/>
+                        <attribute id="UUID" type="FixedString" value="{uuid}"/>
+                    </node>"#
+                ));
+            }
+            xml.push_str(&format!(
+                r#"</children></node><node id="ModuleInfo">
+                    <attribute id="Folder" type="LSString" value="{folder}"/>
+                    <attribute id="Name" type="LSString" value="{folder}"/>
+                    <attribute id="UUID" type="FixedString" value="{uuid}"/>
+                </node></children></node></region></save>"#
+            ));
+            xml
+        };
+
+        for (folder, uuid, dependencies) in [
+            (
+                "Framework",
+                "0000-framework",
+                vec![("GustavX", "0000-gustav")],
+            ),
+            (
+                "Armor",
+                "0000-armor",
+                vec![("Framework", "0000-framework"), ("Library", "0000-library")],
+            ),
+        ] {
+            let mod_ = game.add_mod(folder, None).unwrap();
+            let meta = meta(folder, uuid, &dependencies);
+            lspk::build(
+                &mod_.dir().unwrap().join(format!("{folder}.pak")),
+                &[(&format!("Mods/{folder}/meta.lsx"), meta.as_bytes(), 2)],
+            );
+            mod_.index_modules().unwrap();
+
+            let mut entry = profile.add_mod_entry(mod_).unwrap();
+            entry.set_enabled(true).unwrap();
+        }
+
+        let entries = profile.mod_entries().unwrap();
+        let armor = entries.last().unwrap();
+        let modules = armor.mod_().modules().unwrap();
+        assert_eq!(modules.len(), 1);
+        assert_eq!(modules.first().unwrap().uuid, "0000-armor");
+
+        let missing = profile.missing_dependencies().unwrap();
+        assert_eq!(missing.len(), 1);
+        let missing = missing.first().unwrap();
+        assert_eq!(&missing.entry, armor);
+        assert_eq!(missing.module, "Armor");
+        assert_eq!(missing.dependency.uuid, "0000-library");
+
+        // Disabling a mod makes the modules it provides unavailable
+        entries.first().unwrap().clone().set_enabled(false).unwrap();
+        assert_eq!(profile.missing_dependencies().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_plugins() {
+        let repo = Repository::mock();
+        let data_dir = tempdir().unwrap();
+        plugin::build(
+            &data_dir.path().join("Skyrim.esm"),
+            GameId::SkyrimSE,
+            1,
+            &[],
+        );
+
+        let mut game = repo.add_game("Skyrim", DeployKind::CreationEngine).unwrap();
+        game.set_targets(vec![data_dir.path().to_path_buf()])
+            .unwrap();
+        let mut profile = game.add_profile("Test").unwrap();
+
+        for (name, plugin, flags, masters) in [
+            ("Base", "Base.esm", 1, vec!["Skyrim.esm"]),
+            ("Patch", "Patch.esp", 0, vec!["Base.esm", "Missing.esm"]),
+        ] {
+            let mod_ = game.add_mod(name, None).unwrap();
+            plugin::build(
+                &mod_.dir().unwrap().join(plugin),
+                GameId::SkyrimSE,
+                flags,
+                &masters,
+            );
+
+            let mut entry = profile.add_mod_entry(mod_).unwrap();
+            entry.set_enabled(true).unwrap();
+        }
+
+        let entries = profile.sync_plugin_entries().unwrap();
+        let names: Vec<String> = entries.iter().map(|e| e.name().unwrap()).collect();
+        assert_eq!(names, ["Base.esm", "Patch.esp"]);
+        assert_eq!(profile.plugin_entries().unwrap(), entries);
+
+        let issues = profile.validate_plugins().unwrap();
+        assert!(matches!(
+            issues.as_slice(),
+            [PluginIssue::MissingMaster { plugin, master }]
+                if plugin == "Patch.esp" && master == "Missing.esm"
+        ));
+
+        profile.set_plugin_order(&["Patch.esp"]).unwrap();
+        let issues = profile.validate_plugins().unwrap();
+        assert!(issues.iter().any(|i| matches!(
+            i,
+            PluginIssue::MasterAfterDependent { master, .. } if master == "Base.esm"
+        )));
+
+        let mut entries = profile.set_plugin_order(&["Base.esm"]).unwrap();
+        entries.first_mut().unwrap().set_enabled(false).unwrap();
+        let issues = profile.validate_plugins().unwrap();
+        assert!(issues.iter().any(|i| matches!(
+            i,
+            PluginIssue::DisabledMaster { master, .. } if master == "Base.esm"
+        )));
+
+        // Syncing keeps the order and the enabled state of known plugins
+        let entries = profile.sync_plugin_entries().unwrap();
+        assert!(!entries.first().unwrap().enabled().unwrap());
+
+        profile
+            .mod_entries()
+            .unwrap()
+            .last()
+            .unwrap()
+            .clone()
+            .set_enabled(false)
+            .unwrap();
+        let issues = profile.validate_plugins().unwrap();
+        assert!(issues.iter().any(|i| matches!(
+            i,
+            PluginIssue::ProviderDisabled { plugin, .. } if plugin == "Patch.esp"
+        )));
+    }
+
+    #[test]
+    fn test_plugin_limits() {
+        let repo = Repository::mock();
+        let data_dir = tempdir().unwrap();
+        plugin::build(
+            &data_dir.path().join("Skyrim.esm"),
+            GameId::SkyrimSE,
+            1,
+            &[],
+        );
+
+        let mut game = repo.add_game("Skyrim", DeployKind::CreationEngine).unwrap();
+        game.set_targets(vec![data_dir.path().to_path_buf()])
+            .unwrap();
+        let mut profile = game.add_profile("Test").unwrap();
+
+        let mod_ = game.add_mod("Plugins", None).unwrap();
+        for (plugin, flags, form_ids) in [
+            ("Small.esp", 0, [0x0100_0800]),
+            ("Big.esp", 0, [0x0100_1000]),
+            ("Light.esl", 0, [0x0100_0800]),
+        ] {
+            let path = mod_.dir().unwrap().join(plugin);
+            plugin::build(&path, GameId::SkyrimSE, flags, &["Skyrim.esm"]);
+            plugin::add_records(&path, GameId::SkyrimSE, &form_ids);
+        }
+        profile
+            .add_mod_entry(mod_)
+            .unwrap()
+            .set_enabled(true)
+            .unwrap();
+        profile.sync_plugin_entries().unwrap();
+
+        assert_eq!(profile.light_candidates().unwrap(), ["Small.esp"]);
+
+        let counts = profile.plugin_counts().unwrap().unwrap();
+        assert_eq!((counts.full, counts.light), (3, 1));
+        assert!(!counts.exceeded());
+
+        let limit = DeployKind::Gamebryo.plugin_limit().unwrap();
+        let counts = PluginCounts {
+            full: 256,
+            light: 0,
+            limit,
+        };
+        assert!(counts.exceeded());
+        assert_eq!(DeployKind::OpenMW.plugin_limit(), None);
+    }
+
+    #[test]
+    fn test_sort_plugins() {
+        let repo = Repository::mock();
+        let dir = tempdir().unwrap();
+        plugin::build(&dir.path().join("Skyrim.esm"), GameId::SkyrimSE, 1, &[]);
+        let masterlist = dir.path().join("masterlist.yaml");
+        fs::write(
+            &masterlist,
+            "plugins:\n  - name: First.esp\n    after: [ Second.esp ]\n",
+        )
+        .unwrap();
+
+        let mut game = repo.add_game("Skyrim", DeployKind::CreationEngine).unwrap();
+        game.set_targets(vec![dir.path().to_path_buf()]).unwrap();
+        let mut profile = game.add_profile("Test").unwrap();
+
+        for name in ["First", "Second"] {
+            let mod_ = game.add_mod(name, None).unwrap();
+            plugin::build(
+                &mod_.dir().unwrap().join(format!("{name}.esp")),
+                GameId::SkyrimSE,
+                0,
+                &["Skyrim.esm"],
+            );
+            profile.add_mod_entry(mod_).unwrap();
+        }
+        profile.sync_plugin_entries().unwrap();
+
+        profile.sort_plugins(&masterlist, None).unwrap();
+
+        let names: Vec<String> = profile
+            .plugin_entries()
+            .unwrap()
+            .iter()
+            .map(|e| e.name().unwrap())
+            .collect();
+        assert_eq!(names, ["Second.esp", "First.esp"]);
+    }
+
+    #[test]
+    fn test_overwrite() {
+        let repo = Repository::mock();
+
+        let mut game = repo.add_game("Skyrim", DeployKind::CreationEngine).unwrap();
+        let mut profile = game.add_profile("Test").unwrap();
+
+        let overwrite_dir = profile.overwrite_dir().unwrap();
+        fs::create_dir_all(overwrite_dir.join("meshes/lod")).unwrap();
+        fs::write(overwrite_dir.join("meshes/lod/tree.nif"), "lod").unwrap();
+        fs::write(overwrite_dir.join("skse.log"), "log").unwrap();
+
+        let mut files = profile.overwrite_files().unwrap();
+        files.sort();
+        assert_eq!(
+            files,
+            vec![
+                PathBuf::from("meshes/lod/tree.nif"),
+                PathBuf::from("skse.log")
+            ]
+        );
+
+        let entry = profile
+            .create_mod_from_overwrite("Generated LOD", &[PathBuf::from("meshes/lod/tree.nif")])
+            .unwrap();
+
+        assert!(entry.enabled().unwrap());
+        assert_eq!(profile.mod_entries().unwrap(), vec![entry.clone()]);
+        assert_eq!(
+            fs::read_to_string(entry.mod_().dir().unwrap().join("meshes/lod/tree.nif")).unwrap(),
+            "lod"
+        );
+        assert!(!overwrite_dir.join("meshes").exists());
+
+        assert!(
+            profile
+                .delete_overwrite_files(&[PathBuf::from("../skse.log")])
+                .is_err()
+        );
+        profile
+            .delete_overwrite_files(&[PathBuf::from("skse.log")])
+            .unwrap();
+        assert!(profile.overwrite_files().unwrap().is_empty());
+    }
 }
@@ -0,0 +1,177 @@
+use std::{
+    fs::{self, File},
+    path::PathBuf,
+    process::ExitStatus,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use agdb::{DbId, QueryBuilder, QueryId};
+
+use crate::repository::{
+    CoreConfigHandle,
+    db::DbHandle,
+    entities::{Result, get_field, set_field, tool::Tool},
+    models::{NO_EXIT_CODE, RunModel, ToolModel},
+};
+
+/// Represents a record of a launched [`Tool`] in the Barnacle system.
+///
+/// The tool's standard output and error are written to a log file of their own while it runs,
+/// and the record is completed with its exit code once it exits.
+#[derive(Debug, Clone)]
+pub struct Run {
+    pub(crate) id: DbId,
+    pub(crate) db: DbHandle,
+    pub(crate) cfg: CoreConfigHandle,
+}
+
+impl Run {
+    pub(crate) fn from_id(id: DbId, db: DbHandle, cfg: CoreConfigHandle) -> Self {
+        Self { id, db, cfg }
+    }
+
+    /// Name of the tool at the time of the run
+    pub fn tool_name(&self) -> Result<String> {
+        get_field(&self.db, self.id, "tool")
+    }
+
+    /// Name of the profile that was deployed for the run
+    pub fn profile_name(&self) -> Result<String> {
+        get_field(&self.db, self.id, "profile")
+    }
+
+    pub fn log_path(&self) -> Result<PathBuf> {
+        get_field(&self.db, self.id, "log_path")
+    }
+
+    /// Returns everything the tool wrote to its standard output and error.
+    pub fn log(&self) -> Result<String> {
+        let bytes = fs::read(self.log_path()?)?;
+
+        Ok(String::from_utf8_lossy(&bytes).into_owned())
+    }
+
+    /// When the tool was started, in seconds since the Unix epoch
+    pub fn started(&self) -> Result<u64> {
+        get_field(&self.db, self.id, "started")
+    }
+
+    /// When the tool exited, in seconds since the Unix epoch. `None` if it's still running, or
+    /// Barnacle stopped waiting for it.
+    pub fn ended(&self) -> Result<Option<u64>> {
+        let ended: u64 = get_field(&self.db, self.id, "ended")?;
+
+        Ok((ended != 0).then_some(ended))
+    }
+
+    /// The tool's exit code. `None` if it hasn't exited, was killed by a signal or failed to
+    /// start.
+    pub fn exit_code(&self) -> Result<Option<i32>> {
+        let exit_code: i64 = get_field(&self.db, self.id, "exit_code")?;
+
+        Ok(i32::try_from(exit_code)
+            .ok()
+            .filter(|_| exit_code != NO_EXIT_CODE))
+    }
+
+    /// Returns the [`Tool`] that was run, unless it has been removed since.
+    pub fn tool(&self) -> Result<Option<Tool>> {
+        Ok(self
+            .db
+            .read()
+            .exec(
+                QueryBuilder::select()
+                    .elements::<ToolModel>()
+                    .search()
+                    .from("tools")
+                    .to(self.id)
+                    .query(),
+            )?
+            .elements
+            .pop()
+            .map(|e| Tool::from_id(e.id, self.db.clone(), self.cfg.clone())))
+    }
+
+    /// Record a new run of the given [`Tool`], returning it along with its log file, created
+    /// empty.
+    pub(crate) fn add(
+        db: DbHandle,
+        cfg: CoreConfigHandle,
+        tool: &Tool,
+        profile: &str,
+    ) -> Result<(Self, File)> {
+        let log_dir = cfg.read().log_dir().to_path_buf();
+        let started = now();
+        let model = RunModel::new(&tool.name()?, profile, PathBuf::new(), started);
+
+        let mut run = db.write().transaction_mut(|t| -> Result<Run> {
+            let run_id = t
+                .exec_mut(QueryBuilder::insert().element(model).query())?
+                .elements
+                .first()
+                .expect("A successful query should not be empty")
+                .id;
+
+            // Link the record to the root "runs" node and to the tool
+            t.exec_mut(
+                QueryBuilder::insert()
+                    .edges()
+                    .from([QueryId::from("runs"), QueryId::from(tool.id)])
+                    .to(run_id)
+                    .query(),
+            )?;
+
+            Ok(Run::from_id(run_id, db.clone(), cfg.clone()))
+        })?;
+
+        let log_path = log_dir.join(format!("{started}-{}.log", run.id.0));
+        let file = File::create(&log_path)?;
+        set_field(&mut run.db, run.id, "log_path", log_path)?;
+
+        Ok((run, file))
+    }
+
+    /// Complete the record once the tool has exited, or without a status if it failed to
+    /// start.
+    pub(crate) fn finish(&mut self, status: Option<ExitStatus>) -> Result<()> {
+        let exit_code = status
+            .and_then(|status| status.code())
+            .map_or(NO_EXIT_CODE, i64::from);
+
+        set_field(&mut self.db, self.id, "ended", now())?;
+        set_field(&mut self.db, self.id, "exit_code", exit_code)
+    }
+
+    pub(crate) fn list(db: DbHandle, cfg: CoreConfigHandle) -> Result<Vec<Run>> {
+        Ok(db
+            .read()
+            .exec(
+                QueryBuilder::select()
+                    .elements::<RunModel>()
+                    .search()
+                    .from("runs")
+                    .where_()
+                    .node()
+                    .and()
+                    .neighbor()
+                    .query(),
+            )?
+            .elements
+            .iter()
+            .map(|e| Run::from_id(e.id, db.clone(), cfg.clone()))
+            .collect())
+    }
+}
+
+impl PartialEq for Run {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
@@ -1,30 +1,244 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
-use agdb::DbId;
+use agdb::{DbId, QueryBuilder};
 
-use crate::repository::{db::DbHandle, entities::get_field};
+use crate::{
+    launch::Launch,
+    repository::{
+        CoreConfigHandle, Repository,
+        db::DbHandle,
+        entities::{
+            Result, game::Game, get_field, mod_::Mod, profile::Profile, run::Run, set_field,
+        },
+        models::{GameModel, ModModel, RunModel, ToolModel},
+        snapshot::ToolSnapshot,
+    },
+};
 
-/// Represents a tool entity in the Barnacle system.
+/// Represents a tool entity in the Barnacle system, an executable that runs with a profile
+/// deployed, such as the game itself or a script extender.
 ///
 /// Provides methods to inspect and modify this tool's data.
 /// Always reflects the current database state.
 #[derive(Debug, Clone)]
 pub struct Tool {
-    id: DbId,
-    db: DbHandle,
+    pub(crate) id: DbId,
+    pub(crate) db: DbHandle,
+    pub(crate) cfg: CoreConfigHandle,
 }
 
 impl Tool {
-    pub fn name(&self) -> String {
-        get_field(&self.db, self.id, "name").unwrap()
+    pub(crate) fn from_id(id: DbId, db: DbHandle, cfg: CoreConfigHandle) -> Self {
+        Self { id, db, cfg }
     }
 
-    pub fn path(&self) -> PathBuf {
-        get_field(&self.db, self.id, "path").unwrap()
+    pub fn name(&self) -> Result<String> {
+        get_field(&self.db, self.id, "name")
     }
 
-    // TODO: This can actually be Option<String>
-    pub fn args(&self) -> String {
-        get_field(&self.db, self.id, "args").unwrap()
+    pub fn path(&self) -> Result<PathBuf> {
+        get_field(&self.db, self.id, "path")
+    }
+
+    pub fn args(&self) -> Result<Vec<String>> {
+        get_field(&self.db, self.id, "args")
+    }
+
+    /// Returns the environment variables set for the tool, in the order they're set. They're
+    /// set after the ones of the game's runtime, so they can override them.
+    pub fn env(&self) -> Result<Vec<(String, String)>> {
+        let names: Vec<String> = get_field(&self.db, self.id, "env_names")?;
+        let values: Vec<String> = get_field(&self.db, self.id, "env_values")?;
+
+        Ok(names.into_iter().zip(values).collect())
+    }
+
+    pub fn set_env(&mut self, new_env: Vec<(String, String)>) -> Result<()> {
+        let (names, values): (Vec<String>, Vec<String>) = new_env.into_iter().unzip();
+
+        set_field(&mut self.db, self.id, "env_names", names)?;
+        set_field(&mut self.db, self.id, "env_values", values)
+    }
+
+    /// Returns the directory the tool is started in, or `None` if it's started in the
+    /// executable's own directory. A relative directory is relative to the executable's.
+    pub fn working_dir(&self) -> Result<Option<PathBuf>> {
+        let dir: PathBuf = get_field(&self.db, self.id, "working_dir")?;
+
+        Ok((!dir.as_os_str().is_empty()).then_some(dir))
+    }
+
+    pub fn set_working_dir(&mut self, new_working_dir: Option<&Path>) -> Result<()> {
+        set_field(
+            &mut self.db,
+            self.id,
+            "working_dir",
+            new_working_dir.unwrap_or(Path::new("")).to_path_buf(),
+        )
+    }
+
+    /// Whether the tool's path is relative to the game's first target, for tools that are
+    /// installed as mods and only exist in the target while a profile is deployed.
+    pub fn run_in_target(&self) -> Result<bool> {
+        get_field(&self.db, self.id, "run_in_target")
+    }
+
+    pub fn set_run_in_target(&mut self, run_in_target: bool) -> Result<()> {
+        set_field(&mut self.db, self.id, "run_in_target", run_in_target)
+    }
+
+    /// Returns the [`Mod`] that files created by the tool are moved into once it exits,
+    /// instead of being left in the profile's overwrite directory.
+    pub fn output_mod(&self) -> Result<Option<Mod>> {
+        Ok(self
+            .db
+            .read()
+            .exec(
+                QueryBuilder::select()
+                    .elements::<ModModel>()
+                    .search()
+                    .from(self.id)
+                    .where_()
+                    .node()
+                    .and()
+                    .neighbor()
+                    .query(),
+            )?
+            .elements
+            .pop()
+            .map(|e| Mod::from_id(e.id, self.db.clone(), self.cfg.clone())))
+    }
+
+    pub fn set_output_mod(&mut self, new_output_mod: Option<&Mod>) -> Result<()> {
+        let old_output_mod = self.output_mod()?;
+
+        self.db.write().transaction_mut(|t| {
+            // Unlink the previous output mod, if there is one
+            if let Some(old_output_mod) = old_output_mod {
+                t.exec_mut(
+                    QueryBuilder::remove()
+                        .search()
+                        .from(self.id)
+                        .to(old_output_mod.id)
+                        .where_()
+                        .edge()
+                        .query(),
+                )?;
+            }
+            if let Some(new_output_mod) = new_output_mod {
+                t.exec_mut(
+                    QueryBuilder::insert()
+                        .edges()
+                        .from(self.id)
+                        .to(new_output_mod.id)
+                        .query(),
+                )?;
+            }
+
+            Ok(())
+        })
+    }
+
+    /// Returns the full path of the executable, resolving it against the game's first target
+    /// if [`Tool::run_in_target`] is set.
+    pub fn resolved_path(&self) -> Result<PathBuf> {
+        let path = self.path()?;
+        if !self.run_in_target()? {
+            return Ok(path);
+        }
+
+        Ok(match self.parent()?.targets()?.first() {
+            Some(target) => target.join(path),
+            None => path,
+        })
+    }
+
+    /// Read all of the tool's fields at once.
+    pub fn snapshot(&self) -> Result<ToolSnapshot> {
+        let model: ToolModel = self
+            .db
+            .read()
+            .exec(
+                QueryBuilder::select()
+                    .elements::<ToolModel>()
+                    .ids(self.id)
+                    .query(),
+            )?
+            .try_into()?;
+
+        Ok(ToolSnapshot {
+            id: self.id.0,
+            game_id: self.parent()?.id.0,
+            name: model.name,
+            path: model.path,
+            args: model.args,
+            env: model.env_names.into_iter().zip(model.env_values).collect(),
+            working_dir: (!model.working_dir.as_os_str().is_empty()).then_some(model.working_dir),
+            run_in_target: model.run_in_target,
+            output_mod_id: self.output_mod()?.map(|m| m.id.0),
+        })
+    }
+
+    /// Returns the parent [`Game`] of this [`Tool`]
+    pub fn parent(&self) -> Result<Game> {
+        let parent_game_id = self
+            .db
+            .read()
+            .exec(
+                QueryBuilder::select()
+                    .elements::<GameModel>()
+                    .search()
+                    .from("games")
+                    .to(self.id)
+                    .query(),
+            )?
+            .elements
+            .pop()
+            .expect("A successful query should not be empty")
+            .id;
+
+        Ok(Game::from_id(
+            parent_game_id,
+            self.db.clone(),
+            self.cfg.clone(),
+        ))
+    }
+
+    /// Returns the recorded [`Run`]s of this [`Tool`], oldest first
+    pub fn runs(&self) -> Result<Vec<Run>> {
+        Ok(self
+            .db
+            .read()
+            .exec(
+                QueryBuilder::select()
+                    .elements::<RunModel>()
+                    .search()
+                    .from(self.id)
+                    .where_()
+                    .node()
+                    .and()
+                    .neighbor()
+                    .query(),
+            )?
+            .elements
+            .iter()
+            .map(|e| Run::from_id(e.id, self.db.clone(), self.cfg.clone()))
+            .collect())
+    }
+
+    /// Run the tool with the given [`Profile`] deployed. See [`Launch`] for how the deployment
+    /// is handled.
+    pub fn launch(&self, profile: &Profile) -> crate::Result<Launch> {
+        Launch::start(
+            &Repository::from_handles(self.db.clone(), self.cfg.clone()),
+            self,
+            profile,
+        )
+    }
+}
+
+impl PartialEq for Tool {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
     }
 }
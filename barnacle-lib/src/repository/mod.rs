@@ -1,9 +1,13 @@
-use std::sync::Arc;
+use std::{path::PathBuf, sync::Arc};
 
 use parking_lot::RwLock;
+use tracing::warn;
 
 use crate::{
     Result,
+    definitions::GamePreset,
+    deployers::{self, Deployer, Setup},
+    mounts::{self, Mount},
     repository::{
         config::{CoreConfig, CoreConfigHandle},
         db::DbHandle,
@@ -16,9 +20,13 @@ mod models;
 
 pub mod config;
 pub mod entities;
+pub mod snapshot;
 
-pub use entities::{Game, Mod, ModEntry, Profile, Tool};
-pub use models::DeployKind;
+pub use entities::{
+    Deployment, Game, MissingDependency, Mod, ModEntry, PluginCounts, PluginEntry, PluginIssue,
+    Profile, Run, Tool,
+};
+pub use models::{DeployKind, DeployMethod, PluginLimit};
 
 /// Central access point for all persistent data.
 ///
@@ -31,17 +39,85 @@ pub struct Repository {
     cfg: CoreConfigHandle,
 }
 
+/// A mount found on a game target that may need to be cleaned up.
+#[derive(Debug, Clone)]
+pub enum MountIssue {
+    /// A deployment left mounted by an earlier session, e.g. because Barnacle crashed.
+    Stale(Deployment),
+    /// A filesystem mounted on a game target that Barnacle has no record of.
+    Foreign(Mount),
+}
+
 impl Repository {
+    /// Open the repository. Records of deployments whose mounts have disappeared since the last
+    /// session, e.g. because the machine was rebooted, are dropped. Any mounts that are still
+    /// around can be inspected with [`Repository::mount_issues`].
     pub fn new() -> Self {
-        Self {
+        let repo = Self {
             db: DbHandle::new(),
             cfg: Arc::new(RwLock::new(CoreConfig::load())),
+        };
+
+        if let Err(e) = repo.reconcile_deployments() {
+            warn!("Failed to reconcile deployments with the mount table: {e}");
         }
+
+        repo
+    }
+
+    pub(crate) fn from_handles(db: DbHandle, cfg: CoreConfigHandle) -> Self {
+        Self { db, cfg }
+    }
+
+    /// Whether a profile deployed to launch a game or tool is undeployed once it exits. Profiles
+    /// that were deployed already are always left deployed.
+    pub fn undeploy_after_launch(&self) -> bool {
+        self.cfg.read().undeploy_after_launch()
     }
 
-    pub fn add_game(&self, name: &str, deploy_kind: DeployKind) -> Result<Game> {
-        let model = GameModel::new(name, deploy_kind);
-        Ok(Game::add(self.db.clone(), self.cfg.clone(), model)?)
+    pub fn set_undeploy_after_launch(&self, undeploy: bool) {
+        self.cfg.write().set_undeploy_after_launch(undeploy);
+    }
+
+    /// Add a game set up from a [`GamePreset`]: either a bare [`DeployKind`], or a
+    /// [`Definition`](crate::definitions::Definition) with an install directory, which
+    /// [`DetectedGame`](crate::detect::DetectedGame)s convert into.
+    ///
+    /// A game added from a definition gets its data directory as the target, its runtime,
+    /// AppData folder, implicit masters and root files set, and the definition's tools that
+    /// exist in the install directory.
+    pub fn add_game(&self, name: &str, preset: impl Into<GamePreset>) -> Result<Game> {
+        let (definition, install_dir, runtime) = match preset.into() {
+            GamePreset::Bare(deploy_kind) => {
+                let model = GameModel::new(name, deploy_kind);
+                return Ok(Game::add(self.db.clone(), self.cfg.clone(), model)?);
+            }
+            GamePreset::Definition {
+                definition,
+                install_dir,
+                runtime,
+            } => (definition, install_dir, runtime),
+        };
+
+        let model = GameModel::new(name, definition.deploy_kind);
+        let mut game = Game::add(self.db.clone(), self.cfg.clone(), model)?;
+        game.set_targets(vec![install_dir.join(&definition.data_dir)])?;
+        game.set_runtime(&runtime)?;
+        if let Some(appdata_dir) = definition.appdata_dir(&runtime) {
+            game.set_appdata_dir(appdata_dir)?;
+        }
+        game.set_implicit_masters(definition.implicit_masters.clone())?;
+        game.set_root_files(definition.root_files.clone())?;
+
+        for tool in &definition.tools {
+            let path = install_dir.join(&tool.path);
+            if path.is_file() {
+                let args: Vec<&str> = tool.args.iter().map(String::as_str).collect();
+                game.add_tool(&tool.name, &path, &args)?;
+            }
+        }
+
+        Ok(game)
     }
 
     pub fn remove_game(&self, target: Game) -> Result<()> {
@@ -62,6 +138,199 @@ impl Repository {
         Ok(Profile::current(self.db.clone(), self.cfg.clone())?)
     }
 
+    /// Deploy a [`Profile`] to its game's targets. Refuses to deploy if any of the targets is
+    /// already used by another deployment or has something mounted on it.
+    pub fn deploy(&self, profile: &Profile) -> Result<Deployment> {
+        let setup = Setup::new(profile)?;
+        self.ensure_targets_free(&setup.targets)?;
+
+        if let Some(counts) = profile.plugin_counts()?
+            && counts.exceeded()
+        {
+            warn!(
+                "{} full and {} light plugins are enabled, but the game only loads {} and {}",
+                counts.full, counts.light, counts.limit.full, counts.limit.light
+            );
+        }
+
+        let mut deployer = deployers::new_deployer(profile, &setup)?;
+        deployer.deploy()?;
+
+        self.record(profile, setup, deployer)
+    }
+
+    /// Bring a [`Deployment`] up to date with its profile's current mods. Deployments are
+    /// updated in place where the deployer supports it, so copies are only made of files that
+    /// changed. Overlay mounts, and deployments whose game settings changed since, are undone
+    /// and made again instead.
+    pub fn redeploy(&self, deployment: Deployment) -> Result<Deployment> {
+        let profile = deployment.profile()?;
+        let recorded = deployment.setup()?;
+        let setup = Setup::new(&profile)?;
+
+        let in_place = deployment.mount_points()?.is_empty()
+            && (
+                setup.kind,
+                setup.method,
+                &setup.targets,
+                &setup.appdata_dir,
+                &setup.root_files,
+            ) == (
+                recorded.kind,
+                recorded.method,
+                &recorded.targets,
+                &recorded.appdata_dir,
+                &recorded.root_files,
+            );
+        if !in_place {
+            self.undeploy(deployment)?;
+            return self.deploy(&profile);
+        }
+
+        let mut deployer = deployers::new_deployer(&profile, &setup)?;
+        if let Err(e) = deployer.deploy() {
+            // Whatever is left of the deployment is undone, and the record goes with it
+            match deployer.undeploy() {
+                Ok(()) => deployment.remove()?,
+                Err(undeploy_err) => {
+                    warn!("Failed to undo a deployment that failed to update: {undeploy_err}")
+                }
+            }
+
+            return Err(e.into());
+        }
+
+        deployment.remove()?;
+        self.record(&profile, setup, deployer)
+    }
+
+    /// Record a deployment that was just made, undoing it if that fails.
+    fn record(
+        &self,
+        profile: &Profile,
+        setup: Setup,
+        mut deployer: Box<dyn Deployer>,
+    ) -> Result<Deployment> {
+        let mount_points = deployer.mount_points();
+        match Deployment::add(
+            self.db.clone(),
+            self.cfg.clone(),
+            profile,
+            setup,
+            mount_points,
+        ) {
+            Ok(deployment) => Ok(deployment),
+            Err(e) => {
+                // Without a record the deployment couldn't be undone later, so undo it now
+                if let Err(undeploy_err) = deployer.undeploy() {
+                    warn!("Failed to undo an unrecorded deployment: {undeploy_err}");
+                }
+
+                Err(e.into())
+            }
+        }
+    }
+
+    /// Undo a [`Deployment`] the way it was made and delete its record.
+    pub fn undeploy(&self, deployment: Deployment) -> Result<()> {
+        let profile = deployment.profile()?;
+
+        deployers::new_deployer(&profile, &deployment.setup()?)?.undeploy()?;
+        deployment.remove()?;
+
+        Ok(())
+    }
+
+    pub fn deployments(&self) -> Result<Vec<Deployment>> {
+        Ok(Deployment::list(self.db.clone(), self.cfg.clone())?)
+    }
+
+    /// Returns every recorded [`Run`] of a tool, across all games
+    pub fn runs(&self) -> Result<Vec<Run>> {
+        Ok(Run::list(self.db.clone(), self.cfg.clone())?)
+    }
+
+    /// Compare the recorded deployments against the mounts that currently exist, returning
+    /// deployments left mounted by an earlier session and unknown mounts on game targets.
+    pub fn mount_issues(&self) -> Result<Vec<MountIssue>> {
+        let mounts = mounts::mounts()?;
+        let mut recorded_mount_points = Vec::new();
+        let mut issues = Vec::new();
+
+        for deployment in self.deployments()? {
+            let mount_points = deployment.mount_points()?;
+
+            if deployment.is_stale()? && is_any_mounted(&mounts, &mount_points) {
+                issues.push(MountIssue::Stale(deployment));
+            }
+
+            recorded_mount_points.extend(mount_points);
+        }
+
+        for game in self.games()? {
+            for target in game.targets()? {
+                issues.extend(
+                    mounts
+                        .iter()
+                        .filter(|m| m.mount_point.starts_with(&target))
+                        .filter(|m| !recorded_mount_points.contains(&m.mount_point))
+                        .cloned()
+                        .map(MountIssue::Foreign),
+                );
+            }
+        }
+
+        Ok(issues)
+    }
+
+    /// Cleanly unmount whatever a [`MountIssue`] refers to.
+    pub fn resolve_mount_issue(&self, issue: MountIssue) -> Result<()> {
+        match issue {
+            MountIssue::Stale(deployment) => self.undeploy(deployment),
+            MountIssue::Foreign(mount) => Ok(mounts::unmount(&mount.mount_point)?),
+        }
+    }
+
+    /// Drop records of deployments that mounted something which is no longer mounted.
+    fn reconcile_deployments(&self) -> Result<()> {
+        let mounts = mounts::mounts()?;
+
+        for deployment in self.deployments()? {
+            let mount_points = deployment.mount_points()?;
+
+            if !mount_points.is_empty() && !is_any_mounted(&mounts, &mount_points) {
+                warn!(
+                    "Dropping record of a deployment that is no longer mounted: {mount_points:?}"
+                );
+                deployment.remove()?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn ensure_targets_free(&self, targets: &[PathBuf]) -> Result<()> {
+        for deployment in self.deployments()? {
+            if let Some(target) = deployment
+                .targets()?
+                .into_iter()
+                .find(|t| targets.contains(t))
+            {
+                return Err(deployers::Error::TargetInUse(target).into());
+            }
+        }
+
+        let mounts = mounts::mounts()?;
+        if let Some(target) = targets
+            .iter()
+            .find(|t| mounts.iter().any(|m| m.mount_point.starts_with(t)))
+        {
+            return Err(deployers::Error::TargetInUse(target.clone()).into());
+        }
+
+        Ok(())
+    }
+
     #[cfg(test)]
     /// Return are mock version of a [`Repository`] with an in-memory database and configuration
     /// file.
@@ -73,6 +342,12 @@ impl Repository {
     }
 }
 
+fn is_any_mounted(mounts: &[Mount], mount_points: &[PathBuf]) -> bool {
+    mount_points
+        .iter()
+        .any(|p| mounts.iter().any(|m| &m.mount_point == p))
+}
+
 impl Default for Repository {
     fn default() -> Self {
         Self::new()
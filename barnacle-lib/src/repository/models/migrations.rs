@@ -0,0 +1,117 @@
+//! Migrations of databases created with older model versions
+//!
+//! Each version's models are kept in a module of their own, like [`super::v1`], so that it's
+//! clear what a migration starts from.
+//!
+//! agdb can't convert an element that lacks any of a model's fields, so every field added to a
+//! stored model needs a migration that fills it in on existing elements. Migrations only
+//! insert values that are missing or still in their old form, which makes them safe to run
+//! again if one is interrupted before the model version is updated.
+
+use std::path::PathBuf;
+
+use agdb::{DbAny, DbError, DbId, DbKeyValue, DbValue, QueryBuilder};
+
+use super::{DeployMethod, RuntimeKind};
+
+/// Bring the database from `version` up to [`super::CURRENT_MODEL_VERSION`].
+pub(crate) fn migrate(db: &mut DbAny, version: u64) -> Result<(), DbError> {
+    if version < 2 {
+        v1_to_v2(db)?;
+    }
+
+    Ok(())
+}
+
+/// Version 2 added deployment methods, AppData directories, implicit masters, root files and
+/// runtimes to games, and split the arguments of tools, which also gained environment variables and working
+/// directories.
+fn v1_to_v2(db: &mut DbAny) -> Result<(), DbError> {
+    insert_missing(
+        db,
+        "games",
+        vec![
+            ("deploy_method", DeployMethod::default()).into(),
+            ("appdata_dir", PathBuf::new()).into(),
+            ("implicit_masters", Vec::<String>::new()).into(),
+            ("root_files", Vec::<String>::new()).into(),
+            ("runtime", RuntimeKind::default()).into(),
+            ("runtime_path", PathBuf::new()).into(),
+            ("prefix", PathBuf::new()).into(),
+            ("steam_app_id", 0_u64).into(),
+            ("steam_dir", PathBuf::new()).into(),
+        ],
+    )?;
+
+    // Arguments used to be a single optional string, which is left out when unset
+    for id in linked_ids(db, "tools")? {
+        let args = values(db, id)?
+            .into_iter()
+            .find(|kv| kv.key == DbValue::from("args"))
+            .map(|kv| kv.value);
+
+        if let Some(DbValue::String(args)) = args {
+            let args: Vec<String> = args.split_whitespace().map(str::to_string).collect();
+            db.exec_mut(
+                QueryBuilder::insert()
+                    .values([[("args", args).into()]])
+                    .ids(id)
+                    .query(),
+            )?;
+        }
+    }
+
+    insert_missing(
+        db,
+        "tools",
+        vec![
+            ("args", Vec::<String>::new()).into(),
+            ("env_names", Vec::<String>::new()).into(),
+            ("env_values", Vec::<String>::new()).into(),
+            ("working_dir", PathBuf::new()).into(),
+            ("run_in_target", false).into(),
+        ],
+    )
+}
+
+/// Give every element linked from `alias` the values in `defaults` that it doesn't have yet.
+fn insert_missing(db: &mut DbAny, alias: &str, defaults: Vec<DbKeyValue>) -> Result<(), DbError> {
+    for id in linked_ids(db, alias)? {
+        let keys: Vec<DbValue> = values(db, id)?.into_iter().map(|kv| kv.key).collect();
+
+        let missing: Vec<DbKeyValue> = defaults
+            .iter()
+            .filter(|kv| !keys.contains(&kv.key))
+            .cloned()
+            .collect();
+
+        if !missing.is_empty() {
+            db.exec_mut(QueryBuilder::insert().values(vec![missing]).ids(id).query())?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns the IDs of the elements linked from `alias`.
+fn linked_ids(db: &DbAny, alias: &str) -> Result<Vec<DbId>, DbError> {
+    Ok(db
+        .exec(
+            QueryBuilder::search()
+                .from(alias)
+                .where_()
+                .neighbor()
+                .query(),
+        )?
+        .ids())
+}
+
+/// Returns every value stored on an element.
+fn values(db: &DbAny, id: DbId) -> Result<Vec<DbKeyValue>, DbError> {
+    Ok(db
+        .exec(QueryBuilder::select().ids(id).query())?
+        .elements
+        .into_iter()
+        .flat_map(|element| element.values)
+        .collect())
+}
@@ -7,33 +7,55 @@
 //! required for inserts. Migration between schema versions is handled
 //! internally.
 
-mod v1;
+mod migrations;
+// Older versions are only kept to describe the databases that migrations start from
+#[allow(dead_code)]
+pub(crate) mod v1;
+mod v2;
+
+pub(crate) use migrations::migrate;
 
 // Re-export current version of models
+pub(crate) mod deployments {
+    pub(crate) use super::v2::deployments::*;
+}
 pub(crate) mod games {
-    pub use super::v1::games::*;
+    pub use super::v2::games::*;
 }
 pub(crate) mod mods {
-    pub(crate) use super::v1::mods::*;
+    pub(crate) use super::v2::mods::*;
+}
+pub(crate) mod modules {
+    pub(crate) use super::v2::modules::*;
 }
 pub(crate) mod mod_entries {
-    pub(crate) use super::v1::mod_entries::*;
+    pub(crate) use super::v2::mod_entries::*;
+}
+pub(crate) mod plugin_entries {
+    pub(crate) use super::v2::plugin_entries::*;
 }
 pub(crate) mod profiles {
-    pub(crate) use super::v1::profiles::*;
+    pub(crate) use super::v2::profiles::*;
+}
+pub(crate) mod runs {
+    pub(crate) use super::v2::runs::*;
 }
 pub(crate) mod tools {
-    pub(crate) use super::v1::tools::*;
+    pub(crate) use super::v2::tools::*;
 }
 
 // Also re-export the main types at `models` level for convenience
+pub(crate) use deployments::*;
 pub(crate) use games::*;
 pub(crate) use mod_entries::*;
 pub(crate) use mods::*;
+pub(crate) use modules::*;
+pub(crate) use plugin_entries::*;
 pub(crate) use profiles::*;
+pub(crate) use runs::*;
 pub(crate) use tools::*;
 
-pub use games::DeployKind;
+pub use games::{DeployKind, DeployMethod, PluginLimit};
 
 use agdb::{DbId, DbType};
 
@@ -43,7 +65,7 @@ use agdb::{DbId, DbType};
 /// changes in a way that requires migration. It is independent of the
 /// Barnacle application version and is used solely to determine whether
 /// migrations need to be applied when initializing the database.
-pub(crate) const CURRENT_MODEL_VERSION: u64 = 1;
+pub(crate) const CURRENT_MODEL_VERSION: u64 = 2;
 
 /// Holds the model version of the local database. If this value is lower than
 /// [`CURRENT_MODEL_VERSION`], migrations will be performed until the database
@@ -0,0 +1,46 @@
+use std::path::PathBuf;
+
+use agdb::{DbId, DbType};
+
+use super::games::{DeployKind, DeployMethod};
+use crate::deployers::Setup;
+
+/// A record of a deployed profile, kept so that deployments can be recovered after a crash.
+#[derive(Debug, Clone, DbType, PartialEq, PartialOrd)]
+pub(crate) struct DeploymentModel {
+    pub(crate) db_id: Option<DbId>,
+    pub(crate) deploy_kind: DeployKind,
+    pub(crate) deploy_method: DeployMethod,
+    /// The game's resolved AppData directory at the time, empty if it had none
+    pub(crate) appdata_dir: PathBuf,
+    /// The game targets the profile was deployed to
+    pub(crate) targets: Vec<PathBuf>,
+    /// Directories a filesystem was mounted over, if the deployer mounts anything
+    pub(crate) mount_points: Vec<PathBuf>,
+    /// The directories that were layered over the targets, lowest priority first
+    pub(crate) lower_dirs: Vec<PathBuf>,
+    /// Patterns of the mod files that were deployed to the install directory
+    pub(crate) root_files: Vec<String>,
+    /// When the deployment happened, in seconds since the Unix epoch
+    pub(crate) timestamp: u64,
+    /// The ID of the process that deployed, used to tell deployments left behind by an earlier
+    /// session from ones made by the running one
+    pub(crate) pid: u64,
+}
+
+impl DeploymentModel {
+    pub fn new(setup: Setup, mount_points: Vec<PathBuf>, timestamp: u64, pid: u64) -> Self {
+        Self {
+            db_id: None,
+            deploy_kind: setup.kind,
+            deploy_method: setup.method,
+            appdata_dir: setup.appdata_dir,
+            targets: setup.targets,
+            mount_points,
+            lower_dirs: setup.mod_dirs,
+            root_files: setup.root_files,
+            timestamp,
+            pid,
+        }
+    }
+}
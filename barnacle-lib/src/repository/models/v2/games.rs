@@ -0,0 +1,166 @@
+use std::{path::PathBuf, str::FromStr};
+
+use agdb::{DbId, DbSerialize, DbType, DbValue};
+use serde::{Deserialize, Serialize, de::IntoDeserializer};
+use strum::{Display, EnumIter};
+
+#[derive(
+    Debug,
+    Clone,
+    Default,
+    DbValue,
+    DbSerialize,
+    Copy,
+    PartialEq,
+    PartialOrd,
+    Display,
+    EnumIter,
+    Serialize,
+    Deserialize,
+)]
+#[strum(serialize_all = "title_case")]
+#[serde(rename_all = "snake_case")]
+pub enum DeployKind {
+    /// Deploys directly to the game directory.
+    #[default]
+    Overlay,
+    /// Same as the overlay type, but also writes the `plugins.txt` and `loadorder.txt` of
+    /// Oblivion, Fallout 3, New Vegas and Skyrim, and orders plugins by their timestamps.
+    Gamebryo,
+    /// Same as the overlay type, but also writes the `plugins.txt` of Skyrim Special Edition,
+    /// Skyrim VR, Fallout 4 and Starfield.
+    CreationEngine,
+    /// Adds the individual mod directories to `openmw.cfg`, along with their plugins and
+    /// archives. Nothing is mounted or placed in the game directory.
+    #[strum(serialize = "OpenMW")]
+    #[serde(rename = "openmw")]
+    OpenMW,
+    /// Links `.pak` mods into the Larian user data directory and registers them in
+    /// `modsettings.lsx`. Loose files are deployed to the game's `Data` directory.
+    #[strum(serialize = "Baldur's Gate 3")]
+    #[serde(rename = "baldurs_gate_3")]
+    BaldursGate3,
+}
+
+impl DeployKind {
+    /// How many plugins the game can load at once, for the kinds that deploy plugins with a
+    /// limited number of load order slots
+    pub fn plugin_limit(self) -> Option<PluginLimit> {
+        match self {
+            Self::Gamebryo => Some(PluginLimit {
+                full: 255,
+                light: 0,
+            }),
+            // The last full slot, 0xFE, is shared by all light plugins
+            Self::CreationEngine => Some(PluginLimit {
+                full: 254,
+                light: 4096,
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// Parses the `snake_case` names kinds are serialized with, like `creation_engine`.
+impl FromStr for DeployKind {
+    type Err = serde::de::value::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::deserialize(s.into_deserializer())
+    }
+}
+
+/// The number of plugins a game can load.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PluginLimit {
+    /// Plugins that take up a load order slot of their own
+    pub full: usize,
+    /// Light plugins, which share a single slot
+    pub light: usize,
+}
+
+/// What a game runs through, stored apart from the settings of the
+/// [`Runtime`](crate::runtime::Runtime) it stands for.
+#[derive(Debug, Clone, Default, DbValue, DbSerialize, Copy, PartialEq, PartialOrd)]
+pub(crate) enum RuntimeKind {
+    #[default]
+    Native,
+    Wine,
+    Proton,
+}
+
+/// How the files of a profile's mods are put into the target directory.
+#[derive(
+    Debug,
+    Clone,
+    Default,
+    DbValue,
+    DbSerialize,
+    Copy,
+    PartialEq,
+    PartialOrd,
+    Display,
+    EnumIter,
+    Serialize,
+    Deserialize,
+)]
+#[strum(serialize_all = "title_case")]
+#[serde(rename_all = "snake_case")]
+pub enum DeployMethod {
+    /// Mounts an OverlayFS over the target. Requires Linux 5.11 or newer.
+    #[default]
+    Overlay,
+    /// Symlinks every file into the target.
+    Symlink,
+    /// Hard links every file into the target. The mods must be on the same filesystem.
+    Hardlink,
+    /// Copies every file into the target, backing up the original game files it replaces.
+    Copy,
+}
+
+#[derive(Debug, Clone, DbType, PartialEq, PartialOrd)]
+pub(crate) struct GameModel {
+    pub(crate) db_id: Option<DbId>,
+    pub(crate) name: String,
+    pub(crate) targets: Vec<PathBuf>,
+    pub(crate) deploy_kind: DeployKind,
+    pub(crate) deploy_method: DeployMethod,
+    /// The game's folder in `AppData/Local`, where plugin lists are kept, or OpenMW's
+    /// configuration directory. Empty if unset.
+    pub(crate) appdata_dir: PathBuf,
+    /// The masters the game loads on its own, in order. Empty to go by the base master found in
+    /// the target.
+    pub(crate) implicit_masters: Vec<String>,
+    /// Patterns of the files at the top of a mod that are deployed to the directory above the
+    /// primary target, the install directory, instead of to the target itself
+    pub(crate) root_files: Vec<String>,
+    pub(crate) runtime: RuntimeKind,
+    /// The Wine binary or the Proton directory, depending on the runtime
+    pub(crate) runtime_path: PathBuf,
+    /// The Wine prefix, or Proton's compat data directory
+    pub(crate) prefix: PathBuf,
+    /// Steam app ID passed to Proton
+    pub(crate) steam_app_id: u64,
+    /// The Steam installation Proton uses the runtime libraries of
+    pub(crate) steam_dir: PathBuf,
+}
+
+impl GameModel {
+    pub fn new(name: &str, deploy_kind: DeployKind) -> Self {
+        Self {
+            db_id: None,
+            name: name.to_string(),
+            targets: Vec::new(),
+            deploy_kind,
+            deploy_method: DeployMethod::default(),
+            appdata_dir: PathBuf::new(),
+            implicit_masters: Vec::new(),
+            root_files: Vec::new(),
+            runtime: RuntimeKind::default(),
+            runtime_path: PathBuf::new(),
+            prefix: PathBuf::new(),
+            steam_app_id: 0,
+            steam_dir: PathBuf::new(),
+        }
+    }
+}
@@ -0,0 +1,9 @@
+pub mod deployments;
+pub mod games;
+pub mod mod_entries;
+pub mod mods;
+pub mod modules;
+pub mod plugin_entries;
+pub mod profiles;
+pub mod runs;
+pub mod tools;
@@ -0,0 +1,8 @@
+use agdb::{DbId, DbType};
+
+#[derive(Debug, Clone, DbType, Default, PartialEq, PartialOrd)]
+pub(crate) struct ModEntryModel {
+    db_id: Option<DbId>,
+    pub(crate) enabled: bool,
+    pub(crate) notes: String,
+}
@@ -0,0 +1,64 @@
+use agdb::{DbId, DbType};
+
+use crate::formats::lsx::{Dependency, ModuleInfo};
+
+/// A module found in one of a mod's packages, for games whose mods are packaged modules such as
+/// Baldur's Gate 3. Linked from the [`super::mods::ModModel`] it belongs to.
+#[derive(Debug, Clone, DbType, PartialEq, PartialOrd)]
+pub(crate) struct ModuleModel {
+    pub(crate) db_id: Option<DbId>,
+    pub(crate) uuid: String,
+    pub(crate) folder: String,
+    pub(crate) name: String,
+    pub(crate) author: String,
+    pub(crate) description: String,
+    pub(crate) version64: i64,
+    pub(crate) md5: String,
+    // The dependencies are stored as one list per field, in the same order
+    pub(crate) dependency_uuids: Vec<String>,
+    pub(crate) dependency_folders: Vec<String>,
+    pub(crate) dependency_names: Vec<String>,
+}
+
+impl From<&ModuleInfo> for ModuleModel {
+    fn from(info: &ModuleInfo) -> Self {
+        let dependencies = &info.dependencies;
+
+        Self {
+            db_id: None,
+            uuid: info.uuid.clone(),
+            folder: info.folder.clone(),
+            name: info.name.clone(),
+            author: info.author.clone(),
+            description: info.description.clone(),
+            version64: info.version64,
+            md5: info.md5.clone(),
+            dependency_uuids: dependencies.iter().map(|d| d.uuid.clone()).collect(),
+            dependency_folders: dependencies.iter().map(|d| d.folder.clone()).collect(),
+            dependency_names: dependencies.iter().map(|d| d.name.clone()).collect(),
+        }
+    }
+}
+
+impl From<ModuleModel> for ModuleInfo {
+    fn from(model: ModuleModel) -> Self {
+        let dependencies = model
+            .dependency_uuids
+            .into_iter()
+            .zip(model.dependency_folders)
+            .zip(model.dependency_names)
+            .map(|((uuid, folder), name)| Dependency { uuid, folder, name })
+            .collect();
+
+        Self {
+            uuid: model.uuid,
+            folder: model.folder,
+            name: model.name,
+            author: model.author,
+            description: model.description,
+            version64: model.version64,
+            md5: model.md5,
+            dependencies,
+        }
+    }
+}
@@ -0,0 +1,19 @@
+use agdb::{DbId, DbType};
+
+#[derive(Debug, Clone, DbType, PartialEq, PartialOrd)]
+pub(crate) struct PluginEntryModel {
+    pub(crate) db_id: Option<DbId>,
+    /// File name of the plugin
+    pub(crate) name: String,
+    pub(crate) enabled: bool,
+}
+
+impl PluginEntryModel {
+    pub fn new(name: &str, enabled: bool) -> Self {
+        Self {
+            db_id: None,
+            name: name.to_string(),
+            enabled,
+        }
+    }
+}
@@ -0,0 +1,40 @@
+use std::path::PathBuf;
+
+use agdb::{DbId, DbType};
+
+/// A record of a launched [`Tool`](crate::repository::Tool), along with where its output was
+/// logged.
+#[derive(Debug, Clone, DbType, PartialEq, PartialOrd)]
+pub(crate) struct RunModel {
+    pub(crate) db_id: Option<DbId>,
+    /// Name of the tool at the time of the run
+    pub(crate) tool: String,
+    /// Name of the profile that was deployed
+    pub(crate) profile: String,
+    /// The file the tool's standard output and error were written to
+    pub(crate) log_path: PathBuf,
+    /// When the tool was started, in seconds since the Unix epoch
+    pub(crate) started: u64,
+    /// When the tool exited, in seconds since the Unix epoch. Zero until it does.
+    pub(crate) ended: u64,
+    /// The exit code, or [`NO_EXIT_CODE`] if the tool hasn't exited, was killed by a signal
+    /// or failed to start
+    pub(crate) exit_code: i64,
+}
+
+/// Stands in for the exit code of runs that don't have one
+pub(crate) const NO_EXIT_CODE: i64 = i64::MIN;
+
+impl RunModel {
+    pub fn new(tool: &str, profile: &str, log_path: PathBuf, started: u64) -> Self {
+        Self {
+            db_id: None,
+            tool: tool.to_string(),
+            profile: profile.to_string(),
+            log_path,
+            started,
+            ended: 0,
+            exit_code: NO_EXIT_CODE,
+        }
+    }
+}
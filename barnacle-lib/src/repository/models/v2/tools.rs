@@ -0,0 +1,37 @@
+use std::path::PathBuf;
+
+use agdb::{DbId, DbType};
+
+#[derive(Debug, Clone, DbType, PartialEq, PartialOrd)]
+pub(crate) struct ToolModel {
+    pub(crate) db_id: Option<DbId>,
+    /// A human friendly display name
+    pub(crate) name: String,
+    /// The path to the tool's executable
+    pub(crate) path: PathBuf,
+    /// Additional command-line arguments, passed as they are
+    pub(crate) args: Vec<String>,
+    /// Names of the environment variables set for the tool, in the order they're set
+    pub(crate) env_names: Vec<String>,
+    /// Values of the environment variables, matching `env_names`
+    pub(crate) env_values: Vec<String>,
+    /// The directory the tool is started in. Empty for the executable's own directory.
+    pub(crate) working_dir: PathBuf,
+    /// Whether the path and working directory are relative to the game's deployed target
+    pub(crate) run_in_target: bool,
+}
+
+impl ToolModel {
+    pub fn new(name: &str, path: PathBuf, args: &[&str]) -> Self {
+        Self {
+            db_id: None,
+            name: name.to_string(),
+            path,
+            args: args.iter().map(|a| a.to_string()).collect(),
+            env_names: Vec::new(),
+            env_values: Vec::new(),
+            working_dir: PathBuf::new(),
+            run_in_target: false,
+        }
+    }
+}
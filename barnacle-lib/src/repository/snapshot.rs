@@ -0,0 +1,163 @@
+//! Plain copies of entities, taken at one point in time.
+//!
+//! Unlike the entities, which query the database on every access, snapshots hold all of an
+//! entity's fields along with its ID and the IDs of the entities it refers to. They serialize
+//! to a stable JSON encoding for scripts and other frontends: fields keep their names and
+//! order, and enums are written in `snake_case`. Runtimes are objects with their settings,
+//! tagged by a `kind` field.
+
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    repository::{DeployKind, DeployMethod},
+    runtime::Runtime,
+};
+
+/// A snapshot of a [`Game`](crate::repository::Game)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GameSnapshot {
+    pub id: i64,
+    pub name: String,
+    pub deploy_kind: DeployKind,
+    pub deploy_method: DeployMethod,
+    pub targets: Vec<PathBuf>,
+    /// As it's stored, which can be relative to the runtime's `AppData/Local`. Empty if unset.
+    pub appdata_dir: PathBuf,
+    /// Empty if they're found from the base master in the target
+    pub implicit_masters: Vec<String>,
+    /// Patterns of the files deployed to the install directory
+    pub root_files: Vec<String>,
+    pub runtime: Runtime,
+}
+
+/// A snapshot of a [`Profile`](crate::repository::Profile)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProfileSnapshot {
+    pub id: i64,
+    pub game_id: i64,
+    pub name: String,
+    /// The profile's mod entries, in order
+    pub mod_entries: Vec<ModEntrySnapshot>,
+}
+
+/// A snapshot of a [`Mod`](crate::repository::Mod)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ModSnapshot {
+    pub id: i64,
+    pub game_id: i64,
+    pub name: String,
+    pub dir: PathBuf,
+}
+
+/// A snapshot of a [`ModEntry`](crate::repository::ModEntry)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ModEntrySnapshot {
+    pub id: i64,
+    pub mod_id: i64,
+    /// Name of the mod the entry points to
+    pub name: String,
+    pub enabled: bool,
+    pub notes: String,
+}
+
+/// A snapshot of a [`Tool`](crate::repository::Tool)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolSnapshot {
+    pub id: i64,
+    pub game_id: i64,
+    pub name: String,
+    pub path: PathBuf,
+    pub args: Vec<String>,
+    /// Environment variables as name and value pairs, in the order they're set
+    pub env: Vec<(String, String)>,
+    pub working_dir: Option<PathBuf>,
+    pub run_in_target: bool,
+    pub output_mod_id: Option<i64>,
+}
+
+/// Encode snapshots as pretty-printed JSON.
+pub fn to_json<T: Serialize + ?Sized>(value: &T) -> String {
+    serde_json::to_string_pretty(value).expect("Snapshots should always serialize to JSON")
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+
+    use serde_json::{Value, json};
+
+    use crate::Repository;
+
+    use super::*;
+
+    #[test]
+    fn test_to_json() {
+        let repo = Repository::mock();
+
+        let mut game = repo.add_game("Skyrim", DeployKind::CreationEngine).unwrap();
+        game.set_targets(vec![PathBuf::from("/games/skyrim/Data")])
+            .unwrap();
+        game.set_runtime(&Runtime::Wine {
+            binary: PathBuf::new(),
+            prefix: PathBuf::from("/prefixes/skyrim"),
+        })
+        .unwrap();
+        let mut profile = game.add_profile("Test").unwrap();
+        let mod_ = game.add_mod("SkyUI", None).unwrap();
+        let mut entry = profile.add_mod_entry(mod_.clone()).unwrap();
+        entry.set_enabled(true).unwrap();
+        let mut tool = game
+            .add_tool("SKSE", Path::new("/games/skyrim/skse64_loader.exe"), &[])
+            .unwrap();
+        tool.set_env(vec![("DXVK_HUD".to_string(), "fps".to_string())])
+            .unwrap();
+        tool.set_output_mod(Some(&mod_)).unwrap();
+
+        let game = game.snapshot().unwrap();
+        let profile = profile.snapshot().unwrap();
+        let mod_ = mod_.snapshot().unwrap();
+        let tool = tool.snapshot().unwrap();
+
+        assert_eq!(profile.game_id, game.id);
+        assert_eq!(profile.mod_entries, [entry.snapshot().unwrap()]);
+        assert_eq!(tool.output_mod_id, Some(mod_.id));
+
+        let value: Value = serde_json::from_str(&to_json(&game)).unwrap();
+        assert_eq!(
+            value,
+            json!({
+                "id": game.id,
+                "name": "Skyrim",
+                "deploy_kind": "creation_engine",
+                "deploy_method": "overlay",
+                "targets": ["/games/skyrim/Data"],
+                "appdata_dir": "",
+                "implicit_masters": [],
+                "root_files": [],
+                "runtime": {
+                    "kind": "wine",
+                    "binary": "",
+                    "prefix": "/prefixes/skyrim",
+                },
+            })
+        );
+
+        let decoded: GameSnapshot = serde_json::from_str(&to_json(&game)).unwrap();
+        assert_eq!(decoded, game);
+        assert_eq!(
+            "creation_engine".parse::<DeployKind>().unwrap(),
+            DeployKind::CreationEngine
+        );
+        assert!("creation-engine".parse::<DeployKind>().is_err());
+
+        let value: Value = serde_json::from_str(&to_json(&tool)).unwrap();
+        assert_eq!(value.get("env"), Some(&json!([["DXVK_HUD", "fps"]])));
+        assert_eq!(value.get("working_dir"), Some(&Value::Null));
+
+        let json = to_json(&[profile.clone()]);
+        let decoded: Vec<ProfileSnapshot> = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded, [profile]);
+    }
+}
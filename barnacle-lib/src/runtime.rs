@@ -0,0 +1,174 @@
+//! The compatibility layers Windows games run through on Linux.
+
+use std::{
+    env,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use serde::{Deserialize, Serialize};
+
+/// Name of the user Proton runs programs as inside its prefix
+const PROTON_USER: &str = "steamuser";
+
+/// How a game and its tools are run.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Runtime {
+    /// Run directly, for games with a Linux version
+    #[default]
+    Native,
+    /// Run through Wine
+    Wine {
+        /// The `wine` executable to use. Empty for the one on the `PATH`.
+        binary: PathBuf,
+        /// The `WINEPREFIX`. Empty for Wine's default, `~/.wine`.
+        prefix: PathBuf,
+    },
+    /// Run through one of Steam's Proton versions, outside of Steam
+    Proton {
+        /// The directory containing the `proton` script
+        proton_dir: PathBuf,
+        /// The game's directory in `steamapps/compatdata`, which holds the prefix
+        compat_data: PathBuf,
+        /// The game's Steam app ID
+        app_id: u32,
+        /// The Steam installation, whose runtime libraries Proton uses. Empty if unknown.
+        steam_dir: PathBuf,
+    },
+}
+
+impl Runtime {
+    /// The Wine prefix programs run in, which holds their `C:` drive. `None` for native games.
+    pub fn prefix(&self) -> Option<PathBuf> {
+        match self {
+            Self::Native => None,
+            Self::Wine { prefix, .. } if prefix.as_os_str().is_empty() => {
+                env::var_os("HOME").map(|home| Path::new(&home).join(".wine"))
+            }
+            Self::Wine { prefix, .. } => Some(prefix.clone()),
+            Self::Proton { compat_data, .. } => Some(compat_data.join("pfx")),
+        }
+    }
+
+    /// The `AppData/Local` directory of the user programs run as inside the prefix.
+    pub fn local_appdata_dir(&self) -> Option<PathBuf> {
+        let user = match self {
+            Self::Native => return None,
+            // Wine names the prefix's user after the one running it
+            Self::Wine { .. } => env::var("USER").unwrap_or_else(|_| "user".to_string()),
+            Self::Proton { .. } => PROTON_USER.to_string(),
+        };
+
+        Some(
+            self.prefix()?
+                .join("drive_c/users")
+                .join(user)
+                .join("AppData/Local"),
+        )
+    }
+
+    /// Build the command that runs the given executable with the given arguments.
+    pub fn command(&self, program: &Path, args: &[String]) -> Command {
+        let mut command = match self {
+            Self::Native => Command::new(program),
+            Self::Wine { binary, prefix } => {
+                let binary = if binary.as_os_str().is_empty() {
+                    Path::new("wine")
+                } else {
+                    binary.as_path()
+                };
+
+                let mut command = Command::new(binary);
+                if !prefix.as_os_str().is_empty() {
+                    command.env("WINEPREFIX", prefix);
+                }
+                command.arg(program);
+                command
+            }
+            Self::Proton {
+                proton_dir,
+                compat_data,
+                app_id,
+                steam_dir,
+            } => {
+                let mut command = Command::new(proton_dir.join("proton"));
+                command
+                    .arg("run")
+                    .arg(program)
+                    .env("STEAM_COMPAT_DATA_PATH", compat_data)
+                    .env("SteamAppId", app_id.to_string())
+                    .env("SteamGameId", app_id.to_string());
+                // Proton looks for Steam's runtime libraries there
+                if !steam_dir.as_os_str().is_empty() {
+                    command.env("STEAM_COMPAT_CLIENT_INSTALL_PATH", steam_dir);
+                }
+                command
+            }
+        };
+        command.args(args);
+
+        command
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::ffi::OsStr;
+
+    use super::*;
+
+    #[test]
+    fn test_command() {
+        let runtime = Runtime::Proton {
+            proton_dir: PathBuf::from("/steam/steamapps/common/Proton 9.0"),
+            compat_data: PathBuf::from("/library/steamapps/compatdata/489830"),
+            app_id: 489830,
+            steam_dir: PathBuf::from("/steam"),
+        };
+
+        let command = runtime.command(Path::new("/games/SkyrimSE.exe"), &["-x".to_string()]);
+        let args: Vec<&OsStr> = command.get_args().collect();
+        let envs: Vec<(&OsStr, Option<&OsStr>)> = command.get_envs().collect();
+
+        assert_eq!(
+            command.get_program(),
+            "/steam/steamapps/common/Proton 9.0/proton"
+        );
+        assert_eq!(args, ["run", "/games/SkyrimSE.exe", "-x"]);
+        assert!(envs.contains(&(
+            OsStr::new("STEAM_COMPAT_CLIENT_INSTALL_PATH"),
+            Some(OsStr::new("/steam"))
+        )));
+        assert!(envs.contains(&(OsStr::new("SteamAppId"), Some(OsStr::new("489830")))));
+        assert_eq!(
+            runtime.local_appdata_dir().unwrap(),
+            runtime
+                .prefix()
+                .unwrap()
+                .join("drive_c/users/steamuser/AppData/Local")
+        );
+
+        let runtime = Runtime::Wine {
+            binary: PathBuf::new(),
+            prefix: PathBuf::from("/prefix"),
+        };
+        let command = runtime.command(Path::new("C:/game.exe"), &[]);
+
+        assert_eq!(command.get_program(), "wine");
+        assert_eq!(
+            command.get_envs().collect::<Vec<_>>(),
+            [(OsStr::new("WINEPREFIX"), Some(OsStr::new("/prefix")))]
+        );
+
+        // Without a prefix, Wine picks its default one
+        let runtime = Runtime::Wine {
+            binary: PathBuf::new(),
+            prefix: PathBuf::new(),
+        };
+        let command = runtime.command(Path::new("C:/game.exe"), &[]);
+
+        assert_eq!(command.get_envs().count(), 0);
+        assert_eq!(Runtime::Native.prefix(), None);
+    }
+}